@@ -0,0 +1,135 @@
+#[cfg(feature = "weather")]
+use std::env;
+use tokio::task::JoinHandle;
+#[cfg(feature = "weather")]
+use crate::ext;
+
+/*
+ * RWELCOME_SSH_ANNOUNCE=1 posts a notification to ntfy (https://ntfy.sh)
+ * whenever rwelcome greets an SSH login, so logins on a server don't go
+ * unnoticed. The topic is RWELCOME_NTFY_TOPIC, and RWELCOME_NTFY_URL points
+ * at a self-hosted ntfy instead of ntfy.sh. The notification is sent in the
+ * background while everything else is acquired and rendered.
+ */
+
+/// The notification for a login by `username` on `hostname` at `time`.
+/// `ssh_client` is sshd's SSH_CLIENT: "<client ip> <client port> <server port>".
+#[cfg(feature = "weather")]
+pub fn announce_message(username: &str, hostname: &str, time: &str, ssh_client: &str) -> String {
+    match ssh_client.split_whitespace().next() {
+        Some(ip) => format!("Login: {username}@{hostname} at {time} from {ip}"),
+        None => format!("Login: {username}@{hostname} at {time}"),
+    }
+}
+
+/// The URL to post a notification for `topic` to.
+#[cfg(feature = "weather")]
+fn topic_url(topic: &str) -> String {
+    let base = ext::env_non_empty("RWELCOME_NTFY_URL").unwrap_or_else(|| "https://ntfy.sh".to_string());
+    format!("{}/{}", base.trim_end_matches('/'), topic)
+}
+
+/// Starts sending the login notification, if it's turned on, there's a
+/// topic to send it to and this is an SSH login.
+/// The returned task gives up after 3 seconds.
+#[cfg(feature = "weather")]
+pub fn spawn_ssh_announce(username: &str, hostname: &str) -> Option<JoinHandle<()>> {
+    if !ext::env_flag("RWELCOME_SSH_ANNOUNCE") {
+        return None;
+    }
+    let topic = ext::env_non_empty("RWELCOME_NTFY_TOPIC")?;
+    let ssh_client = env::var("SSH_CLIENT").ok()?;
+    let time = chrono::Local::now().format("%H:%M").to_string();
+    let message = announce_message(username, hostname, &time, &ssh_client);
+    let url = topic_url(&topic);
+    Some(tokio::task::spawn(async move {
+        let client = match reqwest::Client::builder().timeout(std::time::Duration::from_secs(3)).build() {
+            Ok(client) => client,
+            Err(err) => {
+                eprintln!("rwelcome: warning: couldn't announce the login: {err}");
+                return;
+            },
+        };
+        let result = client.post(&url).body(message).send().await
+            .and_then(|response| response.error_for_status());
+        if let Err(err) = result {
+            eprintln!("rwelcome: warning: couldn't announce the login: {err}");
+        }
+    }))
+}
+
+/// Without the network features there's nothing to send the notification with.
+#[cfg(not(feature = "weather"))]
+pub fn spawn_ssh_announce(_username: &str, _hostname: &str) -> Option<JoinHandle<()>> {
+    None
+}
+
+#[cfg(all(test, feature = "weather"))]
+mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+
+    /// Answers one HTTP request with 200 OK, giving back the request it got.
+    fn mock_ntfy(listener: TcpListener) -> std::thread::JoinHandle<String> {
+        std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = Vec::new();
+            let mut buf = [0u8; 1024];
+            loop {
+                let len = stream.read(&mut buf).unwrap();
+                if len == 0 {
+                    break;
+                }
+                request.extend_from_slice(&buf[..len]);
+                let text = String::from_utf8_lossy(&request);
+                let Some((headers, body)) = text.split_once("\r\n\r\n") else { continue };
+                let content_length = headers.lines()
+                    .find_map(|line| line.to_lowercase().strip_prefix("content-length:").map(|len| len.trim().parse().unwrap()))
+                    .unwrap_or(0);
+                if body.len() >= content_length {
+                    break;
+                }
+            }
+            stream.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\nConnection: close\r\n\r\n").unwrap();
+            String::from_utf8(request).unwrap()
+        })
+    }
+
+    #[test]
+    fn message_says_who_logged_in_from_where() {
+        assert_eq!(
+            announce_message("anthony", "web-1", "14:32", "192.168.1.5 51234 22"),
+            "Login: anthony@web-1 at 14:32 from 192.168.1.5",
+        );
+        assert_eq!(announce_message("anthony", "web-1", "14:32", ""), "Login: anthony@web-1 at 14:32");
+    }
+
+    #[test]
+    fn login_is_posted_to_the_topic() {
+        let _lock = crate::ENV_LOCK.lock().unwrap_or_else(|err| err.into_inner());
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        let server = mock_ntfy(listener);
+        env::set_var("RWELCOME_SSH_ANNOUNCE", "1");
+        env::set_var("RWELCOME_NTFY_TOPIC", "logins");
+        env::set_var("RWELCOME_NTFY_URL", &url);
+        env::set_var("SSH_CLIENT", "192.168.1.5 51234 22");
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let task = runtime.block_on(async { spawn_ssh_announce("anthony", "web-1") }).unwrap();
+        runtime.block_on(task).unwrap();
+
+        env::remove_var("RWELCOME_NTFY_TOPIC");
+        let without_topic = runtime.block_on(async { spawn_ssh_announce("anthony", "web-1").is_none() });
+        for var in ["RWELCOME_SSH_ANNOUNCE", "RWELCOME_NTFY_URL", "SSH_CLIENT"] {
+            env::remove_var(var);
+        }
+
+        let request = server.join().unwrap();
+        assert!(request.starts_with("POST /logins HTTP/1.1\r\n"), "{request}");
+        let body = request.split_once("\r\n\r\n").unwrap().1;
+        assert!(body.starts_with("Login: anthony@web-1 at "), "{body}");
+        assert!(body.ends_with(" from 192.168.1.5"), "{body}");
+        assert!(without_topic);
+    }
+}
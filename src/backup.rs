@@ -0,0 +1,195 @@
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Component, Path, PathBuf};
+use crate::config;
+use crate::environment;
+use crate::ext;
+
+/*
+ * A backup is a gzipped tar archive laid out as:
+ *
+ *   todos/<file>   every todo list (and its bookkeeping files), from the todos directory
+ *   config.toml    the config file
+ *   cache/<file>   the cache directory, e.g. the public IP
+ */
+
+/// Whether `name` is the todo list called `base_name` or a file kept next to
+/// it, e.g. `todos.work` or `todos.archive`, but not `todos-old`.
+fn is_todos_file(name: &str, base_name: &str) -> bool {
+    name == base_name || name.starts_with(&format!("{base_name}."))
+}
+
+/// The files to back up, as (path inside the archive, path on disk) pairs.
+fn backup_files(todos_base: &str) -> io::Result<Vec<(PathBuf, PathBuf)>> {
+    let mut files = Vec::new();
+    if !environment::is_remote_todos(todos_base) {
+        let base = Path::new(todos_base);
+        let dir = base.parent().unwrap_or(Path::new("."));
+        let base_name = base.file_name().and_then(|name| name.to_str()).unwrap_or("");
+        if let Ok(entries) = fs::read_dir(dir) {
+            for entry in entries {
+                let path = entry?.path();
+                let name = path.file_name().and_then(|name| name.to_str()).unwrap_or("");
+                if path.is_file() && is_todos_file(name, base_name) {
+                    files.push((Path::new("todos").join(name), path.clone()));
+                }
+            }
+        }
+    }
+    if let Some(path) = config::config_path().filter(|path| path.is_file()) {
+        files.push((PathBuf::from("config.toml"), path));
+    }
+    if let Some(Ok(entries)) = ext::cache_dir().map(fs::read_dir) {
+        for entry in entries {
+            let path = entry?.path();
+            if let (true, Some(name)) = (path.is_file(), path.file_name()) {
+                files.push((Path::new("cache").join(name), path.clone()));
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Where a file from the archive belongs on disk, or None if it isn't one
+/// rwelcome puts in its backups (including anything trying to escape with `..`).
+fn restore_destination(archive_path: &Path, todos_base: &str) -> Option<PathBuf> {
+    let parts: Vec<&str> = archive_path.components()
+        .map(|component| match component {
+            Component::Normal(part) => part.to_str(),
+            _ => None,
+        })
+        .collect::<Option<_>>()?;
+    match parts.as_slice() {
+        ["todos", name] if !environment::is_remote_todos(todos_base) => {
+            let base = Path::new(todos_base);
+            is_todos_file(name, base.file_name()?.to_str()?)
+                .then(|| base.parent().unwrap_or(Path::new(".")).join(name))
+        },
+        ["config.toml"] => config::config_path(),
+        ["cache", name] => Some(ext::cache_dir()?.join(name)),
+        _ => None,
+    }
+}
+
+/// Writes a backup of all of rwelcome's data to `out`, returning how many files it holds.
+pub fn backup(out: impl Write, todos_base: &str) -> io::Result<usize> {
+    let files = backup_files(todos_base)?;
+    let mut archive = tar::Builder::new(GzEncoder::new(out, Compression::default()));
+    for (archive_path, path) in &files {
+        archive.append_path_with_name(path, archive_path)?;
+    }
+    archive.into_inner()?.finish()?.flush()?;
+    Ok(files.len())
+}
+
+/// Restores the backup at `archive_path`. The whole archive is checked before
+/// anything is written, and if any files would be overwritten the user is asked first.
+/// Returns how many files were restored, or None if the user said no.
+pub fn restore(archive_path: &str, todos_base: &str) -> io::Result<Option<usize>> {
+    let mut files = Vec::<(PathBuf, Vec<u8>)>::new();
+    let mut archive = tar::Archive::new(GzDecoder::new(fs::File::open(archive_path)?));
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let path = entry.path()?.into_owned();
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        let destination = match restore_destination(&path, todos_base) {
+            Some(destination) => destination,
+            None => return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("rwelcome: error: {} doesn't belong in an rwelcome backup.", path.display()),
+            )),
+        };
+        let mut contents = Vec::new();
+        entry.read_to_end(&mut contents)?;
+        files.push((destination, contents));
+    }
+
+    let existing: Vec<&PathBuf> = files.iter().map(|(path, _)| path).filter(|path| path.exists()).collect();
+    if !existing.is_empty() {
+        for path in &existing {
+            println!("  {}", path.display());
+        }
+        print!("Overwrite the existing file(s) above? (y/n)> ");
+        io::stdout().flush()?;
+        let mut input = String::new();
+        io::stdin().read_line(&mut input)?;
+        if !input.trim().to_lowercase().starts_with("y") {
+            return Ok(None);
+        }
+    }
+    for (path, contents) in &files {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, contents)?;
+    }
+    Ok(Some(files.len()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    #[test]
+    fn backups_restore_todo_lists_config_and_cache() {
+        let _lock = crate::ENV_LOCK.lock().unwrap_or_else(|err| err.into_inner());
+        let root = tempfile::tempdir().unwrap();
+        let old_home = env::var_os("HOME");
+        env::set_var("HOME", root.path());
+        env::remove_var("XDG_CONFIG_HOME");
+        env::remove_var("XDG_CACHE_HOME");
+        env::remove_var("RWELCOME_CONFIG_PATH");
+
+        let todos_dir = root.path().join("todos");
+        let todos_base = todos_dir.join("todos");
+        let config = root.path().join(".config/rwelcome/config.toml");
+        let cache = root.path().join(".cache/rwelcome/public_ip.txt");
+        let files = [
+            (todos_base.clone(), "buy milk"),
+            (todos_dir.join("todos.work"), "send report"),
+            (todos_dir.join("todos.archive"), "2026-01-02\twater plants"),
+            (config.clone(), "[env]\n"),
+            (cache.clone(), "192.0.2.1"),
+        ];
+        for (path, contents) in &files {
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, contents).unwrap();
+        }
+        // Not part of the list, just named like it.
+        fs::write(todos_dir.join("todos-old"), "stale").unwrap();
+
+        let todos_base = todos_base.to_str().unwrap();
+        let archive = root.path().join("backup.tar.gz");
+        let count = backup(fs::File::create(&archive).unwrap(), todos_base).unwrap();
+        for (path, _) in &files {
+            fs::remove_file(path).unwrap();
+        }
+        let restored = restore(archive.to_str().unwrap(), todos_base);
+        let all_back = files.iter().all(|(path, contents)| fs::read_to_string(path).ok().as_deref() == Some(contents));
+
+        match old_home {
+            Some(home) => env::set_var("HOME", home),
+            None => env::remove_var("HOME"),
+        }
+        assert_eq!(count, files.len());
+        assert_eq!(restored.unwrap(), Some(files.len()));
+        assert!(all_back);
+    }
+
+    #[test]
+    fn restore_only_accepts_files_from_a_backup() {
+        let base = "/home/me/.local/share/rwelcome/todos";
+        let dir = Path::new("/home/me/.local/share/rwelcome");
+        assert_eq!(restore_destination(Path::new("todos/todos.work"), base), Some(dir.join("todos.work")));
+        assert_eq!(restore_destination(Path::new("todos/todos-old"), base), None);
+        assert_eq!(restore_destination(Path::new("todos/../../.bashrc"), base), None);
+        assert_eq!(restore_destination(Path::new("/etc/passwd"), base), None);
+    }
+}
@@ -0,0 +1,215 @@
+use clap::{Command, ValueEnum};
+use std::env;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+/*
+ * Completion scripts are generated by clap_complete from the command line
+ * definition, so they always match it, global flags included. Each starts
+ * with a comment giving the version it's for, so installing it again can
+ * tell whether the installed one is current.
+ */
+
+/// The shells rwelcome can complete in.
+#[derive(Clone, Copy, ValueEnum)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+impl Shell {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Shell::Bash => "bash",
+            Shell::Zsh => "zsh",
+            Shell::Fish => "fish",
+        }
+    }
+
+    fn generator(self) -> clap_complete::Shell {
+        match self {
+            Shell::Bash => clap_complete::Shell::Bash,
+            Shell::Zsh => clap_complete::Shell::Zsh,
+            Shell::Fish => clap_complete::Shell::Fish,
+        }
+    }
+}
+
+/// The comment that starts a script generated for `version` of `name`.
+fn version_comment(shell: Shell, name: &str, version: &str) -> String {
+    format!("# {} completions for {} {}", shell.name(), name, version)
+}
+
+/// Generates the completion script for `shell` from `cmd`.
+pub fn generate(shell: Shell, cmd: &mut Command) -> String {
+    let name = cmd.get_name().to_string();
+    let comment = version_comment(shell, &name, cmd.get_version().unwrap_or(""));
+    let mut script = Vec::new();
+    clap_complete::generate(shell.generator(), cmd, &name, &mut script);
+    let script = String::from_utf8_lossy(&script);
+    // zsh needs its #compdef line first.
+    match script.split_once('\n') {
+        Some((compdef, rest)) if compdef.starts_with("#compdef") => format!("{compdef}\n{comment}\n{rest}"),
+        _ => format!("{comment}\n{script}"),
+    }
+}
+
+/// The version an installed script was generated for, from its comment.
+fn installed_version(shell: Shell, name: &str, script: &str) -> Option<String> {
+    let prefix = version_comment(shell, name, "");
+    script.lines().take(2).find_map(|line| line.strip_prefix(&prefix).map(str::to_string))
+}
+
+/// The directories to install `shell`'s completions for `name` in, most preferred
+/// first, with the file name each needs and whether it's the per-user fallback.
+fn install_targets(shell: Shell, name: &str) -> Vec<(PathBuf, String, bool)> {
+    let home = env::var("HOME").map(PathBuf::from).unwrap_or_default();
+    let data_home = match env::var("XDG_DATA_HOME") {
+        Ok(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => home.join(".local/share"),
+    };
+    match shell {
+        Shell::Bash => {
+            let user_dir = match env::var("BASH_COMPLETION_USER_DIR") {
+                Ok(dir) if !dir.is_empty() => PathBuf::from(dir).join("completions"),
+                _ => data_home.join("bash-completion/completions"),
+            };
+            vec![
+                (PathBuf::from("/etc/bash_completion.d"), name.to_string(), false),
+                (user_dir, name.to_string(), true),
+            ]
+        },
+        Shell::Zsh => {
+            // zsh only exports $FPATH when asked to, so this is often empty.
+            let mut targets: Vec<_> = env::var("FPATH").unwrap_or_default()
+                .split(':')
+                .filter(|dir| !dir.is_empty())
+                .map(|dir| (PathBuf::from(dir), format!("_{name}"), false))
+                .collect();
+            targets.push((data_home.join("zsh/site-functions"), format!("_{name}"), true));
+            targets
+        },
+        Shell::Fish => {
+            let config_home = match env::var("XDG_CONFIG_HOME") {
+                Ok(dir) if !dir.is_empty() => PathBuf::from(dir),
+                _ => home.join(".config"),
+            };
+            vec![(config_home.join("fish/completions"), format!("{name}.fish"), true)]
+        },
+    }
+}
+
+/// What to do after installing `shell`'s completions at `path`.
+fn instructions(shell: Shell, path: &std::path::Path, fallback: bool) -> String {
+    let dir = path.parent().unwrap_or(path).display();
+    match (shell, fallback) {
+        (Shell::Bash, true) => format!(
+            "bash-completion loads completions from {dir} in new shells. If you don't use \
+             bash-completion, add `source {}` to ~/.bashrc; if you've moved its user directory, \
+             point $BASH_COMPLETION_USER_DIR at it.",
+            path.display(),
+        ),
+        (Shell::Zsh, true) => format!(
+            "Add `fpath=({dir} $fpath)` to ~/.zshrc, before `compinit` runs, then open a new shell."
+        ),
+        _ => "Open a new shell to use them.".to_string(),
+    }
+}
+
+/// Installs `script`, the completions for `shell` generated for `version`, where
+/// the shell will find them. System directories are tried first, and only if they
+/// already exist; when they can't be written to, the per-user directory is created
+/// and used instead. A script that's already installed for the same version is
+/// left as it is. Returns what was done and what to do next, for printing.
+pub fn install(shell: Shell, name: &str, version: &str, script: &str) -> io::Result<String> {
+    let mut skipped = Vec::new();
+    for (dir, file_name, fallback) in install_targets(shell, name) {
+        if fallback {
+            fs::create_dir_all(&dir)?;
+        } else if !dir.is_dir() {
+            continue;
+        }
+        let path = dir.join(file_name);
+        let installed = fs::read_to_string(&path).ok()
+            .and_then(|installed| installed_version(shell, name, &installed));
+        if installed.as_deref() == Some(version) {
+            return Ok(format!(
+                "The {} completions at {} are already up to date.\n{}",
+                shell.name(),
+                path.display(),
+                instructions(shell, &path, fallback),
+            ));
+        }
+        match fs::write(&path, script) {
+            Ok(()) => {
+                let mut message = String::new();
+                for (dir, err) in skipped {
+                    message.push_str(&format!("Couldn't write to {} ({err}), so they're installed for you only.\n", dir));
+                }
+                let done = match installed {
+                    Some(installed) => format!("Updated the {} completions at {} from {installed}", shell.name(), path.display()),
+                    None => format!("Installed the {} completions to {}", shell.name(), path.display()),
+                };
+                message.push_str(&format!("{done}.\n{}", instructions(shell, &path, fallback)));
+                return Ok(message);
+            },
+            Err(err) if err.kind() == io::ErrorKind::PermissionDenied && !fallback => {
+                skipped.push((dir.display().to_string(), err));
+            },
+            Err(err) => return Err(err),
+        }
+    }
+    Err(io::Error::new(io::ErrorKind::NotFound, "there's nowhere to install them"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+
+    #[test]
+    fn global_flags_are_completed_after_subcommands() {
+        let script = generate(Shell::Bash, &mut crate::Cli::command());
+        // The options offered once `edit` has been seen, e.g. in `rwelcome --list work edit <TAB>`.
+        let opts = script.lines()
+            .skip_while(|line| line.trim() != "rwelcome__subcmd__edit)")
+            .find(|line| line.trim_start().starts_with("opts="))
+            .unwrap();
+        assert!(opts.contains("--list"), "{opts}");
+        assert!(opts.contains("add done"), "{opts}");
+    }
+
+    #[test]
+    fn scripts_start_with_their_version() {
+        let zsh = generate(Shell::Zsh, &mut crate::Cli::command());
+        let mut lines = zsh.lines();
+        assert_eq!(lines.next(), Some("#compdef rwelcome"));
+        assert_eq!(lines.next().map(str::to_string), Some(version_comment(Shell::Zsh, "rwelcome", env!("CARGO_PKG_VERSION"))));
+        let fish = generate(Shell::Fish, &mut crate::Cli::command());
+        assert_eq!(installed_version(Shell::Fish, "rwelcome", &fish).as_deref(), Some(env!("CARGO_PKG_VERSION")));
+    }
+
+    #[test]
+    fn installing_the_same_version_again_changes_nothing() {
+        let _lock = crate::ENV_LOCK.lock().unwrap_or_else(|err| err.into_inner());
+        let dir = tempfile::tempdir().unwrap();
+        env::set_var("XDG_CONFIG_HOME", dir.path());
+        let path = dir.path().join("fish/completions/rwelcome.fish");
+        let old = format!("{}\ncomplete -c rwelcome -f\n", version_comment(Shell::Fish, "rwelcome", "0.0.9"));
+        let new = format!("{}\ncomplete -c rwelcome -f -a show\n", version_comment(Shell::Fish, "rwelcome", "0.1.0"));
+
+        let first = install(Shell::Fish, "rwelcome", "0.0.9", &old);
+        let again = install(Shell::Fish, "rwelcome", "0.0.9", "# something else\n");
+        let kept = fs::read_to_string(&path);
+        let updated = install(Shell::Fish, "rwelcome", "0.1.0", &new);
+        env::remove_var("XDG_CONFIG_HOME");
+
+        assert!(first.unwrap().starts_with("Installed the fish completions"));
+        assert!(again.unwrap().contains("already up to date"));
+        assert_eq!(kept.unwrap(), old);
+        assert!(updated.unwrap().contains("from 0.0.9"));
+        assert_eq!(fs::read_to_string(&path).unwrap(), new);
+    }
+}
@@ -0,0 +1,489 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use crate::ext;
+use crate::theme::{self, Theme};
+
+/// The layout of the TOML config file. Every section is optional.
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct ConfigFile {
+    weather: WeatherSection,
+    /// Values for RWELCOME_* environment variables that aren't set, from [env].
+    env: BTreeMap<String, String>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct WeatherSection {
+    icons: HashMap<String, String>,
+}
+
+/// Where a setting's value came from.
+#[derive(Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ConfigSource {
+    Default,
+    File,
+    Env,
+}
+
+impl ConfigSource {
+    pub fn describe(self) -> &'static str {
+        match self {
+            ConfigSource::Default => "default",
+            ConfigSource::File    => "from config file",
+            ConfigSource::Env     => "from env",
+        }
+    }
+}
+
+/// The units to show the weather in.
+#[derive(Clone, Copy, PartialEq)]
+pub enum WeatherUnits {
+    /// °C, km/h, mb, mm and km.
+    Metric,
+    /// °F, mph, inches and miles.
+    Imperial,
+    /// K, m/s, hPa, mm and km.
+    Si,
+}
+
+impl WeatherUnits {
+    pub fn name(self) -> &'static str {
+        match self {
+            WeatherUnits::Metric   => "metric",
+            WeatherUnits::Imperial => "imperial",
+            WeatherUnits::Si       => "si",
+        }
+    }
+}
+
+/// Settings read from the config file and the environment.
+pub struct Config {
+    /// Weather icons keyed by condition substring, from [weather.icons].
+    pub weather_icons: HashMap<String, String>,
+    /// The weather API key, from RWELCOME_WEATHER_API_KEY. No key means no weather.
+    pub weather_api_key: Option<String>,
+    /// Which weather service to use: "weatherapi", "openweathermap" or
+    /// "openmeteo" (which needs no key), from RWELCOME_WEATHER_PROVIDER.
+    pub weather_provider: String,
+    /// The units to show the weather in, from RWELCOME_WEATHER_UNITS.
+    pub weather_units: WeatherUnits,
+    /// The weather location's coordinates, when RWELCOME_WEATHER_LOCATION
+    /// is given as e.g. "lat:50.8225,lon:-0.1372" rather than a place name.
+    pub lat: Option<f64>,
+    pub lon: Option<f64>,
+    /// The name to show instead of the hostname, from RWELCOME_HOSTNAME_ALIAS.
+    pub hostname_alias: Option<String>,
+    /// Label text for each section, overridable with RWELCOME_LABEL_*.
+    pub label_uptime: String,
+    pub label_memory: String,
+    pub label_kernel: String,
+    pub label_cpu_temp: String,
+    pub label_weather: String,
+    pub label_todos: String,
+    /// The colors to draw with, from RWELCOME_COLOR_SCHEME and RWELCOME_COLOR_*.
+    pub theme: Theme,
+    /// Where each setting above came from, keyed by field name. Missing means the default.
+    pub sources: HashMap<&'static str, ConfigSource>,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            weather_icons: HashMap::new(),
+            weather_api_key: None,
+            weather_provider: "weatherapi".to_string(),
+            weather_units: WeatherUnits::Metric,
+            lat: None,
+            lon: None,
+            hostname_alias: None,
+            label_uptime: "Uptime".to_string(),
+            label_memory: "Memory".to_string(),
+            label_kernel: "Kernel".to_string(),
+            label_cpu_temp: "CPU temp".to_string(),
+            label_weather: "Weather".to_string(),
+            label_todos: "Todos".to_string(),
+            theme: Theme::default(),
+            sources: HashMap::new(),
+        }
+    }
+}
+
+/// Reads the label override in `var`, falling back to `default`.
+/// Labels are printed verbatim, so newlines and escape sequences are rejected
+/// with a warning rather than being allowed to mangle the output.
+fn env_label(var: &str, default: String) -> String {
+    match ext::env_non_empty(var) {
+        Some(label) if label.chars().any(|c| c.is_control()) => {
+            eprintln!("rwelcome: warning: {var} contains control characters, using default {default}");
+            default
+        },
+        Some(label) => label,
+        None => default,
+    }
+}
+
+/// Whether `var`, set in the environment or the config file's [env] section, is
+/// what `resolved` came from, rather than it being the default because `var`
+/// is unset or held an invalid value.
+fn env_source(var: &str, resolved: &str) -> ConfigSource {
+    match env::var(var) {
+        Ok(value) if value.trim().eq_ignore_ascii_case(resolved.trim()) => var_source(var),
+        _ => ConfigSource::Default,
+    }
+}
+
+/// The location of the config file: RWELCOME_CONFIG_PATH if set, otherwise
+/// rwelcome/config.toml inside $XDG_CONFIG_HOME (or ~/.config).
+pub fn config_path() -> Option<PathBuf> {
+    if let Some(path) = ext::env_non_empty("RWELCOME_CONFIG_PATH") {
+        return Some(PathBuf::from(path));
+    }
+    let config_dir = match env::var("XDG_CONFIG_HOME") {
+        Ok(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(env::var("HOME").ok()?).join(".config"),
+    };
+    Some(config_dir.join("rwelcome").join("config.toml"))
+}
+
+/// Parses a location given as coordinates, e.g. "lat:50.8225,lon:-0.1372",
+/// into a latitude and longitude. Returns None for anything else, such as a place name.
+pub fn parse_coordinates(location: &str) -> Option<(f64, f64)> {
+    let (lat, lon) = location.split_once(',')?;
+    let lat: f64 = lat.trim().strip_prefix("lat:")?.trim().parse().ok()?;
+    let lon: f64 = lon.trim().strip_prefix("lon:")?.trim().parse().ok()?;
+    let valid = (-90.0..=90.0).contains(&lat) && (-180.0..=180.0).contains(&lon);
+    valid.then_some((lat, lon))
+}
+
+/// Reads and parses the config file at `path`, giving None if there isn't one.
+fn read_file(path: &Path) -> Result<Option<ConfigFile>, String> {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(format!("couldn't read {}: {}", path.display(), err)),
+    };
+    match toml::from_str(&contents) {
+        Ok(file) => Ok(Some(file)),
+        Err(err) => Err(format!("couldn't parse {}: {}", path.display(), err)),
+    }
+}
+
+/// Checks that the config file, if there is one, can be read and parsed.
+/// Returns the path of the file that was checked.
+pub fn check_file() -> Result<Option<PathBuf>, String> {
+    let path = match config_path() {
+        Some(path) => path,
+        None => return Ok(None),
+    };
+    match read_file(&path)? {
+        Some(_) => Ok(Some(path)),
+        None => Ok(None),
+    }
+}
+
+/// Reads the config file. A missing file simply gives the defaults,
+/// while an unreadable or malformed one is reported on stderr first.
+fn load_file() -> ConfigFile {
+    let path = match config_path() {
+        Some(path) => path,
+        None => return ConfigFile::default(),
+    };
+    match read_file(&path) {
+        Ok(file) => file.unwrap_or_default(),
+        Err(err) => {
+            eprintln!("rwelcome: warning: {err}");
+            ConfigFile::default()
+        },
+    }
+}
+
+/// The config file's [env] section, without applying it. Empty if there's
+/// no file or it can't be read.
+pub fn file_env() -> BTreeMap<String, String> {
+    match config_path().map(|path| read_file(&path)) {
+        Some(Ok(Some(file))) => file.env,
+        _ => BTreeMap::new(),
+    }
+}
+
+/// The variables `apply_env_defaults()` set from the config file's [env] section.
+static FILE_VARS: OnceLock<Vec<String>> = OnceLock::new();
+
+/// Sets the environment variables given in the config file's [env] section,
+/// except those already set, so the real environment always wins.
+/// Problems with the file are left for `Config::load()` to report.
+///
+/// This must be called before any other threads are started, since setting
+/// a variable while another thread reads the environment isn't safe.
+pub fn apply_env_defaults() {
+    let mut set = Vec::new();
+    for (var, value) in file_env() {
+        if env::var_os(&var).is_none() {
+            env::set_var(&var, value);
+            set.push(var);
+        }
+    }
+    let _ = FILE_VARS.set(set);
+}
+
+/// Where the value of the set variable `var` came from: the config file's
+/// [env] section, or the environment.
+fn var_source(var: &str) -> ConfigSource {
+    match FILE_VARS.get() {
+        Some(vars) if vars.iter().any(|file_var| file_var == var) => ConfigSource::File,
+        _ => ConfigSource::Env,
+    }
+}
+
+impl Config {
+    /// Loads the config file, then applies any overrides from the environment.
+    pub fn load() -> Config {
+        let file = load_file();
+        let defaults = Config::default();
+        let coordinates = env::var("RWELCOME_WEATHER_LOCATION").ok()
+            .and_then(|location| parse_coordinates(&location));
+        let mut config = Config {
+            weather_icons: file.weather.icons,
+            weather_api_key: ext::env_non_empty("RWELCOME_WEATHER_API_KEY"),
+            weather_provider: ext::env_one_of(
+                "RWELCOME_WEATHER_PROVIDER",
+                &["weatherapi", "openweathermap", "openmeteo"],
+                &defaults.weather_provider,
+            ),
+            weather_units: match ext::env_one_of("RWELCOME_WEATHER_UNITS", &["metric", "imperial", "si"], "metric").as_str() {
+                "imperial" => WeatherUnits::Imperial,
+                "si" => WeatherUnits::Si,
+                _ => WeatherUnits::Metric,
+            },
+            lat: coordinates.map(|(lat, _)| lat),
+            lon: coordinates.map(|(_, lon)| lon),
+            hostname_alias: ext::env_non_empty("RWELCOME_HOSTNAME_ALIAS"),
+            label_uptime: env_label("RWELCOME_LABEL_UPTIME", defaults.label_uptime),
+            label_memory: env_label("RWELCOME_LABEL_MEMORY", defaults.label_memory),
+            label_kernel: env_label("RWELCOME_LABEL_KERNEL", defaults.label_kernel),
+            label_cpu_temp: env_label("RWELCOME_LABEL_CPU_TEMP", defaults.label_cpu_temp),
+            label_weather: env_label("RWELCOME_LABEL_WEATHER", defaults.label_weather),
+            label_todos: env_label("RWELCOME_LABEL_TODOS", defaults.label_todos),
+            theme: theme::load_theme(),
+            sources: HashMap::new(),
+        };
+
+        let mut sources = HashMap::new();
+        if !config.weather_icons.is_empty() {
+            sources.insert("weather_icons", ConfigSource::File);
+        }
+        if config.weather_api_key.is_some() {
+            sources.insert("weather_api_key", var_source("RWELCOME_WEATHER_API_KEY"));
+        }
+        if coordinates.is_some() {
+            sources.insert("lat", var_source("RWELCOME_WEATHER_LOCATION"));
+            sources.insert("lon", var_source("RWELCOME_WEATHER_LOCATION"));
+        }
+        if config.hostname_alias.is_some() {
+            sources.insert("hostname_alias", var_source("RWELCOME_HOSTNAME_ALIAS"));
+        }
+        sources.insert("weather_provider", env_source("RWELCOME_WEATHER_PROVIDER", &config.weather_provider));
+        sources.insert("weather_units", env_source("RWELCOME_WEATHER_UNITS", config.weather_units.name()));
+        sources.insert("label_uptime", env_source("RWELCOME_LABEL_UPTIME", &config.label_uptime));
+        sources.insert("label_memory", env_source("RWELCOME_LABEL_MEMORY", &config.label_memory));
+        sources.insert("label_kernel", env_source("RWELCOME_LABEL_KERNEL", &config.label_kernel));
+        sources.insert("label_cpu_temp", env_source("RWELCOME_LABEL_CPU_TEMP", &config.label_cpu_temp));
+        sources.insert("label_weather", env_source("RWELCOME_LABEL_WEATHER", &config.label_weather));
+        sources.insert("label_todos", env_source("RWELCOME_LABEL_TODOS", &config.label_todos));
+        config.sources = sources;
+        config
+    }
+
+    fn source(&self, field: &str) -> ConfigSource {
+        self.sources.get(field).copied().unwrap_or(ConfigSource::Default)
+    }
+
+    /// The top-level settings as field names and values, with None for unset ones.
+    /// The API key is masked, so the output can be shared when asking for help.
+    fn settings(&self) -> Vec<(&'static str, Option<toml::Value>)> {
+        let string = |value: &str| Some(toml::Value::String(value.to_string()));
+        vec![
+            ("weather_api_key", self.weather_api_key.as_ref().and_then(|_| string("********"))),
+            ("weather_provider", string(&self.weather_provider)),
+            ("weather_units", string(self.weather_units.name())),
+            ("lat", self.lat.map(toml::Value::Float)),
+            ("lon", self.lon.map(toml::Value::Float)),
+            ("hostname_alias", self.hostname_alias.as_deref().and_then(string)),
+            ("label_uptime", string(&self.label_uptime)),
+            ("label_memory", string(&self.label_memory)),
+            ("label_kernel", string(&self.label_kernel)),
+            ("label_cpu_temp", string(&self.label_cpu_temp)),
+            ("label_weather", string(&self.label_weather)),
+            ("label_todos", string(&self.label_todos)),
+        ]
+    }
+
+    /// The top-level settings as the [env] entries that give them, keyed by
+    /// the variable, with where each came from. None is for unset ones.
+    fn env_settings(&self) -> Vec<(&'static str, Option<String>, ConfigSource)> {
+        let location = match (self.lat, self.lon) {
+            (Some(lat), Some(lon)) => Some(format!("lat:{lat},lon:{lon}")),
+            _ => None,
+        };
+        vec![
+            ("RWELCOME_WEATHER_API_KEY", self.weather_api_key.as_ref().map(|_| "********".to_string()), self.source("weather_api_key")),
+            ("RWELCOME_WEATHER_PROVIDER", Some(self.weather_provider.clone()), self.source("weather_provider")),
+            ("RWELCOME_WEATHER_UNITS", Some(self.weather_units.name().to_string()), self.source("weather_units")),
+            ("RWELCOME_WEATHER_LOCATION", location, self.source("lat")),
+            ("RWELCOME_HOSTNAME_ALIAS", self.hostname_alias.clone(), self.source("hostname_alias")),
+            ("RWELCOME_LABEL_UPTIME", Some(self.label_uptime.clone()), self.source("label_uptime")),
+            ("RWELCOME_LABEL_MEMORY", Some(self.label_memory.clone()), self.source("label_memory")),
+            ("RWELCOME_LABEL_KERNEL", Some(self.label_kernel.clone()), self.source("label_kernel")),
+            ("RWELCOME_LABEL_CPU_TEMP", Some(self.label_cpu_temp.clone()), self.source("label_cpu_temp")),
+            ("RWELCOME_LABEL_WEATHER", Some(self.label_weather.clone()), self.source("label_weather")),
+            ("RWELCOME_LABEL_TODOS", Some(self.label_todos.clone()), self.source("label_todos")),
+        ]
+    }
+
+    /// The resolved configuration as TOML, with where each value came from in a
+    /// comment. Saved as the config file, it gives the same configuration: the
+    /// settings go under [env], as the variables they're read from. The masked
+    /// API key is commented out, so it can't replace the real one.
+    pub fn to_toml(&self) -> String {
+        let mut out = String::from("# The resolved rwelcome configuration.\n[env]\n");
+        for (var, value, source) in self.env_settings() {
+            let source = source.describe();
+            match value {
+                Some(value) if var == "RWELCOME_WEATHER_API_KEY" => {
+                    out.push_str(&format!("# {var} = {}  # {source}\n", toml::Value::String(value)));
+                },
+                Some(value) => out.push_str(&format!("{var} = {}  # {source}\n", toml::Value::String(value))),
+                None => out.push_str(&format!("# {var} is not set  # {source}\n")),
+            }
+        }
+        out.push_str("\n[weather.icons]\n");
+        let mut icons: Vec<_> = self.weather_icons.iter().collect();
+        icons.sort();
+        for (condition, icon) in icons {
+            out.push_str(&format!(
+                "{} = {}  # {}\n",
+                toml::Value::String(condition.clone()),
+                toml::Value::String(icon.clone()),
+                self.source("weather_icons").describe(),
+            ));
+        }
+        out
+    }
+
+    /// The resolved configuration as JSON, mapping each field to its value and source.
+    pub fn to_json(&self) -> serde_json::Value {
+        let mut fields = serde_json::Map::new();
+        for (field, value) in self.settings() {
+            fields.insert(field.to_string(), serde_json::json!({
+                "value": value,
+                "source": self.source(field),
+            }));
+        }
+        fields.insert("weather_icons".to_string(), serde_json::json!({
+            "value": self.weather_icons,
+            "source": self.source("weather_icons"),
+        }));
+        serde_json::Value::Object(fields)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn printed_config_loads_back() {
+        let config = Config {
+            weather_api_key: Some("secret".to_string()),
+            lat: Some(50.8225),
+            lon: Some(-0.1372),
+            label_todos: "Tasks".to_string(),
+            ..Config::default()
+        };
+        let file: ConfigFile = toml::from_str(&config.to_toml()).unwrap();
+        assert_eq!(file.env.get("RWELCOME_LABEL_TODOS").map(String::as_str), Some("Tasks"));
+        assert_eq!(file.env.get("RWELCOME_WEATHER_PROVIDER").map(String::as_str), Some("weatherapi"));
+        let location = file.env.get("RWELCOME_WEATHER_LOCATION").unwrap();
+        assert_eq!(parse_coordinates(location), Some((50.8225, -0.1372)));
+        // The masked key mustn't end up replacing the real one.
+        assert!(!file.env.contains_key("RWELCOME_WEATHER_API_KEY"));
+    }
+
+    #[test]
+    fn env_section_values_are_from_the_config_file() {
+        let _lock = crate::ENV_LOCK.lock().unwrap_or_else(|err| err.into_inner());
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        fs::write(&path, "[env]\nRWELCOME_LABEL_KERNEL = \"Core\"\nRWELCOME_LABEL_MEMORY = \"RAM\"\n").unwrap();
+        env::set_var("RWELCOME_CONFIG_PATH", &path);
+        env::set_var("RWELCOME_LABEL_MEMORY", "Mem");
+        apply_env_defaults();
+        let config = Config::load();
+        for var in ["RWELCOME_CONFIG_PATH", "RWELCOME_LABEL_MEMORY", "RWELCOME_LABEL_KERNEL"] {
+            env::remove_var(var);
+        }
+
+        assert_eq!(config.label_kernel, "Core");
+        assert!(config.source("label_kernel") == ConfigSource::File);
+        // The environment wins over the file.
+        assert_eq!(config.label_memory, "Mem");
+        assert!(config.source("label_memory") == ConfigSource::Env);
+    }
+
+    #[test]
+    fn file_env_is_read_without_being_applied() {
+        let _lock = crate::ENV_LOCK.lock().unwrap_or_else(|err| err.into_inner());
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        fs::write(&path, "[env]\nRWELCOME_TODOS_PATH = \"/srv/todos\"\n").unwrap();
+        env::set_var("RWELCOME_CONFIG_PATH", &path);
+        env::remove_var("RWELCOME_TODOS_PATH");
+        let file_vars = file_env();
+        let applied = env::var_os("RWELCOME_TODOS_PATH");
+        env::set_var("RWELCOME_CONFIG_PATH", dir.path().join("missing.toml"));
+        let missing = file_env();
+        env::remove_var("RWELCOME_CONFIG_PATH");
+        assert_eq!(file_vars.get("RWELCOME_TODOS_PATH").map(String::as_str), Some("/srv/todos"));
+        assert_eq!(applied, None);
+        assert!(missing.is_empty());
+    }
+
+    #[test]
+    fn hostname_alias_is_printed() {
+        let config = Config { hostname_alias: Some("web-1".to_string()), ..Config::default() };
+        let file: ConfigFile = toml::from_str(&config.to_toml()).unwrap();
+        assert_eq!(file.env.get("RWELCOME_HOSTNAME_ALIAS").map(String::as_str), Some("web-1"));
+        assert_eq!(config.to_json()["hostname_alias"]["value"], "web-1");
+        assert!(Config::default().to_toml().contains("# RWELCOME_HOSTNAME_ALIAS is not set"));
+    }
+
+    #[test]
+    fn coordinates_need_both_halves_in_range() {
+        assert_eq!(parse_coordinates("lat:50.8225,lon:-0.1372"), Some((50.8225, -0.1372)));
+        assert_eq!(parse_coordinates(" lat: -33.87 , lon: 151.21 "), Some((-33.87, 151.21)));
+        for location in ["Brighton", "Brighton, UK", "lat:50.8225", "lon:-0.1372,lat:50.8225", "lat:91,lon:0", "lat:0,lon:181", "lat:x,lon:0"] {
+            assert_eq!(parse_coordinates(location), None, "{location}");
+        }
+    }
+
+    #[test]
+    fn weather_location_can_be_coordinates() {
+        let _lock = crate::ENV_LOCK.lock().unwrap_or_else(|err| err.into_inner());
+        env::set_var("RWELCOME_CONFIG_PATH", "/nonexistent/config.toml");
+        env::set_var("RWELCOME_WEATHER_LOCATION", "lat:50.8225,lon:-0.1372");
+        let coordinates = Config::load();
+        env::set_var("RWELCOME_WEATHER_LOCATION", "Brighton");
+        let name = Config::load();
+        env::remove_var("RWELCOME_WEATHER_LOCATION");
+        env::remove_var("RWELCOME_CONFIG_PATH");
+        assert_eq!((coordinates.lat, coordinates.lon), (Some(50.8225), Some(-0.1372)));
+        assert_eq!((name.lat, name.lon), (None, None));
+    }
+}
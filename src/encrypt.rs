@@ -0,0 +1,81 @@
+use std::io::{self, Write};
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+use crate::ext;
+
+/*
+ * With RWELCOME_TODOS_ENCRYPT=gpg, todo lists and the files kept next to them
+ * are stored encrypted with gpg, with a .gpg extension, and are only ever in
+ * plain text in memory. They're encrypted for RWELCOME_GPG_RECIPIENT, whose
+ * secret key gpg needs in order to decrypt them again (it may ask for the
+ * passphrase through gpg-agent).
+ */
+
+/// Decrypts the file at `path`. gpg is killed if it hasn't finished within
+/// RWELCOME_GPG_TIMEOUT_SECS seconds (30 by default), e.g. because it's waiting
+/// for a passphrase nobody is there to type.
+pub fn decrypt(path: &Path) -> io::Result<String> {
+    let timeout = Duration::from_secs(ext::parse_env_number("RWELCOME_GPG_TIMEOUT_SECS", 30));
+    let path_arg = path.to_string_lossy();
+    let output = ext::run_command("gpg", &["--quiet", "--decrypt", &path_arg], timeout)?
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "gpg isn't installed"))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(io::Error::other(format!("gpg couldn't decrypt {}: {}", path.display(), stderr.trim())));
+    }
+    String::from_utf8(output.stdout).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+/// Encrypts `contents` for `recipient` into the file at `path`, replacing it.
+pub fn encrypt(contents: &str, recipient: &str, path: &Path) -> io::Result<()> {
+    let mut child = Command::new("gpg")
+        .args(["--quiet", "--batch", "--yes", "--encrypt", "--recipient", recipient, "--output"])
+        .arg(path)
+        .stdin(Stdio::piped())
+        .stderr(Stdio::inherit())
+        .spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(contents.as_bytes())?;
+    }
+    if !child.wait()?.success() {
+        return Err(io::Error::other(format!("gpg couldn't encrypt for {recipient}")));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    #[test]
+    fn round_trip() {
+        let _lock = crate::ENV_LOCK.lock().unwrap_or_else(|err| err.into_inner());
+        let home = tempfile::tempdir().unwrap();
+        env::set_var("GNUPGHOME", home.path());
+        let generated = Command::new("gpg")
+            .args(["--batch", "--quiet", "--passphrase", "", "--quick-gen-key", "rwelcome-test@example.com", "future-default", "default", "never"])
+            .stderr(Stdio::null())
+            .status();
+        if !generated.is_ok_and(|status| status.success()) {
+            env::remove_var("GNUPGHOME");
+            return; // No gpg to test with.
+        }
+
+        let path = home.path().join("todos.gpg");
+        let encrypted = encrypt("buy milk\n", "rwelcome-test@example.com", &path);
+        let decrypted = decrypt(&path);
+        let _ = Command::new("gpgconf").args(["--kill", "gpg-agent"]).status();
+        env::remove_var("GNUPGHOME");
+
+        encrypted.unwrap();
+        assert!(!fs_contains(&path, "buy milk"));
+        assert_eq!(decrypted.unwrap(), "buy milk\n");
+    }
+
+    fn fs_contains(path: &Path, text: &str) -> bool {
+        String::from_utf8_lossy(&std::fs::read(path).unwrap()).contains(text)
+    }
+}
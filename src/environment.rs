@@ -1,6 +1,16 @@
-use std::fs::{self, File};
-use std::io::{self, Write, Read, BufRead, BufReader};
+use std::fs;
+use std::io::{self, Write, BufRead, BufReader};
 use std::env;
+use std::os::unix::fs::MetadataExt;
+use std::os::unix::net::UnixStream;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+#[cfg(feature = "weather")]
+use std::hash::Hasher;
+use chrono::{DateTime, Days, Local, Months, NaiveDate};
+use crate::ext::{self, ProcFs};
+#[cfg(any(target_os = "macos", target_os = "freebsd"))]
+use crate::sysctl;
 
 macro_rules! io_err {
     ($message:expr) => {
@@ -11,24 +21,489 @@ macro_rules! io_err {
     };
 }
 
+/// A single todo item.
+/// In the todos file, an item may start with the date it was added,
+/// e.g. `[2025-07-10] Buy milk`; that prefix is split out into `added`.
+#[derive(Clone, Debug)]
+pub struct Todo {
+    /// The todo itself, as written after any date prefix.
+    pub text: String,
+    /// The date it was added, if it was recorded.
+    pub added: Option<NaiveDate>,
+}
+
+impl Todo {
+    /// Parses a line of the todos file.
+    pub fn parse(line: &str) -> Todo {
+        let dated = line.strip_prefix('[')
+            .and_then(|rest| rest.split_once("] "))
+            .and_then(|(date, text)| {
+                NaiveDate::parse_from_str(date, "%Y-%m-%d").ok().map(|date| (date, text))
+            });
+        match dated {
+            Some((date, text)) => Todo { text: text.to_string(), added: Some(date) },
+            None => Todo { text: line.to_string(), added: None },
+        }
+    }
+
+    /// Formats the todo as a line of the todos file.
+    pub fn to_line(&self) -> String {
+        match self.added {
+            Some(date) => format!("[{}] {}", date.format("%Y-%m-%d"), self.text),
+            None => self.text.clone(),
+        }
+    }
+
+    /// The date the todo is due by, from a "due:YYYY-MM-DD" in its text.
+    pub fn due(&self) -> Option<NaiveDate> {
+        parse_due_date(&self.text)
+    }
+
+    /// The todo's recurrence, given as an `@every:7d`, `@every:1w` or `@every:1m` word.
+    pub fn recurrence(&self) -> Option<RecurrenceSpec> {
+        self.text.split_whitespace()
+            .find_map(|word| word.strip_prefix("@every:"))
+            .and_then(RecurrenceSpec::parse)
+    }
+
+    /// The todo's priority, given as a `priority:high`, `priority:medium` or `priority:low` word.
+    pub fn priority(&self) -> Option<Priority> {
+        self.text.split_whitespace()
+            .find_map(|word| word.strip_prefix("priority:"))
+            .and_then(|priority| match priority {
+                "high" => Some(Priority::High),
+                "medium" => Some(Priority::Medium),
+                "low" => Some(Priority::Low),
+                _ => None,
+            })
+    }
+}
+
+/// How important a todo is, most important first.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Priority {
+    /// Marked `priority:high`.
+    High,
+    /// Marked `priority:medium`.
+    Medium,
+    /// Marked `priority:low`.
+    Low,
+}
+
+/// The orders todos can be displayed in, see `sort_todos()`.
+#[derive(Clone, Copy, PartialEq)]
+pub enum SortKey {
+    /// Case-insensitive alphabetical order.
+    Alpha,
+    /// High priority first.
+    Priority,
+    /// Soonest due first.
+    Due,
+    /// Most recently added first.
+    Added,
+}
+
+impl SortKey {
+    fn name(self) -> &'static str {
+        match self {
+            SortKey::Alpha => "alpha",
+            SortKey::Priority => "priority",
+            SortKey::Due => "due",
+            SortKey::Added => "added",
+        }
+    }
+}
+
+/// Sorts numbered todos for display, keeping each todo's number so that it
+/// can still be edited by it. Todos without the metadata being sorted on go last,
+/// in file order. If none of the todos have it, the order is left alone and a
+/// warning is printed.
+pub fn sort_todos(todos: &mut [(usize, &Todo)], key: SortKey) {
+    let has_metadata = |todo: &Todo| match key {
+        SortKey::Alpha => true,
+        SortKey::Priority => todo.priority().is_some(),
+        SortKey::Due => todo.due().is_some(),
+        SortKey::Added => todo.added.is_some(),
+    };
+    if !todos.is_empty() && !todos.iter().any(|(_, todo)| has_metadata(todo)) {
+        eprintln!(
+            "rwelcome: warning: RWELCOME_TODOS_SORT={} but no todos have that information, keeping file order",
+            key.name()
+        );
+        return;
+    }
+    match key {
+        SortKey::Alpha => todos.sort_by_key(|(_, todo)| todo.text.to_lowercase()),
+        SortKey::Priority => todos.sort_by_key(|(_, todo)| (todo.priority().is_none(), todo.priority())),
+        SortKey::Due => todos.sort_by_key(|(_, todo)| (todo.due().is_none(), todo.due())),
+        SortKey::Added => todos.sort_by_key(|(_, todo)| (todo.added.is_none(), std::cmp::Reverse(todo.added))),
+    }
+}
+
+/// The unit of a recurrence's interval.
+#[derive(Clone, Copy, PartialEq)]
+pub enum RecurrenceUnit {
+    /// Days, e.g. `@every:7d`.
+    Day,
+    /// Weeks, e.g. `@every:1w`.
+    Week,
+    /// Months, e.g. `@every:1m`.
+    Month,
+}
+
+/// How often a recurring todo comes back, e.g. every 2 weeks.
+#[derive(Clone, Copy, PartialEq)]
+pub struct RecurrenceSpec {
+    /// How many `unit`s apart it comes back.
+    pub count: u32,
+    /// What `count` is counted in.
+    pub unit: RecurrenceUnit,
+}
+
+impl RecurrenceSpec {
+    /// Parses an interval such as "7d", "1w" or "1m".
+    pub fn parse(spec: &str) -> Option<RecurrenceSpec> {
+        let unit = match spec.chars().last()? {
+            'd' => RecurrenceUnit::Day,
+            'w' => RecurrenceUnit::Week,
+            'm' => RecurrenceUnit::Month,
+            _ => return None,
+        };
+        let count = spec[..spec.len() - 1].parse::<u32>().ok().filter(|count| *count > 0)?;
+        Some(RecurrenceSpec { count, unit })
+    }
+}
+
+/// Whether a recurring todo last seen (i.e. completed) on `last_seen` is due to come back `today`.
+pub fn should_recur(spec: &RecurrenceSpec, last_seen: NaiveDate, today: NaiveDate) -> bool {
+    let next = match spec.unit {
+        RecurrenceUnit::Day => last_seen.checked_add_days(Days::new(spec.count as u64)),
+        RecurrenceUnit::Week => last_seen.checked_add_days(Days::new(spec.count as u64 * 7)),
+        RecurrenceUnit::Month => last_seen.checked_add_months(Months::new(spec.count)),
+    };
+    next.is_some_and(|next| next <= today)
+}
+
+/// Whether todo lists are stored encrypted with gpg, set by RWELCOME_TODOS_ENCRYPT=gpg.
+/// Asking for it in a build without the encrypt feature is an error, rather
+/// than a reason to quietly store the todos in plain text.
+fn todos_encrypted() -> io::Result<bool> {
+    match ext::env_non_empty("RWELCOME_TODOS_ENCRYPT").as_deref() {
+        None | Some("none") => Ok(false),
+        Some("gpg") if cfg!(feature = "encrypt") => Ok(true),
+        Some("gpg") => io_err!("RWELCOME_TODOS_ENCRYPT=gpg needs rwelcome to be built with the encrypt feature."),
+        Some(other) => io_err!(format!("RWELCOME_TODOS_ENCRYPT=\"{other}\" isn't supported, use gpg.")),
+    }
+}
+
+/// Where the todo list or bookkeeping file at `path` is actually stored:
+/// `path` itself, or `path` with a .gpg extension when todos are encrypted.
+pub fn stored_todos_path(path: &str) -> io::Result<String> {
+    Ok(if todos_encrypted()? { format!("{path}.gpg") } else { path.to_string() })
+}
+
+/// Reads the todo list or bookkeeping file at `path`, decrypting it if todos are encrypted.
+fn read_todos_file(path: &str) -> io::Result<String> {
+    let stored = stored_todos_path(path)?;
+    #[cfg(feature = "encrypt")]
+    if stored != path {
+        if Path::new(path).exists() && Path::new(&stored).exists() {
+            return io_err!(format!(
+                "both {path} and {stored} exist, so it isn't clear which one holds your todos; remove the one you don't want."
+            ));
+        }
+        if !Path::new(&stored).exists() {
+            return Err(io::Error::new(io::ErrorKind::NotFound, format!("{stored} doesn't exist")));
+        }
+        return crate::encrypt::decrypt(Path::new(&stored));
+    }
+    fs::read_to_string(stored)
+}
+
+/// Writes the todo list or bookkeeping file at `path`, encrypted for
+/// RWELCOME_GPG_RECIPIENT if todos are encrypted. It's written to a temporary
+/// file and renamed into place, so a failed write can't truncate it.
+fn write_todos_file(path: &str, contents: &str) -> io::Result<()> {
+    let stored = stored_todos_path(path)?;
+    let temp_path = format!("{stored}.tmp");
+    #[cfg(feature = "encrypt")]
+    if stored != path {
+        let recipient = ext::env_non_empty("RWELCOME_GPG_RECIPIENT")
+            .ok_or_else(|| io::Error::other("set RWELCOME_GPG_RECIPIENT to encrypt your todos"))?;
+        crate::encrypt::encrypt(contents, &recipient, Path::new(&temp_path))?;
+        return fs::rename(&temp_path, &stored);
+    }
+    fs::write(&temp_path, contents)?;
+    fs::rename(&temp_path, &stored)
+}
+
+/// The file remembering when recurring todos were last completed.
+/// Each line is the completion date, a tab, and the todo's line in the todos file.
+fn recurring_path(todos_path: &str) -> String {
+    format!("{todos_path}.recurring")
+}
+
+/// Acquire the completed recurring todos, with the date each was completed.
+fn acquire_recurring(todos_path: &str) -> Vec<(NaiveDate, Todo)> {
+    let contents = read_todos_file(&recurring_path(todos_path)).unwrap_or_default();
+    contents.lines()
+        .filter_map(|line| {
+            let (date, todo) = line.split_once('\t')?;
+            let date = NaiveDate::parse_from_str(date, "%Y-%m-%d").ok()?;
+            Some((date, Todo::parse(todo)))
+        })
+        .collect()
+}
+
+/// Remembers that the recurring `todos` were completed `today`,
+/// replacing any earlier record of them.
+fn record_recurring(todos_path: &str, todos: &[Todo], today: NaiveDate) -> io::Result<()> {
+    let mut recurring = acquire_recurring(todos_path);
+    recurring.retain(|(_, seen)| !todos.iter().any(|todo| todo.text == seen.text));
+    recurring.extend(todos.iter().map(|todo| (today, todo.clone())));
+    let lines: Vec<String> = recurring.iter()
+        .map(|(date, todo)| format!("{}\t{}", date.format("%Y-%m-%d"), todo.to_line()))
+        .collect();
+    write_todos_file(&recurring_path(todos_path), &lines.join("\n"))
+}
+
+/// A completed todo, kept in the list's archive.
+pub struct ArchivedTodo {
+    /// The todo as it was in the list.
+    pub todo: Todo,
+    /// The day it was marked done.
+    pub completed: NaiveDate,
+}
+
+/// The file keeping the todos completed with `done`, for `edit stats`.
+/// Each line is the completion date, a tab, and the todo's line in the todos file.
+fn archive_path(todos_path: &str) -> String {
+    format!("{todos_path}.archive")
+}
+
+/// Acquire the completed todos in the archive of the list at `todos_path`.
+/// A list that has no archive yet has nothing in it.
+pub fn acquire_archived_todos(todos_path: &str) -> io::Result<Vec<ArchivedTodo>> {
+    let contents = match read_todos_file(&archive_path(todos_path)) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(err),
+    };
+    Ok(contents.lines()
+        .filter_map(|line| {
+            let (date, todo) = line.split_once('\t')?;
+            let completed = NaiveDate::parse_from_str(date, "%Y-%m-%d").ok()?;
+            Some(ArchivedTodo { todo: Todo::parse(todo), completed })
+        })
+        .collect())
+}
+
+/// Adds the `todos` completed `today` to the end of the archive.
+fn record_archived(todos_path: &str, todos: &[Todo], today: NaiveDate) -> io::Result<()> {
+    let mut contents = match read_todos_file(&archive_path(todos_path)) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => String::new(),
+        Err(err) => return Err(err),
+    };
+    for todo in todos {
+        contents.push_str(&format!("{}\t{}\n", today.format("%Y-%m-%d"), todo.to_line()));
+    }
+    write_todos_file(&archive_path(todos_path), &contents)
+}
+
+/// Extensions used for bookkeeping files next to a todo list, which can't be list names.
+const RESERVED_LIST_NAMES: &[&str] = &["recurring", "archive", "gpg", "bak", "redo", "tmp"];
+
+/// Works out the path of the named todo list, which lives next to the default
+/// list at `base_path` with the name as an extension, e.g. `todos.work`.
+/// No name means the default list. Names may only contain letters, digits,
+/// `_` and `-`, so they can't be used to escape the todos directory.
+pub fn todos_list_path(base_path: &str, list: Option<&str>) -> io::Result<String> {
+    let name = match list {
+        Some(name) => name,
+        None => return Ok(base_path.to_string()),
+    };
+    let valid = !name.is_empty()
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+    if !valid {
+        return io_err!(format!("'{name}' is not a valid list name (use letters, digits, _ and -)."));
+    }
+    if RESERVED_LIST_NAMES.contains(&name) {
+        return io_err!(format!("'{name}' is reserved and can't be used as a list name."));
+    }
+    Ok(format!("{base_path}.{name}"))
+}
+
+/// Acquire the names of the todo lists next to the default list at `base_path`.
+/// The default list itself is called "default".
+pub fn acquire_todo_lists(base_path: &str) -> io::Result<Vec<String>> {
+    let base_path = Path::new(base_path);
+    let base_name = base_path.file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or("todos");
+    let dir = base_path.parent().unwrap_or(Path::new("."));
+    let mut lists = Vec::<String>::new();
+    for entry in fs::read_dir(dir)? {
+        let file_name = entry?.file_name();
+        let file_name = match file_name.to_str() {
+            // Encrypted lists have a .gpg extension on top of their usual name.
+            Some(file_name) => file_name.strip_suffix(".gpg").unwrap_or(file_name),
+            None => continue,
+        };
+        if file_name == base_name {
+            lists.push("default".to_string());
+        } else if let Some(name) = file_name.strip_prefix(base_name).and_then(|rest| rest.strip_prefix('.')) {
+            // Skips the bookkeeping files kept next to a list, like todos.recurring.
+            if todos_list_path("", Some(name)).is_ok() {
+                lists.push(name.to_string());
+            }
+        }
+    }
+    lists.sort();
+    lists.dedup();
+    Ok(lists)
+}
+
+/// Whether `todos_path` is the URL of a remote (read-only) todo list.
+pub fn is_remote_todos(todos_path: &str) -> bool {
+    todos_path.starts_with("https://") || todos_path.starts_with("http://")
+}
+
+/// Fetches a remote todo list. The list is cached for
+/// RWELCOME_TODOS_REMOTE_TTL_SECS seconds (300 by default), so a slow or
+/// unreachable server only holds things up once in a while.
+#[cfg(feature = "weather")]
+async fn fetch_remote_todos(url: &str) -> io::Result<String> {
+    // FNV-1a, since std's hashers may change between Rust versions, orphaning the cache.
+    let mut hasher = fnv::FnvHasher::default();
+    hasher.write(url.as_bytes());
+    let cache_name = format!("remote_todos_{:016x}.txt", hasher.finish());
+    let ttl = Duration::from_secs(ext::parse_env_number("RWELCOME_TODOS_REMOTE_TTL_SECS", 300));
+    if let Some(contents) = ext::read_cache(&cache_name, ttl) {
+        return Ok(contents);
+    }
+    let to_io_error = |err: reqwest::Error| io::Error::other(err);
+    let contents = reqwest::get(url).await.map_err(to_io_error)?
+        .error_for_status().map_err(to_io_error)?
+        .text().await.map_err(to_io_error)?;
+    ext::write_cache(&cache_name, &contents);
+    Ok(contents)
+}
+
+#[cfg(not(feature = "weather"))]
+async fn fetch_remote_todos(_url: &str) -> io::Result<String> {
+    io_err!("remote todo lists need rwelcome to be built with the weather feature.")
+}
+
+/// The prefix marking comment lines in todos files: `#` unless overridden
+/// by RWELCOME_TODOS_COMMENT_CHAR (e.g. `//` or `;`).
+fn todos_comment_prefix() -> String {
+    ext::env_non_empty("RWELCOME_TODOS_COMMENT_CHAR").unwrap_or_else(|| "#".to_string())
+}
+
+/// Puts the comment lines of the todos file `contents` back among the todo
+/// `lines` being written. `origins` gives, for each line, the index of the todo
+/// in the file it came from, or None for a new todo. Comments stay above the todo
+/// they were above; if that todo is gone, they move down to the next one left.
+fn keep_comments(contents: &str, comment_prefix: &str, lines: Vec<String>, origins: &[Option<usize>]) -> Vec<String> {
+    let mut above = Vec::<Vec<&str>>::new();
+    let mut below_last = Vec::<&str>::new();
+    for line in contents.lines().take_while(|line| !line.is_empty()) {
+        if line.starts_with(comment_prefix) {
+            below_last.push(line);
+        } else {
+            above.push(std::mem::take(&mut below_last));
+        }
+    }
+    let mut orphaned = Vec::<&str>::new();
+    for (index, comments) in above.iter_mut().enumerate() {
+        if origins.contains(&Some(index)) {
+            comments.splice(0..0, std::mem::take(&mut orphaned));
+        } else {
+            orphaned.append(comments);
+        }
+    }
+    let mut merged = Vec::new();
+    for (line, origin) in lines.into_iter().zip(origins) {
+        if let Some(comments) = origin.and_then(|index| above.get(index)) {
+            merged.extend(comments.iter().map(|comment| comment.to_string()));
+        }
+        merged.push(line);
+    }
+    merged.extend(orphaned.into_iter().chain(below_last).map(str::to_string));
+    merged
+}
+
+/// Parses the contents of a todos file, stopping at the first empty line
+/// and skipping lines that start with `comment_prefix`.
+fn parse_todos(contents: &str, comment_prefix: &str) -> Vec<Todo> {
+    contents.lines()
+        .take_while(|line| !line.is_empty())
+        .filter(|line| !line.starts_with(comment_prefix))
+        .map(Todo::parse)
+        .collect()
+}
+
 /// Acquire todos from the filesystem at `todos_path`.
-pub async fn acquire_todos(todos_path: String) -> io::Result<Vec<String>> {
-    let file = fs::File::open(todos_path)?;
-    let reader = io::BufReader::new(file);
-    let mut todos = Vec::<String>::new();
-    for maybe_line in reader.lines() {
-        let line = maybe_line?;
-        if line.is_empty() {
-            break;
-        }
-        if line.starts_with('#') {
-            continue;
+/// Lines starting with the comment prefix (see `todos_comment_prefix()`) are skipped.
+/// Completed recurring todos whose interval has elapsed are added back to the
+/// list in memory; they're only written to the file by the next edit.
+/// If the file doesn't exist yet, the list is empty; it's only created by
+/// editing it, by `rwelcome init` or, for the default list, by
+/// `acquire_default_todos()`, so reading a mistyped list name doesn't leave a
+/// new list behind.
+/// If `todos_path` is an http(s) URL, the list is fetched from there instead.
+pub async fn acquire_todos(todos_path: String) -> io::Result<Vec<Todo>> {
+    let comment_prefix = todos_comment_prefix();
+    if is_remote_todos(&todos_path) {
+        let contents = fetch_remote_todos(&todos_path).await?;
+        return Ok(parse_todos(&contents, &comment_prefix));
+    }
+    let contents = match read_todos_file(&todos_path) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(err),
+    };
+    let mut todos = parse_todos(&contents, &comment_prefix);
+    let today = Local::now().date_naive();
+    for (last_seen, todo) in acquire_recurring(&todos_path) {
+        let due = todo.recurrence().is_some_and(|spec| should_recur(&spec, last_seen, today));
+        if due && !todos.iter().any(|active| active.text == todo.text) {
+            todos.push(todo);
         }
-        todos.push(line);
     }
     Ok(todos)
 }
 
+/// Acquires the default todo list at `todos_path` like `acquire_todos()`, but
+/// creates it first if it doesn't exist yet, as on a new install, so that
+/// there's a file to add todos to rather than an empty list with nowhere to go.
+pub async fn acquire_default_todos(todos_path: String) -> io::Result<Vec<Todo>> {
+    if !is_remote_todos(&todos_path) {
+        create_todos_file(&todos_path)?;
+    }
+    acquire_todos(todos_path).await
+}
+
+/// Creates the todo list at `todos_path` (along with its directory) with a
+/// short comment explaining it, unless it exists already.
+pub fn create_todos_file(todos_path: &str) -> io::Result<()> {
+    if Path::new(&stored_todos_path(todos_path)?).exists() {
+        return Ok(());
+    }
+    if let Some(dir) = Path::new(todos_path).parent() {
+        fs::create_dir_all(dir)?;
+    }
+    write_todos_file(todos_path, &format!("{} Add your todos here, one per line\n", todos_comment_prefix()))
+}
+
+/// Finds the due date of a todo, given as a `due:YYYY-MM-DD` word anywhere in it.
+pub fn parse_due_date(todo: &str) -> Option<NaiveDate> {
+    todo.split_whitespace()
+        .find_map(|word| word.strip_prefix("due:"))
+        .and_then(|date| NaiveDate::parse_from_str(date, "%Y-%m-%d").ok())
+}
+
 /// Acquire the current user by looking at the LOGNAME or USER environment variables.
 pub fn acquire_current_user() -> Option<String> {
     env::var("LOGNAME")
@@ -37,17 +512,62 @@ pub fn acquire_current_user() -> Option<String> {
 
 /// Acquire the system's hostname from the filesystem.
 /// More specifically, from /proc/sys/kernel/hostname.
-pub fn acquire_hostname() -> std::io::Result<String> {
-    fs::read_to_string("/proc/sys/kernel/hostname")
+#[cfg(target_os = "linux")]
+pub fn acquire_hostname(proc_fs: &ProcFs) -> std::io::Result<String> {
+    fs::read_to_string(proc_fs.hostname_path())
         .map(|s| s.trim().to_string())
 }
 
-/// Acquire the CPU temperature from the filesystem.
-/// More specifically, from /sys/class/hwmon/hwmon1/temp2_input (by default).
-/// If a value is bound to the environment variable RWELCOME_CPU_TEMP, it will look there instead.
-pub fn acquire_cpu_temperature() -> io::Result<f64> {
-    let path = env::var("RWELCOME_CPU_TEMP_PATH")
-        .unwrap_or("/sys/class/hwmon/hwmon1/temp2_input".to_string());
+/// Acquire the system's hostname from the kern.hostname sysctl.
+#[cfg(any(target_os = "macos", target_os = "freebsd"))]
+pub fn acquire_hostname(_proc_fs: &ProcFs) -> std::io::Result<String> {
+    sysctl::hostname(&sysctl::Libc)
+}
+
+/// How readings from several temperature sensors are combined into one.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Aggregate {
+    /// The hottest sensor.
+    Max,
+    /// The average of all the sensors.
+    Avg,
+    /// The first sensor found.
+    First,
+}
+
+impl Aggregate {
+    /// The name it's given by in RWELCOME_CPU_TEMP_AGGREGATE.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Aggregate::Max => "max",
+            Aggregate::Avg => "avg",
+            Aggregate::First => "first",
+        }
+    }
+}
+
+/// A CPU temperature in °C, combined from `sensor_count` sensors.
+pub struct CpuTempInfo {
+    /// The temperature, in °C.
+    pub value: f64,
+    /// How many sensors `value` was combined from.
+    pub sensor_count: u32,
+    /// How the sensors were combined.
+    pub aggregate: Aggregate,
+    /// The sensor's hwmon label, e.g. "Package id 0", when the value is that one sensor's reading.
+    pub label: Option<String>,
+}
+
+/// Reads the label of the hwmon sensor whose reading is at `path`, i.e. temp2_label
+/// for temp2_input. Returns None if it has no label.
+fn read_temperature_label(path: &Path) -> Option<String> {
+    let sensor = path.file_name()?.to_str()?.strip_suffix("_input")?;
+    let label = fs::read_to_string(path.with_file_name(format!("{sensor}_label"))).ok()?;
+    Some(label.trim().to_string()).filter(|label| !label.is_empty())
+}
+
+/// Reads a temperature in millidegrees from a hwmon `temp*_input` or thermal zone `temp` file.
+fn read_temperature(path: &Path) -> io::Result<f64> {
     let contents = fs::read_to_string(path)?;
     let temp_millidegrees: i32 = contents
                                 .trim()
@@ -61,10 +581,436 @@ pub fn acquire_cpu_temperature() -> io::Result<f64> {
     Ok(temp_millidegrees as f64 / 1000.0)
 }
 
+/// What a hwmon sensor measures, and so the unit of its reading.
+#[derive(Clone, Copy, PartialEq)]
+pub enum SensorUnit {
+    /// A temperature sensor, read in °C.
+    Celsius,
+    /// A fan, read in RPM.
+    Rpm,
+    /// A voltage sensor, read in volts.
+    Volts,
+}
+
+impl SensorUnit {
+    /// The unit to show after a reading, e.g. "°C".
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            SensorUnit::Celsius => "°C",
+            SensorUnit::Rpm => " RPM",
+            SensorUnit::Volts => " V",
+        }
+    }
+}
+
+/// The current reading of one hwmon sensor, e.g. "coretemp", "Core 0", 45.0°C.
+pub struct SensorReading {
+    /// The hwmon device's name, e.g. "coretemp".
+    pub hwmon_name: String,
+    /// The sensor's label, or its file name (e.g. "temp1") if it has none.
+    pub sensor_label: String,
+    /// The reading, in `unit`s.
+    pub value: f64,
+    /// What the sensor measures.
+    pub unit: SensorUnit,
+}
+
+/// Reads every temp*_input, fan*_input and in*_input sensor in a hwmon
+/// directory, labelled by its *_label file if it has one.
+/// Temperatures are in millidegrees, fans in RPM and voltages in millivolts.
+fn read_hwmon_sensors(dir: &Path) -> io::Result<Vec<SensorReading>> {
+    let hwmon_name = fs::read_to_string(dir.join("name"))
+        .map(|name| name.trim().to_string())
+        .unwrap_or_else(|_| dir.file_name().unwrap_or_default().to_string_lossy().to_string());
+    let mut sensors = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let entry_path = entry?.path();
+        let Some(sensor) = entry_path.file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| name.strip_suffix("_input")) else { continue };
+        let (unit, scale) = if sensor.starts_with("temp") {
+            (SensorUnit::Celsius, 1000.0)
+        } else if sensor.starts_with("fan") {
+            (SensorUnit::Rpm, 1.0)
+        } else if sensor.starts_with("in") {
+            (SensorUnit::Volts, 1000.0)
+        } else {
+            continue;
+        };
+        let Ok(raw) = fs::read_to_string(&entry_path) else { continue };
+        let Ok(raw) = raw.trim().parse::<i64>() else { continue };
+        let sensor_label = fs::read_to_string(dir.join(format!("{sensor}_label")))
+            .map(|label| label.trim().to_string())
+            .unwrap_or_else(|_| sensor.to_string());
+        sensors.push((sensor.to_string(), SensorReading {
+            hwmon_name: hwmon_name.clone(),
+            sensor_label,
+            value: raw as f64 / scale,
+            unit,
+        }));
+    }
+    // Keep temp2 before temp10.
+    sensors.sort_by_key(|(sensor, _)| {
+        let split = sensor.find(|c: char| c.is_ascii_digit()).unwrap_or(sensor.len());
+        (sensor[..split].to_string(), sensor[split..].parse::<u32>().unwrap_or(0))
+    });
+    Ok(sensors.into_iter().map(|(_, reading)| reading).collect())
+}
+
+/// Acquires the readings of every sensor under /sys/class/hwmon, in hwmon order.
+pub fn acquire_sensors_all(proc_fs: &ProcFs) -> io::Result<Vec<SensorReading>> {
+    let entries = match fs::read_dir(proc_fs.hwmon_dir()) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(err),
+    };
+    let mut dirs: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .collect();
+    dirs.sort_by_key(|dir| {
+        dir.file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| name.strip_prefix("hwmon"))
+            .and_then(|index| index.parse::<u32>().ok())
+            .unwrap_or(u32::MAX)
+    });
+    let mut readings = Vec::new();
+    for dir in dirs {
+        readings.extend(read_hwmon_sensors(&dir).unwrap_or_default());
+    }
+    Ok(readings)
+}
+
+/// Acquires every sensor reading known to lm-sensors, from `sensors -j`.
+/// Returns None when lm-sensors isn't installed.
+pub fn acquire_lm_sensors_data() -> io::Result<Option<serde_json::Value>> {
+    let output = match ext::run_command("sensors", &["-j"], Duration::from_secs(2))? {
+        Some(output) => output,
+        None => return Ok(None),
+    };
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(io::Error::other(stderr.trim().to_string()));
+    }
+    serde_json::from_slice(&output.stdout)
+        .map(Some)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+/// Finds the CPU's temperature in `sensors -j` output from a CPU's chip
+/// (coretemp on Intel, k10temp on AMD), e.g. `"Package id 0": {"temp1_input": 45.0}`.
+/// The whole package's reading is preferred over the cores': "Package id" on
+/// Intel, and on AMD "Tdie" over "Tctl", which some CPUs report with an offset.
+/// Otherwise it's the chip's first temperature, e.g. "Core 0".
+pub fn lm_sensors_cpu_temp(data: &serde_json::Value) -> Option<f64> {
+    const PACKAGE_FEATURES: [&str; 3] = ["Package id", "Tdie", "Tctl"];
+    let features: Vec<(&String, &serde_json::Value)> = data.as_object()?.iter()
+        .filter(|(chip, _)| chip.starts_with("coretemp") || chip.starts_with("k10temp"))
+        .filter_map(|(_, features)| features.as_object())
+        .flat_map(|features| features.iter())
+        .collect();
+    let temperature = |feature: &serde_json::Value| feature.as_object()?.iter()
+        .find(|(name, _)| name.starts_with("temp") && name.ends_with("_input"))
+        .and_then(|(_, value)| value.as_f64());
+    PACKAGE_FEATURES.iter()
+        .find_map(|package| features.iter()
+            .filter(|(name, _)| name.starts_with(package))
+            .find_map(|(_, feature)| temperature(feature)))
+        .or_else(|| features.iter().find_map(|(_, feature)| temperature(feature)))
+}
+
+/// Acquire the CPU temperature from the filesystem.
+/// More specifically, from /sys/class/hwmon/hwmon1/temp2_input (by default).
+/// If a value is bound to the environment variable RWELCOME_CPU_TEMP_PATH, it will look there instead.
+/// RWELCOME_CPU_TEMP_AGGREGATE=max|avg (default max) combines every temp*_input
+/// sensor in that file's directory, while `first` only reads the file itself.
+/// With RWELCOME_PREFER_LMSENSORS=1, lm-sensors is asked first, falling back
+/// to the filesystem when it isn't installed or doesn't know the CPU's chip.
+/// Without RWELCOME_CPU_TEMP_PATH, systems with no hwmon sensor there fall back
+/// to a thermal zone (see `acquire_thermal_zone_temperature()`).
+pub fn acquire_cpu_temperature(proc_fs: &ProcFs) -> io::Result<CpuTempInfo> {
+    let configured = ext::env_non_empty("RWELCOME_CPU_TEMP_PATH");
+    let path = configured.as_ref()
+        .map(PathBuf::from)
+        .unwrap_or_else(|| proc_fs.hwmon_dir().join("hwmon1/temp2_input"));
+    match acquire_hwmon_temperature(&path) {
+        Err(err) if configured.is_none() => match acquire_thermal_zone_temperature(proc_fs) {
+            Ok(value) => Ok(CpuTempInfo { value, sensor_count: 1, aggregate: Aggregate::First, label: None }),
+            // The hwmon error is the more useful one, since that's where most systems have it.
+            Err(_) => Err(err),
+        },
+        result => result,
+    }
+}
+
+/// Reads the temperature from the hwmon sensor at `path`, combined with the
+/// others in its directory as RWELCOME_CPU_TEMP_AGGREGATE says.
+fn acquire_hwmon_temperature(path: &Path) -> io::Result<CpuTempInfo> {
+    let aggregate = match ext::env_one_of("RWELCOME_CPU_TEMP_AGGREGATE", &["max", "avg", "first"], "max").as_str() {
+        "avg" => Aggregate::Avg,
+        "first" => Aggregate::First,
+        _ => Aggregate::Max,
+    };
+    if ext::env_flag("RWELCOME_PREFER_LMSENSORS") {
+        let data = acquire_lm_sensors_data().ok().flatten();
+        if let Some(value) = data.as_ref().and_then(lm_sensors_cpu_temp) {
+            return Ok(CpuTempInfo { value, sensor_count: 1, aggregate: Aggregate::First, label: None });
+        }
+    }
+    if aggregate == Aggregate::First {
+        let value = read_temperature(path)?;
+        return Ok(CpuTempInfo { value, sensor_count: 1, aggregate, label: read_temperature_label(path) });
+    }
+
+    let dir = path.parent().unwrap_or(Path::new("."));
+    let readings: Vec<f64> = read_hwmon_sensors(dir)?
+        .into_iter()
+        .filter(|sensor| sensor.unit == SensorUnit::Celsius)
+        .map(|sensor| sensor.value)
+        .collect();
+    if readings.is_empty() {
+        // Nothing else in the directory to combine, so report the file's own error.
+        let value = read_temperature(path)?;
+        return Ok(CpuTempInfo { value, sensor_count: 1, aggregate, label: read_temperature_label(path) });
+    }
+    let value = match aggregate {
+        Aggregate::Avg => readings.iter().sum::<f64>() / readings.len() as f64,
+        _ => readings.iter().cloned().fold(f64::MIN, f64::max),
+    };
+    // The label only describes the reading if the hottest sensor is the configured one.
+    let label = match aggregate {
+        Aggregate::Max if read_temperature(path).is_ok_and(|own| own == value) => read_temperature_label(path),
+        _ => None,
+    };
+    Ok(CpuTempInfo { value, sensor_count: readings.len() as u32, aggregate, label })
+}
+
+/// Acquires the temperature of a thermal zone, which is where some systems
+/// without hwmon (e.g. a Raspberry Pi) report it: the zone directory at
+/// RWELCOME_THERMAL_ZONE_PATH (or its temp file), or else the first of
+/// thermal_zone0 to thermal_zone3 under /sys/class/thermal that can be read.
+pub fn acquire_thermal_zone_temperature(proc_fs: &ProcFs) -> io::Result<f64> {
+    if let Some(path) = ext::env_non_empty("RWELCOME_THERMAL_ZONE_PATH") {
+        let path = PathBuf::from(path);
+        let path = if path.is_dir() { path.join("temp") } else { path };
+        return read_temperature(&path);
+    }
+    let mut last_err = None;
+    for zone in 0..4 {
+        match read_temperature(&proc_fs.thermal_dir().join(format!("thermal_zone{zone}/temp"))) {
+            Ok(value) => return Ok(value),
+            Err(err) => last_err = Some(err),
+        }
+    }
+    Err(last_err.unwrap_or_else(|| io::Error::from(io::ErrorKind::NotFound)))
+}
+
+/// Acquires the message of the day at `path`: at most `max_lines` lines,
+/// without any empty lines at the end.
+pub fn acquire_motd(path: &str, max_lines: usize) -> io::Result<Vec<String>> {
+    let contents = fs::read_to_string(path)?;
+    let mut lines: Vec<String> = contents.lines().take(max_lines).map(str::to_string).collect();
+    while lines.last().is_some_and(|line| line.trim().is_empty()) {
+        lines.pop();
+    }
+    Ok(lines)
+}
+
+/// Acquires a random fortune from `fortune`, from the fortunes file at `path`
+/// if given, and only a short one if `short` is true.
+/// Returns None if `fortune` isn't installed, fails or takes over a second.
+pub fn acquire_fortune(path: Option<&str>, short: bool) -> Option<String> {
+    let mut args = Vec::new();
+    if short {
+        args.push("-s");
+    }
+    args.extend(path);
+    let output = ext::run_command("fortune", &args, Duration::from_secs(1)).ok()??;
+    if !output.status.success() {
+        return None;
+    }
+    let fortune = String::from_utf8_lossy(&output.stdout).trim_end().to_string();
+    (!fortune.trim().is_empty()).then_some(fortune)
+}
+
+/// Name and (optional) version of the running distribution.
+pub struct OsInfo {
+    /// The distribution's name, e.g. "Arch Linux".
+    pub name: String,
+    /// Its version, if it has one, e.g. "24.04".
+    pub version: Option<String>,
+}
+
+/// Strips the optional surrounding quotes from an os-release value.
+fn unquote_os_release_value(value: &str) -> String {
+    value.trim().trim_matches('"').trim_matches('\'').to_string()
+}
+
+/// Acquires the distribution name and version from the filesystem.
+/// More specifically, from /etc/os-release.
+/// The version comes from VERSION_ID, or BUILD_ID for rolling-release distros
+/// that date-stamp their builds. Arch's BUILD_ID of "rolling" is ignored.
+pub fn acquire_os_info(proc_fs: &ProcFs) -> io::Result<OsInfo> {
+    let file = fs::File::open(proc_fs.os_release_path())?;
+    let reader = BufReader::new(file);
+    let mut name        = None;
+    let mut pretty_name = None;
+    let mut version_id  = None;
+    let mut build_id    = None;
+    for line in reader.lines() {
+        let line = line?;
+        if let Some((key, value)) = line.split_once('=') {
+            match key.trim() {
+                "NAME"        => name = Some(unquote_os_release_value(value)),
+                "PRETTY_NAME" => pretty_name = Some(unquote_os_release_value(value)),
+                "VERSION_ID"  => version_id = Some(unquote_os_release_value(value)),
+                "BUILD_ID"    => build_id = Some(unquote_os_release_value(value)),
+                _ => {},
+            }
+        }
+    }
+    let name = name.or(pretty_name).ok_or(io::Error::new(
+        io::ErrorKind::InvalidData,
+        "invalid os-release data",
+    ))?;
+    let version = version_id
+        .or(build_id.filter(|id| id != "rolling"))
+        .filter(|version| !version.is_empty());
+    Ok(OsInfo { name, version })
+}
+
+/// Acquires the CPU model name from the filesystem.
+/// More specifically, from the first "model name" entry in /proc/cpuinfo.
+pub fn acquire_cpu_model(proc_fs: &ProcFs) -> io::Result<String> {
+    let file = fs::File::open(proc_fs.cpuinfo_path())?;
+    let reader = BufReader::new(file);
+    for line in reader.lines() {
+        let line = line?;
+        if let Some((key, value)) = line.split_once(':') {
+            if key.trim() == "model name" {
+                return Ok(value.split_whitespace().collect::<Vec<_>>().join(" "));
+            }
+        }
+    }
+    Err(io::Error::new(io::ErrorKind::InvalidData, "invalid cpu model data"))
+}
+
+/// Counts the CPU cores, from the "processor" entries in /proc/cpuinfo.
+#[cfg(target_os = "linux")]
+fn acquire_cpu_count(proc_fs: &ProcFs) -> io::Result<usize> {
+    let contents = fs::read_to_string(proc_fs.cpuinfo_path())?;
+    let count = contents.lines()
+        .filter(|line| line.split_once(':').is_some_and(|(key, _)| key.trim() == "processor"))
+        .count();
+    if count == 0 {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "invalid cpu count data"));
+    }
+    Ok(count)
+}
+
+/// Finds the current mode of the primary output (or the first output with a
+/// current mode) in the output of `xrandr --current`, e.g. "1920x1080".
+fn parse_xrandr_resolution(output: &str) -> Option<String> {
+    let mut first = None;
+    let mut in_primary = false;
+    for line in output.lines() {
+        if !line.starts_with(' ') {
+            in_primary = line.contains(" connected primary");
+            continue;
+        }
+        if line.contains('*') {
+            let mode = line.split_whitespace().next()?.to_string();
+            if in_primary {
+                return Some(mode);
+            }
+            first.get_or_insert(mode);
+        }
+    }
+    first
+}
+
+/// Reads the preferred mode of the first connected display from
+/// /sys/class/drm/card*-*/modes, which works without an X server (e.g. on Wayland).
+fn acquire_drm_resolution(proc_fs: &ProcFs) -> io::Result<Option<String>> {
+    let mut connectors: Vec<_> = fs::read_dir(proc_fs.drm_dir())?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.join("modes").exists())
+        .collect();
+    connectors.sort();
+    for connector in connectors {
+        let connected = fs::read_to_string(connector.join("status"))
+            .is_ok_and(|status| status.trim() == "connected");
+        if !connected {
+            continue;
+        }
+        let modes = fs::read_to_string(connector.join("modes"))?;
+        if let Some(mode) = modes.lines().next() {
+            return Ok(Some(mode.trim().to_string()));
+        }
+    }
+    Ok(None)
+}
+
+/// Acquires the screen resolution, e.g. "1920x1080".
+/// Under X11 this asks `xrandr --current` (giving it one second to answer),
+/// otherwise it falls back to the kernel's DRM connectors in sysfs.
+/// Returns None when no resolution can be found.
+pub fn acquire_screen_resolution(proc_fs: &ProcFs) -> io::Result<Option<String>> {
+    if env::var("DISPLAY").is_ok() {
+        let output = ext::run_command("xrandr", &["--current"], Duration::from_secs(1))?;
+        if let Some(output) = output.filter(|output| output.status.success()) {
+            let resolution = parse_xrandr_resolution(&String::from_utf8_lossy(&output.stdout));
+            if resolution.is_some() {
+                return Ok(resolution);
+            }
+        }
+    }
+    match acquire_drm_resolution(proc_fs) {
+        Ok(resolution) => Ok(resolution),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(err),
+    }
+}
+
+/// Tidies a TERM_PROGRAM value for display, e.g. "iTerm.app" becomes "iTerm"
+/// and "Hyper 3.4.1" becomes "Hyper".
+fn clean_term_program(program: &str) -> String {
+    let name = program.trim().trim_end_matches(".app");
+    let words: Vec<&str> = name.split_whitespace()
+        .take_while(|word| !word.trim_start_matches('v').starts_with(|c: char| c.is_ascii_digit()))
+        .collect();
+    words.join(" ")
+}
+
+/// Acquires the name of the terminal emulator: from TERM_PROGRAM (set by iTerm2,
+/// VS Code and others), then TERM, and finally the parent process's executable.
+pub fn acquire_terminal(proc_fs: &ProcFs) -> Option<String> {
+    if let Some(program) = env::var("TERM_PROGRAM").ok()
+        .map(|program| clean_term_program(&program))
+        .filter(|program| !program.is_empty())
+    {
+        return Some(program);
+    }
+    if let Some(term) = env::var("TERM").ok().filter(|term| !term.trim().is_empty()) {
+        return Some(term);
+    }
+    let cmdline = fs::read(proc_fs.cmdline_path(std::os::unix::process::parent_id())).ok()?;
+    let executable = cmdline.split(|byte| *byte == 0).next()?;
+    let executable = String::from_utf8_lossy(executable);
+    let name = Path::new(executable.as_ref()).file_name()?.to_string_lossy().to_string();
+    (!name.is_empty()).then_some(name)
+}
+
 /// Acquires the kernel version from the filesystem.
 /// More specifically, from /proc/version.
-pub fn acquire_kernel_version() -> io::Result<String> {
-    let contents = fs::read_to_string("/proc/version")?;
+/// If `full` is true, the whole contents of /proc/version are returned,
+/// including the build date and compiler version.
+pub fn acquire_kernel_version(proc_fs: &ProcFs, full: bool) -> io::Result<String> {
+    let contents = fs::read_to_string(proc_fs.version_path())?;
+    if full {
+        return Ok(contents.trim().to_owned());
+    }
     let version_info = contents.split_whitespace()
         .nth(2) // the kernel version typically appears as the third word in /proc/version
         .ok_or(io::Error::new(
@@ -75,26 +1021,77 @@ pub fn acquire_kernel_version() -> io::Result<String> {
     Ok(version_info)
 }
 
+/// The system uptime, broken down into whole units.
+pub struct UptimeInfo {
+    /// Whole days.
+    pub days: u64,
+    /// Hours, beyond the whole days.
+    pub hours: u64,
+    /// Minutes, beyond the whole hours.
+    pub minutes: u64,
+    /// Seconds, beyond the whole minutes.
+    pub seconds: u64,
+    /// The average percentage of CPU time spent idle since boot, where known.
+    pub idletime_pct: Option<f64>,
+}
+
+impl UptimeInfo {
+    /// Splits an uptime of `uptime_seconds` into days, hours, minutes and seconds.
+    pub fn from_seconds(uptime_seconds: u64) -> UptimeInfo {
+        UptimeInfo {
+            days: uptime_seconds / 86400,
+            hours: (uptime_seconds % 86400) / 3600,
+            minutes: (uptime_seconds % 3600) / 60,
+            seconds: uptime_seconds % 60,
+            idletime_pct: None,
+        }
+    }
+
+    /// The whole uptime in seconds.
+    pub fn as_seconds(&self) -> u64 {
+        self.days * 86400 + self.hours * 3600 + self.minutes * 60 + self.seconds
+    }
+}
+
 /// Attempts to acquire the current system uptime from the filesystem.
-pub fn acquire_uptime() -> io::Result<(u64, u64)> {
-    let mut file       = fs::File::open("/proc/uptime")?;
-    let mut contents = String::new();
-    file.read_to_string(&mut contents)?;
-    let uptime_str = contents.split_whitespace().next().ok_or(io::Error::new(
+/// The second column of /proc/uptime is the idle time summed over every core,
+/// so it's divided by the number of cores to give the idle percentage.
+#[cfg(target_os = "linux")]
+pub fn acquire_uptime(proc_fs: &ProcFs) -> io::Result<UptimeInfo> {
+    let contents = fs::read_to_string(proc_fs.uptime_path())?;
+    let mut columns = contents.split_whitespace();
+    let uptime_str = columns.next().ok_or(io::Error::new(
         io::ErrorKind::InvalidData,
         "invalid uptime data",
     ))?.trim();
-    let uptime_seconds = uptime_str.parse::<f32>()
+    let uptime_seconds = uptime_str.parse::<f64>()
                         .map_err(|e|
                             io::Error::new(io::ErrorKind::InvalidData, e)
                         )?;
-    let hours = uptime_seconds as u64 / 3600;
-    let minutes = (uptime_seconds as u64 % 3600) / 60;
-    Ok((hours, minutes))
+    let mut uptime = UptimeInfo::from_seconds(uptime_seconds as u64);
+    let idle_seconds = columns.next().and_then(|idle| idle.parse::<f64>().ok());
+    if let (Some(idle_seconds), Ok(cores)) = (idle_seconds, acquire_cpu_count(proc_fs)) {
+        if uptime_seconds > 0.0 {
+            let pct = idle_seconds / (uptime_seconds * cores as f64) * 100.0;
+            uptime.idletime_pct = Some(pct.clamp(0.0, 100.0));
+        }
+    }
+    Ok(uptime)
+}
+
+/// Attempts to acquire the current system uptime from the kern.boottime sysctl.
+#[cfg(any(target_os = "macos", target_os = "freebsd"))]
+pub fn acquire_uptime(_proc_fs: &ProcFs) -> io::Result<UptimeInfo> {
+    let now_seconds = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?
+        .as_secs();
+    Ok(UptimeInfo::from_seconds(sysctl::uptime_seconds(&sysctl::Libc, now_seconds)?))
 }
 
 /// Attempts to parse a memory value given as a string value into a number.
 /// This is a util function used exclusively by the below `acquire_memory_info()`.
+#[cfg(target_os = "linux")]
 fn parse_memory_value(value: &str) -> io::Result<u64> {
     let value = value.split_whitespace().next().ok_or(io::Error::new(
         io::ErrorKind::InvalidData,
@@ -109,124 +1106,1861 @@ fn parse_memory_value(value: &str) -> io::Result<u64> {
         })
 }
 
-/// Attempts to acquire, from the filesystem, the used and total memory
-/// on the system at the moment. More specifically, from /proc/meminfo.
-pub fn acquire_memory_info() -> io::Result<(u64, u64)> {
-    let file = fs::File::open("/proc/meminfo")?;
-    let reader = BufReader::new(file);
-    let mut total_memory     = 0;
-    let mut available_memory = 0;
-    for line in reader.lines() {
-        let line = line?;
-        if let Some((key, value)) = line.split_once(':') {
-            match key.trim() {
-                "MemTotal"     => total_memory = parse_memory_value(value)?,
-                "MemAvailable" => available_memory = parse_memory_value(value)?,
-                _ => {},
+/// Memory figures in kB, as reported by /proc/meminfo.
+/// `available` includes reclaimable caches, whereas `free` is physically unused memory.
+pub struct MemInfo {
+    /// All usable memory (MemTotal).
+    pub total: u64,
+    /// Memory available to start new programs without swapping (MemAvailable).
+    pub available: u64,
+    /// Memory not used for anything at all (MemFree).
+    pub free: u64,
+    /// The page cache (Cached).
+    pub cached_kb: u64,
+    /// Block device buffers (Buffers).
+    pub buffers_kb: u64,
+    /// Reserved huge pages, counted in pages rather than kB.
+    pub hugepages_total: u64,
+    /// How many huge pages are free.
+    pub hugepages_free: u64,
+    /// The size of a huge page.
+    pub hugepage_size_kb: u64,
+}
+
+impl MemInfo {
+    /// Used memory, counting reclaimable caches as free.
+    pub fn used_excluding_caches(&self) -> u64 {
+        self.total.saturating_sub(self.available)
+    }
+
+    /// Used memory, counting only physically unused memory as free.
+    pub fn used_including_caches(&self) -> u64 {
+        self.total.saturating_sub(self.free)
+    }
+}
+
+/// cgroup v1 reports "no limit" as the largest page-aligned 64-bit value.
+#[cfg(target_os = "linux")]
+const CGROUP_V1_NO_LIMIT: u64 = 9223372036854771712;
+
+/// Reads a cgroup memory figure in bytes from the first of `files` (relative to
+/// the cgroup directory) that exists. "max", or cgroup v1's huge sentinel, means
+/// there is no limit.
+#[cfg(target_os = "linux")]
+fn read_cgroup_memory_value(proc_fs: &ProcFs, files: &[&str]) -> io::Result<Option<u64>> {
+    for file in files {
+        let contents = match fs::read_to_string(proc_fs.cgroup_dir().join(file)) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => continue,
+            Err(err) => return Err(err),
+        };
+        let value = contents.trim();
+        if value == "max" {
+            return Ok(None);
+        }
+        let bytes = value.parse::<u64>()
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "invalid cgroup memory data"))?;
+        return Ok((bytes < CGROUP_V1_NO_LIMIT).then_some(bytes));
+    }
+    Ok(None)
+}
+
+/// Acquires the memory limit of the cgroup rwelcome runs in, in bytes, from
+/// memory.max (cgroup v2) or memory/memory.limit_in_bytes (cgroup v1).
+/// Returns None when there's no limit.
+#[cfg(target_os = "linux")]
+pub fn acquire_cgroup_memory_limit(proc_fs: &ProcFs) -> io::Result<Option<u64>> {
+    read_cgroup_memory_value(proc_fs, &["memory.max", "memory/memory.limit_in_bytes"])
+}
+
+/// Acquires how much memory the cgroup rwelcome runs in is using, in bytes.
+#[cfg(target_os = "linux")]
+pub fn acquire_cgroup_memory_usage(proc_fs: &ProcFs) -> io::Result<Option<u64>> {
+    read_cgroup_memory_value(proc_fs, &["memory.current", "memory/memory.usage_in_bytes"])
+}
+
+/// Whether rwelcome is running inside a container: Docker and Podman leave a
+/// file in the root directory, and otherwise init's cgroup gives it away.
+#[cfg(target_os = "linux")]
+pub fn acquire_in_container(proc_fs: &ProcFs) -> bool {
+    if proc_fs.path("/.dockerenv").exists() || proc_fs.path("/run/.containerenv").exists() {
+        return true;
+    }
+    fs::read_to_string(proc_fs.path("/proc/1/cgroup")).is_ok_and(|cgroup| {
+        ["docker", "kubepods", "libpod", "lxc", "containerd"].iter().any(|name| cgroup.contains(name))
+    })
+}
+
+/// Attempts to acquire, from the filesystem, the memory figures
+/// on the system at the moment. More specifically, from /proc/meminfo.
+#[cfg(target_os = "linux")]
+pub fn acquire_memory_info(proc_fs: &ProcFs) -> io::Result<MemInfo> {
+    let file = fs::File::open(proc_fs.meminfo_path())?;
+    let reader = BufReader::new(file);
+    let mut total_memory     = 0;
+    let mut available_memory = 0;
+    let mut free_memory      = 0;
+    let mut cached_kb        = 0;
+    let mut buffers_kb       = 0;
+    let mut hugepages_total  = 0;
+    let mut hugepages_free   = 0;
+    let mut hugepage_size_kb = 0;
+    for line in reader.lines() {
+        let line = line?;
+        if let Some((key, value)) = line.split_once(':') {
+            match key.trim() {
+                "MemTotal"     => total_memory = parse_memory_value(value)?,
+                "MemAvailable" => available_memory = parse_memory_value(value)?,
+                "MemFree"      => free_memory = parse_memory_value(value)?,
+                "Cached"       => cached_kb = parse_memory_value(value)?,
+                "Buffers"      => buffers_kb = parse_memory_value(value)?,
+                "HugePages_Total" => hugepages_total = parse_memory_value(value)?,
+                "HugePages_Free"  => hugepages_free = parse_memory_value(value)?,
+                "Hugepagesize"    => hugepage_size_kb = parse_memory_value(value)?,
+                _ => {},
             }
         }
     }
-    let used_memory = total_memory - available_memory;
-    Ok((used_memory, total_memory))
+    Ok(MemInfo {
+        total: total_memory,
+        available: available_memory,
+        free: free_memory,
+        cached_kb,
+        buffers_kb,
+        hugepages_total,
+        hugepages_free,
+        hugepage_size_kb,
+    })
+}
+
+/// Attempts to acquire the memory figures (in kB, like /proc/meminfo)
+/// from sysctl: the total from hw.memsize (macOS) or hw.physmem (FreeBSD),
+/// and the free page count from vm.page_free_count (macOS) or
+/// vm.stats.vm.v_free_count (FreeBSD). Free and available are the same here.
+#[cfg(any(target_os = "macos", target_os = "freebsd"))]
+pub fn acquire_memory_info(_proc_fs: &ProcFs) -> io::Result<MemInfo> {
+    let (total_memory, free_memory) = sysctl::memory_kb(&sysctl::Libc, sysctl::MEMORY_SYSCTLS)?;
+    Ok(MemInfo {
+        total: total_memory,
+        available: free_memory,
+        free: free_memory,
+        cached_kb: 0,
+        buffers_kb: 0,
+        hugepages_total: 0,
+        hugepages_free: 0,
+        hugepage_size_kb: 0,
+    })
+}
+
+/// How much of the last 10 seconds tasks spent stalled waiting for memory, as
+/// percentages: `some` is when at least one task was, `full` when all of them were.
+pub struct PressureInfo {
+    /// Percentage of time at least one task was stalled.
+    pub some_avg10: f64,
+    /// Percentage of time all tasks were stalled.
+    pub full_avg10: f64,
+}
+
+/// Parses the avg10 figures from the contents of a /proc/pressure file, e.g.
+/// `some avg10=2.30 avg60=1.10 avg300=0.40 total=123456`.
+#[cfg(target_os = "linux")]
+fn parse_pressure(contents: &str) -> Option<PressureInfo> {
+    let avg10 = |kind: &str| {
+        contents.lines()
+            .find(|line| line.split_whitespace().next() == Some(kind))?
+            .split_whitespace()
+            .find_map(|field| field.strip_prefix("avg10="))?
+            .parse::<f64>().ok()
+    };
+    Some(PressureInfo { some_avg10: avg10("some")?, full_avg10: avg10("full")? })
+}
+
+/// Acquires the memory Pressure Stall Information from /proc/pressure/memory.
+/// Returns None on kernels without it (before 4.20, or with PSI turned off).
+#[cfg(target_os = "linux")]
+pub fn acquire_memory_pressure(proc_fs: &ProcFs) -> io::Result<Option<PressureInfo>> {
+    let contents = match fs::read_to_string(proc_fs.memory_pressure_path()) {
+        Ok(contents) => contents,
+        Err(err) if matches!(err.kind(), io::ErrorKind::NotFound | io::ErrorKind::Unsupported) => return Ok(None),
+        Err(err) => return Err(err),
+    };
+    parse_pressure(&contents)
+        .map(Some)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "invalid pressure data"))
+}
+
+/// Pressure Stall Information is Linux-only.
+#[cfg(not(target_os = "linux"))]
+pub fn acquire_memory_pressure(_proc_fs: &ProcFs) -> io::Result<Option<PressureInfo>> {
+    Ok(None)
+}
+
+/// Reads a number of bits from one of the files under /proc/sys/kernel/random.
+#[cfg(target_os = "linux")]
+fn read_entropy_value(path: &Path) -> io::Result<u32> {
+    fs::read_to_string(path)?
+        .trim()
+        .parse()
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, format!("invalid number in {}", path.display())))
+}
+
+/// Acquires how many bits of entropy the kernel's random pool has.
+#[cfg(target_os = "linux")]
+pub fn acquire_entropy_available(proc_fs: &ProcFs) -> io::Result<u32> {
+    read_entropy_value(&proc_fs.random_dir().join("entropy_avail"))
+}
+
+/// Acquires how many bits of entropy the kernel's random pool can hold.
+#[cfg(target_os = "linux")]
+pub fn acquire_entropy_poolsize(proc_fs: &ProcFs) -> io::Result<u32> {
+    read_entropy_value(&proc_fs.random_dir().join("poolsize"))
+}
+
+/// Other systems don't say how much entropy they have.
+#[cfg(not(target_os = "linux"))]
+pub fn acquire_entropy_available(_proc_fs: &ProcFs) -> io::Result<u32> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "the entropy pool is only shown on Linux"))
+}
+
+/// Other systems don't say how big their entropy pool is either.
+#[cfg(not(target_os = "linux"))]
+pub fn acquire_entropy_poolsize(_proc_fs: &ProcFs) -> io::Result<u32> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "the entropy pool is only shown on Linux"))
+}
+
+/// Reads a number from one of the files under /proc/sys/net/netfilter.
+/// Returns None if it doesn't exist, because conntrack isn't loaded.
+#[cfg(target_os = "linux")]
+fn read_conntrack_value(path: &Path) -> io::Result<Option<u64>> {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(err),
+    };
+    contents.trim().parse().map(Some)
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, format!("invalid number in {}", path.display())))
+}
+
+/// How many connections netfilter is tracking, out of how many it can.
+pub struct ConntrackCount {
+    /// Connections tracked right now.
+    pub count: u64,
+    /// The most that can be tracked (nf_conntrack_max).
+    pub max: u64,
+}
+
+impl ConntrackCount {
+    /// How full the table is, from 0 to 1. Zero if it can't hold any.
+    pub fn fraction(&self) -> f64 {
+        if self.max == 0 { 0.0 } else { self.count as f64 / self.max as f64 }
+    }
+}
+
+/// Acquires how many connections netfilter is tracking, and how many it can.
+/// Returns None when the nf_conntrack module isn't loaded.
+#[cfg(target_os = "linux")]
+pub fn acquire_conntrack_count(proc_fs: &ProcFs) -> io::Result<Option<ConntrackCount>> {
+    let dir = proc_fs.netfilter_dir();
+    let count = read_conntrack_value(&dir.join("nf_conntrack_count"))?;
+    let max = read_conntrack_value(&dir.join("nf_conntrack_max"))?;
+    Ok(count.zip(max).map(|(count, max)| ConntrackCount { count, max }))
+}
+
+/// Connection tracking is netfilter's, so Linux-only.
+#[cfg(not(target_os = "linux"))]
+pub fn acquire_conntrack_count(_proc_fs: &ProcFs) -> io::Result<Option<ConntrackCount>> {
+    Ok(None)
+}
+
+/// Space and inode usage of a mounted filesystem, with sizes in bytes.
+pub struct DiskUsage {
+    /// Space in use.
+    pub used: u64,
+    /// The filesystem's size.
+    pub total: u64,
+    /// Inodes in use.
+    pub inodes_used: u64,
+    /// Zero on filesystems without a fixed number of inodes, such as btrfs.
+    pub inodes_total: u64,
+}
+
+/// Acquires the space and inode usage of the filesystem mounted at `path`, using statvfs(3).
+pub fn acquire_disk_usage(path: &str) -> io::Result<DiskUsage> {
+    let cpath = std::ffi::CString::new(path)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statvfs(cpath.as_ptr(), &mut stat) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    let block_size = stat.f_frsize as u64;
+    let total = stat.f_blocks as u64 * block_size;
+    let free = stat.f_bfree as u64 * block_size;
+    Ok(DiskUsage {
+        used: total.saturating_sub(free),
+        total,
+        inodes_used: (stat.f_files as u64).saturating_sub(stat.f_ffree as u64),
+        inodes_total: stat.f_files as u64,
+    })
+}
+
+/// A block device's I/O counters since boot, from /proc/diskstats.
+/// Sectors are always 512 bytes there, whatever the device's own sector size.
+#[cfg(target_os = "linux")]
+pub struct DiskIo {
+    /// Reads completed.
+    pub reads_completed: u64,
+    /// Writes completed.
+    pub writes_completed: u64,
+    /// Sectors read.
+    pub read_sectors: u64,
+    /// Sectors written.
+    pub write_sectors: u64,
+}
+
+/// Finds the line for `device` (e.g. "sda1") in the contents of /proc/diskstats.
+#[cfg(target_os = "linux")]
+fn parse_diskstats(contents: &str, device: &str) -> Option<DiskIo> {
+    contents.lines().find_map(|line| {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.len() < 10 || fields[2] != device {
+            return None;
+        }
+        let field = |index: usize| fields[index].parse::<u64>().ok();
+        Some(DiskIo {
+            reads_completed: field(3)?,
+            read_sectors: field(5)?,
+            writes_completed: field(7)?,
+            write_sectors: field(9)?,
+        })
+    })
+}
+
+/// Acquires the I/O counters of `device`, e.g. "sda1" or "nvme0n1p2".
+#[cfg(target_os = "linux")]
+pub fn acquire_disk_io(proc_fs: &ProcFs, device: &str) -> io::Result<DiskIo> {
+    let contents = fs::read_to_string(proc_fs.diskstats_path())?;
+    parse_diskstats(&contents, device).ok_or_else(|| {
+        io::Error::new(io::ErrorKind::NotFound, format!("no device named {device}"))
+    })
+}
+
+/// Finds the block device that the root filesystem is mounted from, in
+/// /proc/mounts, e.g. "sda1". Symlinks such as /dev/mapper/root are followed
+/// to the device's kernel name, e.g. "dm-0".
+#[cfg(target_os = "linux")]
+pub fn acquire_root_device(proc_fs: &ProcFs) -> io::Result<String> {
+    let contents = fs::read_to_string(proc_fs.mounts_path())?;
+    let source = contents.lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            Some((fields.next()?, fields.next()?))
+        })
+        // The last mount on / is the one in effect.
+        .rfind(|(source, mount_point)| *mount_point == "/" && source.starts_with("/dev/"))
+        .map(|(source, _)| source.to_string())
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no block device mounted on /"))?;
+    let path = fs::canonicalize(proc_fs.path(&source)).unwrap_or_else(|_| PathBuf::from(&source));
+    Ok(path.file_name().unwrap_or_default().to_string_lossy().to_string())
+}
+
+/// How fast a block device is being read from and written to, per second.
+pub struct DiskThroughput {
+    /// Bytes read per second.
+    pub read_bytes_per_sec: f64,
+    /// Bytes written per second.
+    pub write_bytes_per_sec: f64,
+    /// Reads completed per second.
+    pub reads_per_sec: f64,
+    /// Writes completed per second.
+    pub writes_per_sec: f64,
+}
+
+/// Measures the throughput of `device` by reading its counters twice, `sample` apart.
+#[cfg(target_os = "linux")]
+pub async fn acquire_disk_throughput(proc_fs: &ProcFs, device: &str, sample: Duration) -> io::Result<DiskThroughput> {
+    let before = acquire_disk_io(proc_fs, device)?;
+    let start = std::time::Instant::now();
+    tokio::time::sleep(sample).await;
+    let after = acquire_disk_io(proc_fs, device)?;
+    let seconds = start.elapsed().as_secs_f64().max(0.001);
+    let rate = |before: u64, after: u64| after.saturating_sub(before) as f64 / seconds;
+    Ok(DiskThroughput {
+        read_bytes_per_sec: rate(before.read_sectors, after.read_sectors) * 512.0,
+        write_bytes_per_sec: rate(before.write_sectors, after.write_sectors) * 512.0,
+        reads_per_sec: rate(before.reads_completed, after.reads_completed),
+        writes_per_sec: rate(before.writes_completed, after.writes_completed),
+    })
+}
+
+/// There's no /proc/diskstats elsewhere.
+#[cfg(not(target_os = "linux"))]
+pub async fn acquire_disk_throughput(_proc_fs: &ProcFs, _device: &str, _sample: Duration) -> io::Result<DiskThroughput> {
+    Err(io::Error::other("disk I/O statistics need /proc/diskstats"))
+}
+
+/// The round trip time to one of the RWELCOME_PING_HOSTS, in milliseconds,
+/// or None if it didn't answer in time.
+pub struct PingResult {
+    /// The host's name, as given in RWELCOME_PING_HOSTS.
+    pub name: String,
+    /// The round trip time, or None if there was no answer in time.
+    pub latency_ms: Option<f64>,
+}
+
+/// Parses RWELCOME_PING_HOSTS, e.g. "gateway=192.168.1.1,8.8.8.8", into
+/// (name, host) pairs. A host without a name goes by its address.
+pub fn parse_ping_hosts(spec: &str) -> Vec<(String, String)> {
+    spec.split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| match entry.split_once('=') {
+            Some((name, host)) => (name.trim().to_string(), host.trim().to_string()),
+            None => (entry.to_string(), entry.to_string()),
+        })
+        .collect()
+}
+
+/// Parses the round trip time out of `ping`'s output, e.g. "time=0.8 ms".
+fn parse_ping_time(output: &str) -> Option<f64> {
+    let (_, rest) = output.split_once("time=")?;
+    let end = rest.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(rest.len());
+    rest[..end].parse().ok()
+}
+
+/// Pings `host` once with the system's `ping`, which doesn't need root to
+/// send ICMP, giving up after `timeout`. Returns None if it didn't answer.
+fn ping_host(host: &str, timeout: Duration) -> io::Result<Option<f64>> {
+    // Linux's ping waits -W seconds, macOS's -W milliseconds.
+    #[cfg(target_os = "linux")]
+    let wait = timeout.as_secs().max(1).to_string();
+    #[cfg(not(target_os = "linux"))]
+    let wait = timeout.as_millis().to_string();
+    // Leave ping time to start up before killing it.
+    let output = match ext::run_command("ping", &["-c", "1", "-W", &wait, host], timeout + Duration::from_millis(200)) {
+        Ok(Some(output)) => output,
+        Ok(None) => return Err(io::Error::new(io::ErrorKind::NotFound, "ping isn't installed")),
+        Err(err) if err.kind() == io::ErrorKind::TimedOut => return Ok(None),
+        Err(err) => return Err(err),
+    };
+    if !output.status.success() {
+        return Ok(None);
+    }
+    Ok(parse_ping_time(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// Pings each of `hosts`, given as (name, host), all at once, each for at most `timeout`.
+pub async fn acquire_ping_latencies(hosts: Vec<(String, String)>, timeout: Duration) -> io::Result<Vec<PingResult>> {
+    let pings: Vec<_> = hosts.into_iter()
+        .map(|(name, host)| tokio::task::spawn_blocking(move || {
+            ping_host(&host, timeout).map(|latency_ms| PingResult { name, latency_ms })
+        }))
+        .collect();
+    let mut results = Vec::new();
+    for ping in pings {
+        results.push(ping.await.map_err(io::Error::other)??);
+    }
+    Ok(results)
+}
+
+/// The package managers rwelcome knows how to inspect.
+#[derive(Clone, Copy, PartialEq)]
+pub enum PackageManager {
+    /// Arch Linux's pacman.
+    Pacman,
+    /// Debian and Ubuntu's apt.
+    Apt,
+    /// Fedora's dnf.
+    Dnf,
+}
+
+impl PackageManager {
+    /// A file that the package manager rewrites whenever its package database is synced.
+    fn database_path(self) -> &'static str {
+        match self {
+            PackageManager::Pacman => "/var/lib/pacman/sync/core.db",
+            PackageManager::Apt    => "/var/cache/apt/pkgcache.bin",
+            PackageManager::Dnf    => "/var/lib/rpm/rpmdb.sqlite",
+        }
+    }
+}
+
+/// Detects the system's package manager by looking for its database on the filesystem.
+pub fn detect_package_manager(proc_fs: &ProcFs) -> Option<PackageManager> {
+    [PackageManager::Pacman, PackageManager::Apt, PackageManager::Dnf]
+        .into_iter()
+        .find(|manager| proc_fs.path(manager.database_path()).exists())
+}
+
+/// Acquires when the package database was last updated, from its modification time.
+/// Returns None when no known package manager is found.
+pub fn acquire_last_package_update(proc_fs: &ProcFs) -> io::Result<Option<DateTime<Local>>> {
+    let manager = match detect_package_manager(proc_fs) {
+        Some(manager) => manager,
+        None => return Ok(None),
+    };
+    let modified = fs::metadata(proc_fs.path(manager.database_path()))?.modified()?;
+    Ok(Some(modified.into()))
+}
+
+/// A ZFS pool and its health, e.g. "tank", "ONLINE".
+pub struct ZfsPool {
+    /// The pool's name.
+    pub name: String,
+    /// Its health, as `zpool list` reports it.
+    pub health: String,
+}
+
+/// Parses the output of `zpool list -H -o name,health`: one pool per line,
+/// with its name and health separated by a tab.
+fn parse_zpool_list(output: &str) -> Vec<ZfsPool> {
+    output.lines()
+        .filter_map(|line| line.split_once('\t'))
+        .map(|(name, health)| ZfsPool { name: name.trim().to_string(), health: health.trim().to_string() })
+        .collect()
+}
+
+/// Acquires the health of each ZFS pool.
+/// Returns None when ZFS isn't installed.
+pub fn acquire_zfs_pool_health() -> io::Result<Option<Vec<ZfsPool>>> {
+    let output = match ext::run_command("zpool", &["list", "-H", "-o", "name,health"], Duration::from_secs(2))? {
+        Some(output) => output,
+        None => return Ok(None),
+    };
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(io::Error::other(stderr.trim().to_string()));
+    }
+    Ok(Some(parse_zpool_list(&String::from_utf8_lossy(&output.stdout))))
+}
+
+/// Parses the output of `systemctl list-units --state=failed --no-legend --no-pager`
+/// into the failed units' names. systemctl may start each line with a "●".
+fn parse_failed_units(output: &str) -> Vec<String> {
+    output.lines()
+        .filter_map(|line| line.split_whitespace().find(|field| *field != "●" && *field != "*"))
+        .map(str::to_string)
+        .collect()
+}
+
+/// Acquires the names of the systemd units that have failed, e.g. "nginx.service".
+/// Returns None when systemd isn't installed, or isn't what booted the system.
+pub fn acquire_systemd_failed_units(proc_fs: &ProcFs) -> io::Result<Option<Vec<String>>> {
+    // The same check as sd_booted(), so containers without systemd running stay quiet.
+    if !proc_fs.systemd_run_dir().is_dir() {
+        return Ok(None);
+    }
+    let args = ["list-units", "--state=failed", "--no-legend", "--no-pager"];
+    let output = match ext::run_command("systemctl", &args, Duration::from_secs(2))? {
+        Some(output) => output,
+        None => return Ok(None),
+    };
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(io::Error::other(stderr.trim().to_string()));
+    }
+    Ok(Some(parse_failed_units(&String::from_utf8_lossy(&output.stdout))))
+}
+
+/// The state of one NVIDIA GPU.
+pub struct GpuStats {
+    /// The GPU's model name.
+    pub name: String,
+    /// How busy it is, as a percentage.
+    pub utilization_pct: u8,
+    /// Video memory in use, in MiB.
+    pub mem_used_mib: u64,
+    /// Video memory in total, in MiB.
+    pub mem_total_mib: u64,
+    /// Its temperature, in °C.
+    pub temp_c: f64,
+}
+
+/// Parses the output of `nvidia-smi --query-gpu=name,utilization.gpu,memory.used,
+/// memory.total,temperature.gpu --format=csv,noheader,nounits`, one GPU per line.
+/// GPUs that report a figure as "[N/A]" are left out.
+pub fn parse_nvidia_smi(output: &str) -> Vec<GpuStats> {
+    output.lines()
+        .filter_map(|line| {
+            // The name comes first, so split from the right in case it has a comma in it.
+            let mut fields = line.rsplitn(5, ',').map(str::trim);
+            let temp_c = fields.next()?.parse().ok()?;
+            let mem_total_mib = fields.next()?.parse().ok()?;
+            let mem_used_mib = fields.next()?.parse().ok()?;
+            let utilization_pct = fields.next()?.parse().ok()?;
+            let name = fields.next()?.to_string();
+            Some(GpuStats { name, utilization_pct, mem_used_mib, mem_total_mib, temp_c })
+        })
+        .collect()
+}
+
+/// Acquires the utilization, memory and temperature of each NVIDIA GPU.
+/// Returns None when `nvidia-smi` isn't installed.
+pub fn acquire_nvidia_gpu_stats() -> io::Result<Option<Vec<GpuStats>>> {
+    let query = "--query-gpu=name,utilization.gpu,memory.used,memory.total,temperature.gpu";
+    let args = [query, "--format=csv,noheader,nounits"];
+    let output = match ext::run_command("nvidia-smi", &args, Duration::from_secs(2))? {
+        Some(output) => output,
+        None => return Ok(None),
+    };
+    if !output.status.success() {
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        // nvidia-smi explains itself on stdout, e.g. when the driver isn't loaded.
+        let message = if stderr.trim().is_empty() { stdout } else { stderr };
+        return Err(io::Error::other(message.trim().to_string()));
+    }
+    Ok(Some(parse_nvidia_smi(&String::from_utf8_lossy(&output.stdout))))
+}
+
+/// Network traffic recorded by vnStat, in GiB.
+pub struct VnstatInfo {
+    /// Received today.
+    pub daily_rx_gib: f64,
+    /// Sent today.
+    pub daily_tx_gib: f64,
+    /// Received this month.
+    pub monthly_rx_gib: f64,
+    /// Sent this month.
+    pub monthly_tx_gib: f64,
+}
+
+/// Parses the output of `vnstat --json` for `iface`, taking the most recent
+/// day and month. JSON version 1 counts KiB, while version 2 counts bytes.
+pub fn parse_vnstat_json(json: &str, iface: &str) -> Option<VnstatInfo> {
+    let json: serde_json::Value = serde_json::from_str(json).ok()?;
+    let unit = if json["jsonversion"] == "1" { 1024.0 } else { 1.0 };
+    let interface = json["interfaces"].as_array()?.iter()
+        .find(|interface| interface["name"] == iface || interface["id"] == iface)?;
+    let traffic = &interface["traffic"];
+    let to_gib = |bytes: &serde_json::Value| bytes.as_f64().unwrap_or(0.0) * unit / (1024.0 * 1024.0 * 1024.0);
+    // vnStat 1 calls these "days" and "months".
+    let latest = |a: &str, b: &str| traffic[a].as_array().or(traffic[b].as_array())?.last().cloned();
+    let day = latest("day", "days").unwrap_or_default();
+    let month = latest("month", "months").unwrap_or_default();
+    Some(VnstatInfo {
+        daily_rx_gib: to_gib(&day["rx"]),
+        daily_tx_gib: to_gib(&day["tx"]),
+        monthly_rx_gib: to_gib(&month["rx"]),
+        monthly_tx_gib: to_gib(&month["tx"]),
+    })
+}
+
+/// Acquires today's and this month's traffic on `iface` from vnStat, giving it two seconds.
+/// Returns None when vnStat isn't installed.
+pub fn acquire_vnstat_traffic(iface: &str) -> io::Result<Option<VnstatInfo>> {
+    let args = ["--json", "-i", iface, "-d", "1", "-m", "1"];
+    let output = match ext::run_command("vnstat", &args, Duration::from_secs(2))? {
+        Some(output) => output,
+        None => return Ok(None),
+    };
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(io::Error::other(stderr.trim().to_string()));
+    }
+    match parse_vnstat_json(&String::from_utf8_lossy(&output.stdout), iface) {
+        Some(info) => Ok(Some(info)),
+        None => Err(io::Error::other(format!("couldn't understand vnstat's output for {iface}"))),
+    }
+}
+
+/// Parses a /dev/kmsg record such as "3,1234,5678901,-;usb 1-1: device not accepting address",
+/// giving its log level (0 is the most severe) and message.
+#[cfg(target_os = "linux")]
+pub fn parse_kmsg_record(record: &str) -> Option<(u8, String)> {
+    let (header, message) = record.split_once(';')?;
+    let priority: u32 = header.split(',').next()?.parse().ok()?;
+    // The priority also encodes the facility, above the lowest three bits.
+    let level = (priority & 7) as u8;
+    // Lines after the first hold key=value details, not the message.
+    let message = message.lines().next().unwrap_or("").trim();
+    Some((level, message.to_string()))
+}
+
+/// Acquires the last `n` kernel log messages at error level or worse, from /dev/kmsg.
+/// Gives nothing, rather than an error, when the log can't be read, since
+/// many systems only let root read it.
+#[cfg(target_os = "linux")]
+pub fn acquire_dmesg_errors(proc_fs: &ProcFs, n: usize) -> io::Result<Vec<String>> {
+    use std::io::Read;
+    use std::os::unix::fs::OpenOptionsExt;
+    let mut kmsg = match fs::OpenOptions::new().read(true).custom_flags(libc::O_NONBLOCK).open(proc_fs.kmsg_path()) {
+        Ok(kmsg) => kmsg,
+        Err(err) if matches!(err.kind(), io::ErrorKind::PermissionDenied | io::ErrorKind::NotFound) => {
+            return Ok(Vec::new());
+        },
+        Err(err) => return Err(err),
+    };
+    // Each read gives one record, until there are none left to read.
+    let mut errors = std::collections::VecDeque::with_capacity(n + 1);
+    let mut buf = vec![0u8; 8192];
+    loop {
+        match kmsg.read(&mut buf) {
+            Ok(0) => break,
+            Ok(len) => {
+                let record = String::from_utf8_lossy(&buf[..len]);
+                if let Some((level, message)) = parse_kmsg_record(&record) {
+                    if level <= 3 {
+                        errors.push_back(message);
+                        if errors.len() > n {
+                            errors.pop_front();
+                        }
+                    }
+                }
+            },
+            Err(err) if err.kind() == io::ErrorKind::WouldBlock => break,
+            // Records were overwritten while reading; carry on from the oldest left.
+            Err(err) if err.raw_os_error() == Some(libc::EPIPE) => continue,
+            Err(err) if err.kind() == io::ErrorKind::PermissionDenied => return Ok(Vec::new()),
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(errors.into())
+}
+
+/// There's no /dev/kmsg elsewhere, so no kernel errors are shown.
+#[cfg(not(target_os = "linux"))]
+pub fn acquire_dmesg_errors(_proc_fs: &ProcFs, _n: usize) -> io::Result<Vec<String>> {
+    Ok(Vec::new())
+}
+
+/// An NFS share mounted on this machine.
+pub struct NfsMount {
+    /// e.g. "fileserver:/export/home"
+    pub remote: String,
+    /// e.g. "/mnt/home"
+    pub local: String,
+    /// Whether the server answered in time.
+    pub reachable: bool,
+}
+
+/// Undoes the octal escapes /proc/mounts uses for spaces and the like, e.g. "\040".
+#[cfg(target_os = "linux")]
+fn unescape_mount_path(path: &str) -> String {
+    let mut unescaped = String::with_capacity(path.len());
+    let mut rest = path;
+    while let Some(index) = rest.find('\\') {
+        unescaped.push_str(&rest[..index]);
+        let escape = rest.get(index + 1..index + 4).and_then(|digits| u8::from_str_radix(digits, 8).ok());
+        match escape {
+            Some(byte) => {
+                unescaped.push(byte as char);
+                rest = &rest[index + 4..];
+            },
+            None => {
+                unescaped.push('\\');
+                rest = &rest[index + 1..];
+            },
+        }
+    }
+    unescaped.push_str(rest);
+    unescaped
+}
+
+/// Finds the NFS mounts in the contents of /proc/mounts, as (remote, local) pairs.
+#[cfg(target_os = "linux")]
+pub fn parse_nfs_mounts(contents: &str) -> Vec<(String, String)> {
+    contents.lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let (remote, local, fstype) = (fields.next()?, fields.next()?, fields.next()?);
+            matches!(fstype, "nfs" | "nfs4")
+                .then(|| (unescape_mount_path(remote), unescape_mount_path(local)))
+        })
+        .collect()
+}
+
+/// Acquires the NFS mounts from /proc/mounts, and whether each one responds.
+/// A stale mount can hang anything that touches it, so each is checked on its
+/// own thread and counts as unreachable if it doesn't answer within a second.
+#[cfg(target_os = "linux")]
+pub fn acquire_nfs_mounts(proc_fs: &ProcFs) -> io::Result<Vec<NfsMount>> {
+    let contents = fs::read_to_string(proc_fs.mounts_path())?;
+    let checks: Vec<_> = parse_nfs_mounts(&contents).into_iter()
+        .map(|(remote, local)| {
+            let (sender, receiver) = std::sync::mpsc::channel();
+            let path = proc_fs.path(&local);
+            std::thread::spawn(move || {
+                let _ = sender.send(fs::metadata(path).is_ok());
+            });
+            (remote, local, receiver)
+        })
+        .collect();
+    let deadline = std::time::Instant::now() + Duration::from_secs(1);
+    Ok(checks.into_iter()
+        .map(|(remote, local, receiver)| {
+            let remaining = deadline.saturating_duration_since(std::time::Instant::now());
+            let reachable = receiver.recv_timeout(remaining).unwrap_or(false);
+            NfsMount { remote, local, reachable }
+        })
+        .collect())
+}
+
+/// There's no /proc/mounts elsewhere, so no NFS mounts are shown.
+#[cfg(not(target_os = "linux"))]
+pub fn acquire_nfs_mounts(_proc_fs: &ProcFs) -> io::Result<Vec<NfsMount>> {
+    Ok(Vec::new())
+}
+
+/// The state of a Git working tree.
+pub struct GitStatus {
+    /// The checked out branch, or "HEAD" when it is detached.
+    pub branch: String,
+    /// How many files have uncommitted changes (including untracked files).
+    pub modified: usize,
+}
+
+/// Parses the output of `git rev-parse --abbrev-ref HEAD` and `git status --porcelain`,
+/// which lists each file with uncommitted changes on a line of its own.
+fn parse_git_status(rev_parse: &str, porcelain: &str) -> GitStatus {
+    GitStatus {
+        branch: rev_parse.trim().to_string(),
+        modified: porcelain.lines().filter(|line| !line.trim().is_empty()).count(),
+    }
+}
+
+/// Acquires the current branch of the Git repository at `path`, and how many
+/// files in it have uncommitted changes. Each git command is given a second.
+/// Returns None when git isn't installed or `path` isn't a Git repository.
+pub fn acquire_git_status(path: &str) -> io::Result<Option<GitStatus>> {
+    let timeout = Duration::from_secs(1);
+    let rev_parse = match ext::run_command("git", &["-C", path, "rev-parse", "--abbrev-ref", "HEAD"], timeout)? {
+        Some(output) if output.status.success() => output,
+        _ => return Ok(None),
+    };
+    let status = match ext::run_command("git", &["-C", path, "status", "--porcelain"], timeout)? {
+        Some(output) if output.status.success() => output,
+        _ => return Ok(None),
+    };
+    Ok(Some(parse_git_status(
+        &String::from_utf8_lossy(&rev_parse.stdout),
+        &String::from_utf8_lossy(&status.stdout),
+    )))
+}
+
+/// How many package updates are waiting to be installed.
+pub struct UpdateInfo {
+    /// Updates waiting, security updates included.
+    pub total: u32,
+    /// Security updates among `total`, or 0 if the package manager doesn't say.
+    pub security: u32,
+}
+
+/// Counts the upgrades listed by `apt-get --simulate upgrade`, i.e. its "Inst" lines.
+/// Upgrades coming from a "-security" suite are security updates.
+fn parse_apt_simulate(output: &str) -> UpdateInfo {
+    let upgrades: Vec<&str> = output.lines().filter(|line| line.starts_with("Inst ")).collect();
+    UpdateInfo {
+        total: upgrades.len() as u32,
+        security: upgrades.iter().filter(|line| line.contains("-security")).count() as u32,
+    }
+}
+
+/// Counts the updates listed by `dnf check-update`, i.e. its "name.arch version repo" lines.
+/// dnf doesn't say which are security updates here.
+fn parse_dnf_check_update(output: &str) -> UpdateInfo {
+    let total = output.lines()
+        .take_while(|line| !line.starts_with("Obsoleting"))
+        .filter(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            fields.len() == 3 && fields[0].contains('.')
+        })
+        .count();
+    UpdateInfo { total: total as u32, security: 0 }
+}
+
+/// Acquires how many package updates are pending, by asking the package manager.
+/// These commands can be slow, so they're killed after RWELCOME_UPDATES_TIMEOUT_SECS
+/// seconds (5 by default). Returns None when no known package manager (or its
+/// update checker) is found.
+pub fn acquire_pending_updates(proc_fs: &ProcFs) -> io::Result<Option<UpdateInfo>> {
+    let timeout = Duration::from_secs(ext::parse_env_number("RWELCOME_UPDATES_TIMEOUT_SECS", 5));
+    match detect_package_manager(proc_fs) {
+        Some(PackageManager::Apt) => {
+            let output = ext::run_command("apt-get", &["--simulate", "upgrade"], timeout)?;
+            Ok(output.map(|output| parse_apt_simulate(&String::from_utf8_lossy(&output.stdout))))
+        },
+        Some(PackageManager::Pacman) => {
+            // checkupdates (from pacman-contrib) prints one update per line.
+            let output = ext::run_command("checkupdates", &[], timeout)?;
+            Ok(output.map(|output| UpdateInfo {
+                total: String::from_utf8_lossy(&output.stdout).lines().count() as u32,
+                security: 0,
+            }))
+        },
+        Some(PackageManager::Dnf) => {
+            let output = ext::run_command("dnf", &["check-update", "--quiet"], timeout)?;
+            Ok(output.map(|output| parse_dnf_check_update(&String::from_utf8_lossy(&output.stdout))))
+        },
+        None => Ok(None),
+    }
+}
+
+/// How many containers are running under each container engine that's available.
+pub struct ContainerInfo {
+    /// Containers running under Docker, or None if it isn't available.
+    pub docker: Option<u32>,
+    /// Containers running under Podman, or None if it isn't available.
+    pub podman: Option<u32>,
+}
+
+/// Counts the running containers of the Docker-compatible API listening on
+/// the Unix socket at `socket_path`, by asking it for GET /containers/json.
+/// Returns None when there's no socket there.
+fn count_containers(socket_path: &Path) -> io::Result<Option<u32>> {
+    if !socket_path.exists() {
+        return Ok(None);
+    }
+    let mut stream = UnixStream::connect(socket_path)?;
+    stream.set_read_timeout(Some(Duration::from_secs(1)))?;
+    stream.set_write_timeout(Some(Duration::from_secs(1)))?;
+    // HTTP/1.0, so the response isn't chunked and ends when the connection closes.
+    stream.write_all(b"GET /containers/json HTTP/1.0\r\nHost: localhost\r\n\r\n")?;
+    let mut response = String::new();
+    io::Read::read_to_string(&mut stream, &mut response)?;
+    let invalid = || io::Error::new(io::ErrorKind::InvalidData, "invalid container engine response");
+    let (head, body) = response.split_once("\r\n\r\n").ok_or_else(invalid)?;
+    if head.split_whitespace().nth(1) != Some("200") {
+        return Err(invalid());
+    }
+    let containers: Vec<serde_json::Value> = serde_json::from_str(body).map_err(|_| invalid())?;
+    Ok(Some(containers.len() as u32))
+}
+
+/// The per-user Podman socket: in $XDG_RUNTIME_DIR, or /run/user/<uid>, where
+/// the uid is the owner of /proc/self under the root.
+fn podman_socket_path(proc_fs: &ProcFs) -> Option<PathBuf> {
+    let runtime_dir = match env::var("XDG_RUNTIME_DIR") {
+        Ok(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => proc_fs.user_runtime_dir(fs::metadata(proc_fs.path("/proc/self")).ok()?.uid()),
+    };
+    Some(runtime_dir.join("podman").join("podman.sock"))
+}
+
+/// Acquires how many containers are running under Docker and Podman.
+/// Returns None when neither is available. An engine whose socket exists but
+/// can't be queried (e.g. for lack of permission) only causes an error if the
+/// other engine isn't available either.
+pub fn acquire_running_containers(proc_fs: &ProcFs) -> io::Result<Option<ContainerInfo>> {
+    let docker = count_containers(&proc_fs.docker_socket_path());
+    let podman = match podman_socket_path(proc_fs) {
+        Some(path) => count_containers(&path),
+        None => Ok(None),
+    };
+    match (docker, podman) {
+        (Err(err), Ok(None)) | (Ok(None), Err(err)) | (Err(err), Err(_)) => Err(err),
+        (Ok(None), Ok(None)) => Ok(None),
+        (docker, podman) => Ok(Some(ContainerInfo {
+            docker: docker.ok().flatten(),
+            podman: podman.ok().flatten(),
+        })),
+    }
+}
+
+/// Acquires the machine's public IP address from <https://api.ipify.org>.
+/// The answer is cached for five minutes, and the request gives up after
+/// RWELCOME_PUBLIC_IP_TIMEOUT_SECS seconds (3 by default).
+#[cfg(feature = "weather")]
+pub async fn acquire_public_ip() -> io::Result<String> {
+    let cache_name = "public_ip.txt";
+    if let Some(ip) = ext::read_cache(cache_name, Duration::from_secs(300)) {
+        return Ok(ip.trim().to_string());
+    }
+    let timeout = Duration::from_secs(ext::parse_env_number("RWELCOME_PUBLIC_IP_TIMEOUT_SECS", 3));
+    let to_io_error = |err: reqwest::Error| io::Error::other(err);
+    let client = reqwest::Client::builder()
+        .timeout(timeout)
+        .build()
+        .map_err(to_io_error)?;
+    let ip = client.get("https://api.ipify.org")
+        .send().await.map_err(to_io_error)?
+        .text().await.map_err(to_io_error)?;
+    let ip = ip.trim().to_string();
+    ext::write_cache(cache_name, &ip);
+    Ok(ip)
+}
+
+/// Parses a single todo number, explaining what was wrong with it if it isn't one.
+fn parse_todo_number(value: &str) -> io::Result<usize> {
+    match value.trim().parse::<usize>() {
+        Ok(number) => Ok(number),
+        Err(_) => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("rwelcome: error: '{}' is not a valid todo number.", value.trim()),
+        )),
+    }
+}
+
+/// The error for a todo number that isn't in the list.
+fn out_of_range<T>(index: usize, list_len: usize) -> io::Result<T> {
+    io_err!(format!("index {index} is out of range (list has {list_len} items)"))
+}
+
+/// Parses the todo numbers given to `done`, e.g. "2", "2,3,4" or "1,3-5,7".
+/// Ranges are inclusive and must lie within `1..=list_len`, with start <= end.
+fn parse_todo_indices(spec: &str, list_len: usize) -> io::Result<Vec<usize>> {
+    let mut indices = Vec::<usize>::new();
+    for item in spec.split(',').map(str::trim) {
+        if let Some((start, end)) = item.split_once('-') {
+            let start = parse_todo_number(start)?;
+            let end = parse_todo_number(end)?;
+            if start > end {
+                return io_err!(format!("the range {start}-{end} is backwards."));
+            }
+            if start < 1 {
+                return out_of_range(start, list_len);
+            }
+            if end > list_len {
+                return out_of_range(end, list_len);
+            }
+            indices.extend(start..=end);
+        } else {
+            indices.push(parse_todo_number(item)?);
+        }
+    }
+    Ok(indices)
+}
+
+/// A change to the todo list, as given on the command line.
+pub enum TodoEdit {
+    /// Open the todos file in $EDITOR, e.g. rwelcome edit
+    Editor,
+    /// e.g. rwelcome edit add Get bagels (or rwelcome add Get bagels)
+    Add(String),
+    /// e.g. rwelcome edit done 2 (or rwelcome done 1,3-5)
+    Done(String),
+    /// e.g. rwelcome edit fix 2 Get more bagels
+    Fix(String, String),
+    /// Move a todo from one position to another, e.g. rwelcome edit move 5 1
+    Move(String, String),
+    /// Add a todo at a position, e.g. rwelcome edit insert 1 Pay rent
+    Insert(String, String),
+    /// Remove every todo, e.g. rwelcome edit clear
+    Clear,
+    /// Revert the last edit, e.g. rwelcome edit undo
+    Undo,
+    /// Re-apply the last undone edit, e.g. rwelcome edit redo
+    Redo,
+}
+
+/// The todos whose text contains `query`, ignoring case, with their numbers in the list.
+pub fn search_todos<'a>(todos: &'a [Todo], query: &str) -> Vec<(usize, &'a Todo)> {
+    let query = query.to_lowercase();
+    todos.iter()
+        .enumerate()
+        .filter(|(_, todo)| todo.text.to_lowercase().contains(&query))
+        .map(|(index, todo)| (index + 1, todo))
+        .collect()
+}
+
+/// Swaps the todos file with the saved copy at `from` (`.bak` or `.redo`),
+/// saving the current file as `to` so that the swap can itself be reversed.
+fn swap_todos_file(todos_path: &str, from: &str, to: &str) -> io::Result<()> {
+    fs::copy(todos_path, to)?;
+    fs::rename(from, todos_path)
+}
+
+/// Saves a copy of the bookkeeping file at `path` as `to`, or removes `to` if
+/// there's no such file yet, so that restoring `to` also removes the file.
+fn save_optional_file(path: &str, to: &str) -> io::Result<()> {
+    match fs::copy(path, to) {
+        Ok(_) => Ok(()),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => remove_if_exists(to),
+        Err(err) => Err(err),
+    }
+}
+
+/// Like `swap_todos_file()`, for a bookkeeping file saved with `save_optional_file()`.
+fn swap_optional_file(path: &str, from: &str, to: &str) -> io::Result<()> {
+    save_optional_file(path, to)?;
+    match fs::rename(from, path) {
+        Ok(()) => Ok(()),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => remove_if_exists(path),
+        Err(err) => Err(err),
+    }
+}
+
+fn remove_if_exists(path: &str) -> io::Result<()> {
+    match fs::remove_file(path) {
+        Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err),
+        _ => Ok(()),
+    }
+}
+
+/*
+ * Keeps the todos file (and its archive) as it is now as .bak for `edit undo`,
+ * which also makes any pending redo stale. Encrypted todos keep encrypted copies.
+ */
+fn back_up_todos(todos_path: &str) -> io::Result<()> {
+    let stored_path = stored_todos_path(todos_path)?;
+    let stored_archive = stored_todos_path(&archive_path(todos_path))?;
+    if fs::copy(&stored_path, format!("{stored_path}.bak")).is_err()
+        || save_optional_file(&stored_archive, &format!("{stored_archive}.bak")).is_err()
+    {
+        return io_err!("couldn't back up your todos file.");
+    }
+    let _ = fs::remove_file(format!("{stored_path}.redo"));
+    let _ = fs::remove_file(format!("{stored_archive}.redo"));
+    Ok(())
 }
 
 /// Displays an interface allowing the user to edit the todo list.
-/// If `wants_editor` is true, it will attempt to open an instance of
+/// For `TodoEdit::Editor`, it will attempt to open an instance of
 /// an appropriate text editor with the todos file loaded.
-/// Otherwise, it will attempt to parse action verbs supplied as additional arguments,
-/// e.g. rwelcome edit add Get bagels
-/// e.g. rwelcome edit done 2
+/// Otherwise, it applies the edit and writes the list back to the file,
+/// or with `dry_run`, prints what it would write instead.
 /// If anything goes wrong, it will return an Err containing an error
 /// message string that the caller can output to the user.
 pub async fn edit_todos(
-    current_todos: &mut Vec<String>,
-    wants_editor: bool,
-    args: &mut Vec<String>,
-    todos_path: String
-) -> io::Result<Vec<String>> {
-    if wants_editor {
-        let editor = env::var("EDITOR")
-                                .unwrap_or_else(|_| "vi".to_string());
-
-        print!("Open text editor ({editor})? (y/n)> ");
-        io::stdout().flush().expect("io::stdout.flush() failed...");
-        let mut input = String::new();
-        match io::stdin().read_line(&mut input) {
-            Ok(_) => {
-                if !input.trim().to_lowercase().starts_with("y") {
-                    return Ok(current_todos.clone());
+    current_todos: &mut Vec<Todo>,
+    edit: TodoEdit,
+    todos_path: String,
+    dry_run: bool,
+) -> io::Result<Vec<Todo>> {
+    if is_remote_todos(&todos_path) {
+        return io_err!("remote todo lists are read-only.");
+    }
+    if dry_run && matches!(edit, TodoEdit::Editor | TodoEdit::Undo | TodoEdit::Redo) {
+        return io_err!("--dry-run only works with add, done, fix, move, insert and clear.");
+    }
+    if !dry_run {
+        create_todos_file(&todos_path)?;
+    }
+    // Where each todo was in the file, so that comments can be kept with it.
+    let mut origins: Vec<Option<usize>> = (0..current_todos.len()).map(Some).collect();
+    // The todos completed by this edit, archived once the list has been written.
+    let mut completed = Vec::<Todo>::new();
+    // The archive is undone and redone along with the list.
+    let stored_archive = stored_todos_path(&archive_path(&todos_path))?;
+    match edit {
+        TodoEdit::Editor => {
+            if todos_encrypted()? {
+                return io_err!("encrypted todos can't be opened in an editor, since that would write them to disk unencrypted; use add, done and fix.");
+            }
+            let editor = env::var("EDITOR")
+                                    .unwrap_or_else(|_| "vi".to_string());
+
+            print!("Open text editor ({editor})? (y/n)> ");
+            if let Err(err) = io::stdout().flush() {
+                return io_err!(format!("couldn't write to stdout: {err}"));
+            }
+            let mut input = String::new();
+            if let Err(err) = io::stdin().read_line(&mut input) {
+                return io_err!(format!("problem reading input: details: {err}"));
+            }
+            if !input.trim().to_lowercase().starts_with("y") {
+                return Ok(current_todos.clone());
+            }
+            // Whatever happens in the editor can be undone.
+            back_up_todos(&todos_path)?;
+
+            let status = match std::process::Command::new(&editor)
+                .arg(todos_path.clone())
+                .status()
+            {
+                Ok(status) => status,
+                Err(err) => return io_err!(format!("failed to execute editor '{editor}': {err}")),
+            };
+            if !status.success() {
+                return io_err!("editor exited with non-zero status code");
+            }
+            return acquire_todos(todos_path).await;
+        },
+
+        TodoEdit::Undo => {
+            let stored_path = stored_todos_path(&todos_path)?;
+            let backup_path = format!("{stored_path}.bak");
+            if !Path::new(&backup_path).exists() {
+                return io_err!("nothing to undo.");
+            }
+            if swap_todos_file(&stored_path, &backup_path, &format!("{stored_path}.redo")).is_err()
+                || swap_optional_file(&stored_archive, &format!("{stored_archive}.bak"), &format!("{stored_archive}.redo")).is_err()
+            {
+                return io_err!("couldn't restore your previous todos.");
+            }
+            return acquire_todos(todos_path).await;
+        },
+
+        TodoEdit::Redo => {
+            let stored_path = stored_todos_path(&todos_path)?;
+            let redo_path = format!("{stored_path}.redo");
+            if !Path::new(&redo_path).exists() {
+                return io_err!("nothing to redo.");
+            }
+            if swap_todos_file(&stored_path, &redo_path, &format!("{stored_path}.bak")).is_err()
+                || swap_optional_file(&stored_archive, &format!("{stored_archive}.redo"), &format!("{stored_archive}.bak")).is_err()
+            {
+                return io_err!("couldn't re-apply your undone edit.");
+            }
+            return acquire_todos(todos_path).await;
+        },
+
+        TodoEdit::Done(the_rest) => {
+            let mut list_indices = parse_todo_indices(&the_rest, current_todos.len())?;
+            list_indices.sort_unstable();
+            list_indices.dedup();
+
+            // Remove in reverse order to avoid element shifting,
+            // preserving validity of user's given indices.
+            for list_index in list_indices.into_iter().rev() {
+                if list_index > current_todos.len() || list_index < 1 {
+                    return out_of_range(list_index, current_todos.len());
                 }
-            },
-            Err(err) => panic!("rwelcome: error: problem reading input: details: {}", err),
+                completed.push(current_todos.remove(list_index-1));
+                origins.remove(list_index-1);
+            }
+            completed.reverse();
+        },
+
+        TodoEdit::Fix(index, content) => {
+            let idx = parse_todo_number(&index)?;
+            if idx > current_todos.len() || idx < 1 {
+                return out_of_range(idx, current_todos.len());
+            }
+            current_todos[idx-1].text = content;
+        },
+
+        TodoEdit::Move(from, to) => {
+            let from = parse_todo_number(&from)?;
+            let to = parse_todo_number(&to)?;
+            for idx in [from, to] {
+                if idx > current_todos.len() || idx < 1 {
+                    return out_of_range(idx, current_todos.len());
+                }
+            }
+            let todo = current_todos.remove(from-1);
+            current_todos.insert(to-1, todo);
+            let origin = origins.remove(from-1);
+            origins.insert(to-1, origin);
+        },
+
+        TodoEdit::Insert(index, the_rest) => {
+            if the_rest.trim().is_empty() {
+                return io_err!("'insert' requires the todo text.");
+            }
+            // One past the end adds it to the bottom, like `add`.
+            let idx = parse_todo_number(&index)?;
+            if idx > current_todos.len() + 1 || idx < 1 {
+                return out_of_range(idx, current_todos.len());
+            }
+            let added = if ext::env_flag("RWELCOME_TODOS_TIMESTAMPS") {
+                Some(Local::now().date_naive())
+            } else {
+                None
+            };
+            current_todos.insert(idx-1, Todo { text: the_rest, added });
+            origins.insert(idx-1, None);
+        },
+
+        TodoEdit::Clear => {
+            current_todos.clear();
+            origins.clear();
+        },
+
+        TodoEdit::Add(the_rest) => {
+            // An empty line would end the todo list early, hiding everything after it.
+            if the_rest.trim().is_empty() {
+                return io_err!("'add' requires the todo text.");
+            }
+            // With RWELCOME_TODOS_TIMESTAMPS=1, new todos record the date they were added.
+            let added = if ext::env_flag("RWELCOME_TODOS_TIMESTAMPS") {
+                Some(Local::now().date_naive())
+            } else {
+                None
+            };
+            current_todos.push(Todo { text: the_rest, added });
+            origins.push(None);
+        },
+    }
+
+    // Comment lines aren't todos, so put them back where they were.
+    let lines: Vec<String> = current_todos.iter().map(Todo::to_line).collect();
+    let lines = match read_todos_file(&todos_path) {
+        Ok(contents) => keep_comments(&contents, &todos_comment_prefix(), lines, &origins),
+        Err(_) => lines,
+    };
+    if dry_run {
+        println!("[dry run] would write:");
+        for line in &lines {
+            println!("  {line}");
+        }
+        return Ok(current_todos.to_vec());
+    }
+
+    back_up_todos(&todos_path)?;
+    if let Err(err) = write_todos_file(&todos_path, &lines.join("\n")) {
+        return io_err!(format!("couldn't update your todos: {err}"));
+    }
+
+    // Only now that they're gone from the list are completed todos archived.
+    if !completed.is_empty() {
+        let today = Local::now().date_naive();
+        if record_archived(&todos_path, &completed, today).is_err() {
+            return io_err!("couldn't archive your completed todos.");
+        }
+        let completed_recurring: Vec<Todo> = completed.into_iter()
+            .filter(|todo| todo.recurrence().is_some())
+            .collect();
+        if !completed_recurring.is_empty() && record_recurring(&todos_path, &completed_recurring, today).is_err() {
+            return io_err!("couldn't record your recurring todos.");
+        }
+    }
+
+    Ok(current_todos.to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A ProcFs over a fresh temporary directory, which is kept alive alongside it.
+    fn fixture() -> (tempfile::TempDir, ProcFs) {
+        let root = tempfile::tempdir().unwrap();
+        let proc_fs = ProcFs::new(root.path());
+        (root, proc_fs)
+    }
+
+    /// Writes `contents` to `path` under `proc_fs`'s root, creating its directories.
+    fn write_fixture(proc_fs: &ProcFs, path: &str, contents: &str) {
+        let path = proc_fs.path(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents).unwrap();
+    }
+
+    #[test]
+    fn package_manager_is_found_by_its_database() {
+        let (_root, proc_fs) = fixture();
+        assert!(detect_package_manager(&proc_fs).is_none());
+        assert!(acquire_last_package_update(&proc_fs).unwrap().is_none());
+
+        write_fixture(&proc_fs, "/var/cache/apt/pkgcache.bin", "");
+        assert!(detect_package_manager(&proc_fs) == Some(PackageManager::Apt));
+        let updated = acquire_last_package_update(&proc_fs).unwrap().unwrap();
+        assert!((Local::now() - updated).num_seconds() < 60);
+    }
+
+    #[test]
+    fn containers_are_counted_through_the_docker_socket() {
+        let _lock = crate::ENV_LOCK.lock().unwrap_or_else(|err| err.into_inner());
+        let runtime_dir = env::var_os("XDG_RUNTIME_DIR");
+        let (root, proc_fs) = fixture();
+        env::set_var("XDG_RUNTIME_DIR", root.path().join("run/user/1000"));
+        let nothing = acquire_running_containers(&proc_fs);
+
+        // An engine answering one request with `containers`, on a socket at `socket`.
+        let engine = |socket: &Path, containers: &'static str| {
+            fs::create_dir_all(socket.parent().unwrap()).unwrap();
+            let _ = fs::remove_file(socket);
+            let listener = std::os::unix::net::UnixListener::bind(socket).unwrap();
+            std::thread::spawn(move || {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut request = [0; 256];
+                let _ = io::Read::read(&mut stream, &mut request);
+                let response = format!("HTTP/1.0 200 OK\r\nContent-Type: application/json\r\n\r\n{containers}");
+                stream.write_all(response.as_bytes()).unwrap();
+            })
         };
+        let docker_engine = engine(&proc_fs.docker_socket_path(), "[{}, {}]");
+        let docker = acquire_running_containers(&proc_fs);
+        docker_engine.join().unwrap();
 
-        let status = std::process::Command::new(editor)
-            .arg(todos_path.clone())
-            .status()
-            .expect("rwelcome: error: failed to execute editor");
-        if !status.success() {
-            return io_err!("editor exited with non-zero status code");
+        // Without XDG_RUNTIME_DIR, Podman's socket is under /run/user for /proc/self's owner.
+        env::remove_var("XDG_RUNTIME_DIR");
+        fs::create_dir_all(proc_fs.path("/proc/self")).unwrap();
+        let uid = fs::metadata(proc_fs.path("/proc/self")).unwrap().uid();
+        let podman_socket = proc_fs.user_runtime_dir(uid).join("podman/podman.sock");
+        let docker_engine = engine(&proc_fs.docker_socket_path(), "[{}]");
+        let podman_engine = engine(&podman_socket, "[{}, {}, {}]");
+        let both = acquire_running_containers(&proc_fs);
+        docker_engine.join().unwrap();
+        podman_engine.join().unwrap();
+        match runtime_dir {
+            Some(dir) => env::set_var("XDG_RUNTIME_DIR", dir),
+            None => env::remove_var("XDG_RUNTIME_DIR"),
         }
-        return acquire_todos(todos_path).await;
+
+        assert!(nothing.unwrap().is_none());
+        let containers = docker.unwrap().unwrap();
+        assert_eq!(containers.docker, Some(2));
+        assert_eq!(containers.podman, None);
+        let containers = both.unwrap().unwrap();
+        assert_eq!(containers.docker, Some(1));
+        assert_eq!(containers.podman, Some(3));
+    }
+
+    #[test]
+    fn failed_units_are_parsed_from_systemctl() {
+        let output = "\
+● nginx.service      loaded failed failed A high performance web server
+● backup.timer       loaded failed failed Nightly backup
+";
+        assert_eq!(parse_failed_units(output), ["nginx.service", "backup.timer"]);
+        // Without the bullet, as older systemctl versions print it.
+        assert_eq!(parse_failed_units("cups.service loaded failed failed CUPS\n"), ["cups.service"]);
+        assert!(parse_failed_units("").is_empty());
+    }
+
+    #[test]
+    fn zpool_list_gives_each_pools_health() {
+        let output = "tank\tONLINE\ndata\tDEGRADED\nbackup\tFAULTED\n";
+        let pools: Vec<(String, String)> = parse_zpool_list(output).into_iter()
+            .map(|pool| (pool.name, pool.health))
+            .collect();
+        assert_eq!(pools, [
+            ("tank".to_string(), "ONLINE".to_string()),
+            ("data".to_string(), "DEGRADED".to_string()),
+            ("backup".to_string(), "FAULTED".to_string()),
+        ]);
+        assert!(parse_zpool_list("").is_empty());
+        assert!(parse_zpool_list("no pools available\n").is_empty());
+    }
+
+    #[test]
+    fn git_status_counts_modified_files() {
+        let porcelain = " M .bashrc\nM  .config/nvim/init.lua\n?? .config/rwelcome/config.toml\n";
+        let dirty = parse_git_status("main\n", porcelain);
+        assert_eq!((dirty.branch.as_str(), dirty.modified), ("main", 3));
+        let clean = parse_git_status("HEAD\n", "");
+        assert_eq!((clean.branch.as_str(), clean.modified), ("HEAD", 0));
+    }
+
+    #[test]
+    fn motd_is_cut_to_max_lines_without_trailing_blank_lines() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("motd");
+        fs::write(&path, "Welcome to {host}, {user}.\n\nBackups run at 02:00.\n\n  \n").unwrap();
+        let path = path.to_str().unwrap();
+        assert_eq!(acquire_motd(path, 10).unwrap(), ["Welcome to {host}, {user}.", "", "Backups run at 02:00."]);
+        assert_eq!(acquire_motd(path, 2).unwrap(), ["Welcome to {host}, {user}."]);
+        assert!(acquire_motd(path, 0).unwrap().is_empty());
+        assert!(acquire_motd(dir.path().join("missing").to_str().unwrap(), 10).is_err());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn kmsg_records_are_filtered_to_errors() {
+        const KMSG: &[&str] = &[
+            "6,1021,5210345,-;usb 1-1: new high-speed USB device number 4 using xhci_hcd",
+            "3,1022,5213890,-;usb 1-1: device not accepting address 4, error -71",
+            "4,1023,5300112,-;EXT4-fs warning (device sda1): ext4_dx_add_entry: Directory index full!",
+            "2,1024,6011274,-;mce: [Hardware Error]: Machine check events logged\n SUBSYSTEM=cpu\n",
+            // Facility 3 (daemon) at level 3 is still an error.
+            "27,1025,6100000,-;systemd-journald[312]: Failed to write entry, ignoring",
+            "not a kmsg record",
+        ];
+        let errors: Vec<String> = KMSG.iter()
+            .filter_map(|record| parse_kmsg_record(record))
+            .filter(|(level, _)| *level <= 3)
+            .map(|(_, message)| message)
+            .collect();
+        assert_eq!(errors, [
+            "usb 1-1: device not accepting address 4, error -71",
+            "mce: [Hardware Error]: Machine check events logged",
+            "systemd-journald[312]: Failed to write entry, ignoring",
+        ]);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn kmsg_is_read_for_errors_only() {
+        let (_root, proc_fs) = fixture();
+        // Not being able to read the log isn't an error.
+        assert!(acquire_dmesg_errors(&proc_fs, 3).unwrap().is_empty());
+        // A regular file gives all of its records in one read, so these have one each.
+        write_fixture(&proc_fs, "/dev/kmsg", "3,1022,5213890,-;usb 1-1: device not accepting address 4, error -71\n");
+        assert_eq!(acquire_dmesg_errors(&proc_fs, 3).unwrap(), ["usb 1-1: device not accepting address 4, error -71"]);
+        write_fixture(&proc_fs, "/dev/kmsg", "6,1021,5210345,-;usb 1-1: new high-speed USB device number 4 using xhci_hcd\n");
+        assert!(acquire_dmesg_errors(&proc_fs, 3).unwrap().is_empty());
     }
 
-    assert!(args.len() > 2);
-    let verb = &args[2];
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn nfs_mounts_are_reachable_if_their_mount_point_answers() {
+        let (_root, proc_fs) = fixture();
+        write_fixture(&proc_fs, "/proc/mounts", "\
+/dev/nvme0n1p2 / ext4 rw,relatime 0 0
+fileserver:/export/home /mnt/home nfs4 rw,vers=4.2 0 0
+backup:/srv/old\\040photos /mnt/old\\040photos nfs rw,vers=3 0 0
+");
+        fs::create_dir_all(proc_fs.path("/mnt/home")).unwrap();
+        let mounts: Vec<(String, String, bool)> = acquire_nfs_mounts(&proc_fs).unwrap().into_iter()
+            .map(|mount| (mount.remote, mount.local, mount.reachable))
+            .collect();
+        assert_eq!(mounts, [
+            ("fileserver:/export/home".to_string(), "/mnt/home".to_string(), true),
+            ("backup:/srv/old photos".to_string(), "/mnt/old photos".to_string(), false),
+        ]);
+    }
 
-    if verb == "done" || verb == "check" {
-        let the_rest = args[3..].join(" ");
+    #[test]
+    fn failed_units_need_systemd_running() {
+        let (_root, proc_fs) = fixture();
+        assert!(acquire_systemd_failed_units(&proc_fs).unwrap().is_none());
+    }
 
-        let list_indices_or_err: Result<Vec<i32>, _> = the_rest
-                                .split(",")
-                                .map(|s| s.parse::<i32>())
-                                .collect();
+    #[test]
+    fn thermal_zone_temperature_is_in_millidegrees() {
+        let _lock = crate::ENV_LOCK.lock().unwrap_or_else(|err| err.into_inner());
+        env::remove_var("RWELCOME_THERMAL_ZONE_PATH");
+        let (_root, proc_fs) = fixture();
+        assert!(acquire_thermal_zone_temperature(&proc_fs).is_err());
+        // thermal_zone0 is missing, so the next zone is used.
+        write_fixture(&proc_fs, "/sys/class/thermal/thermal_zone1/temp", "45000\n");
+        assert_eq!(acquire_thermal_zone_temperature(&proc_fs).unwrap(), 45.0);
+    }
 
-        if let Err(_) = list_indices_or_err {
-            return io_err!("you should supply a number to mark as done.");
+    #[test]
+    fn cpu_temperature_is_labelled_by_its_sensor() {
+        let _lock = crate::ENV_LOCK.lock().unwrap_or_else(|err| err.into_inner());
+        for var in ["RWELCOME_CPU_TEMP_PATH", "RWELCOME_CPU_TEMP_AGGREGATE", "RWELCOME_PREFER_LMSENSORS"] {
+            env::remove_var(var);
         }
+        let (_root, proc_fs) = fixture();
+        write_fixture(&proc_fs, "/sys/class/hwmon/hwmon1/temp1_input", "50000\n");
+        write_fixture(&proc_fs, "/sys/class/hwmon/hwmon1/temp2_input", "74000\n");
+        let temp = acquire_cpu_temperature(&proc_fs).unwrap();
+        assert_eq!((temp.value, temp.label), (74.0, None));
 
-        let list_indices: Vec<usize> = list_indices_or_err.unwrap().iter().map(|int| *int as usize).collect();
+        write_fixture(&proc_fs, "/sys/class/hwmon/hwmon1/temp2_label", "Package id 0\n");
+        let temp = acquire_cpu_temperature(&proc_fs).unwrap();
+        assert_eq!((temp.value, temp.label.as_deref()), (74.0, Some("Package id 0")));
 
-        // Remove in reverse order to avoid element shifting,
-        // preserving validity of user's given indices.
-        for i in (0..list_indices.len()).rev() {
-            let list_index = list_indices[i];
-            if list_index > current_todos.len() || list_index < 1 {
-                return io_err!("please choose a number that's in the list.");
-            }
-            current_todos.remove(list_index-1);
+        // An empty label is left out, and the reading is still shown.
+        write_fixture(&proc_fs, "/sys/class/hwmon/hwmon1/temp2_label", "\n");
+        assert_eq!(acquire_cpu_temperature(&proc_fs).unwrap().label, None);
+    }
+
+    #[test]
+    fn thermal_zone_path_can_be_the_zone_or_its_temp_file() {
+        let _lock = crate::ENV_LOCK.lock().unwrap_or_else(|err| err.into_inner());
+        let (_root, proc_fs) = fixture();
+        write_fixture(&proc_fs, "/sys/class/thermal/thermal_zone5/temp", "45000\n");
+        let zone = proc_fs.thermal_dir().join("thermal_zone5");
+        env::set_var("RWELCOME_THERMAL_ZONE_PATH", &zone);
+        let from_dir = acquire_thermal_zone_temperature(&proc_fs);
+        env::set_var("RWELCOME_THERMAL_ZONE_PATH", zone.join("temp"));
+        let from_file = acquire_thermal_zone_temperature(&proc_fs);
+        env::remove_var("RWELCOME_THERMAL_ZONE_PATH");
+        assert_eq!(from_dir.unwrap(), 45.0);
+        assert_eq!(from_file.unwrap(), 45.0);
+    }
+
+    #[cfg(not(feature = "encrypt"))]
+    #[test]
+    fn encryption_needs_the_encrypt_feature() {
+        let _lock = crate::ENV_LOCK.lock().unwrap_or_else(|err| err.into_inner());
+        env::set_var("RWELCOME_TODOS_ENCRYPT", "gpg");
+        let stored = stored_todos_path("todos");
+        env::remove_var("RWELCOME_TODOS_ENCRYPT");
+        let err = stored.unwrap_err();
+        assert!(err.to_string().contains("encrypt feature"), "{err}");
+    }
+
+    #[cfg(feature = "encrypt")]
+    #[test]
+    fn encrypted_todos_refuse_a_plain_copy_alongside() {
+        let _lock = crate::ENV_LOCK.lock().unwrap_or_else(|err| err.into_inner());
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("todos");
+        fs::write(&path, "buy milk\n").unwrap();
+        fs::write(dir.path().join("todos.gpg"), "not really encrypted").unwrap();
+        env::set_var("RWELCOME_TODOS_ENCRYPT", "gpg");
+        let read = read_todos_file(path.to_str().unwrap());
+        env::remove_var("RWELCOME_TODOS_ENCRYPT");
+        let err = read.unwrap_err();
+        assert!(err.to_string().contains("both"), "{err}");
+    }
+
+    #[test]
+    fn missing_lists_are_created_by_edits_not_reads() {
+        let _lock = crate::ENV_LOCK.lock().unwrap_or_else(|err| err.into_inner());
+        let dir = tempfile::tempdir().unwrap();
+        let default = dir.path().join("lists/todos").to_str().unwrap().to_string();
+        let path = todos_list_path(&default, Some("typo")).unwrap();
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+
+        let todos = runtime.block_on(acquire_todos(path.clone())).unwrap();
+        assert!(todos.is_empty());
+        assert!(!Path::new(&path).exists());
+
+        // The default list is made on first run, directory and all, and read as empty.
+        let todos = runtime.block_on(acquire_default_todos(default.clone())).unwrap();
+        assert!(todos.is_empty());
+        assert_eq!(fs::read_to_string(&default).unwrap(), "# Add your todos here, one per line\n");
+        fs::write(&default, "buy bread\n").unwrap();
+        let todos = runtime.block_on(acquire_default_todos(default)).unwrap();
+        assert_eq!(todos.len(), 1);
+        assert!(!Path::new(&path).exists());
+
+        let edit = TodoEdit::Add("buy milk".to_string());
+        runtime.block_on(edit_todos(&mut Vec::new(), edit, path.clone(), false)).unwrap();
+        assert!(fs::read_to_string(&path).unwrap().contains("# Add your todos here"));
+        let todos = runtime.block_on(acquire_todos(path)).unwrap();
+        assert_eq!(todos.iter().map(|todo| todo.text.as_str()).collect::<Vec<_>>(), ["buy milk"]);
+    }
+
+    #[test]
+    fn named_lists_live_next_to_the_default_list() {
+        let base = "/home/anthony/.local/share/rwelcome/todos";
+        assert_eq!(todos_list_path(base, None).unwrap(), base);
+        assert_eq!(todos_list_path(base, Some("work")).unwrap(), format!("{base}.work"));
+        assert_eq!(todos_list_path(base, Some("side_project-2")).unwrap(), format!("{base}.side_project-2"));
+    }
+
+    #[test]
+    fn list_names_cant_leave_the_todos_directory() {
+        let base = "/home/anthony/.local/share/rwelcome/todos";
+        for name in ["", "../../etc/passwd", "work/notes", "work.bak", "archive", "recurring"] {
+            assert!(todos_list_path(base, Some(name)).is_err(), "{name:?}");
         }
     }
 
-    else if verb == "fix" {
-        if args.len() < 5 {
-            return io_err!("'fix' requires a todo list number, and the new todo text.");
+    #[test]
+    fn lists_are_found_without_their_bookkeeping_files() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["todos", "todos.work", "todos.work.bak", "todos.personal.gpg", "todos.recurring", "notes"] {
+            fs::write(dir.path().join(name), "").unwrap();
         }
-        let idx = args[3]
-                .parse::<i32>()
-                .or_else(|_| io_err!("'fix' requires a todo list number, and the new todo text."))?
-                as usize;
-        let content = args[4..].join(" ");
-        if idx > current_todos.len() || idx < 1 {
-            return io_err!("please choose a todo list number that's in the list.");
+        let base = dir.path().join("todos");
+        assert_eq!(acquire_todo_lists(base.to_str().unwrap()).unwrap(), ["default", "personal", "work"]);
+    }
+
+    #[test]
+    fn done_accepts_a_range() {
+        assert_eq!(parse_todo_indices("2-5", 6).unwrap(), [2, 3, 4, 5]);
+        assert_eq!(parse_todo_indices("3-3", 3).unwrap(), [3]);
+    }
+
+    #[test]
+    fn done_accepts_ranges_mixed_with_numbers() {
+        assert_eq!(parse_todo_indices("1,3-5,7", 7).unwrap(), [1, 3, 4, 5, 7]);
+        assert_eq!(parse_todo_indices(" 6 , 1-2 ", 7).unwrap(), [6, 1, 2]);
+    }
+
+    #[test]
+    fn done_rejects_bad_ranges() {
+        let backwards = parse_todo_indices("5-2", 6).unwrap_err();
+        assert_eq!(backwards.to_string(), "rwelcome: error: the range 5-2 is backwards.");
+        let past_the_end = parse_todo_indices("4-9", 6).unwrap_err();
+        assert_eq!(past_the_end.to_string(), "rwelcome: error: index 9 is out of range (list has 6 items)");
+        assert!(parse_todo_indices("0-2", 6).is_err());
+        assert!(parse_todo_indices("2-x", 6).is_err());
+    }
+
+    #[test]
+    fn comment_prefix_can_be_changed() {
+        let _lock = crate::ENV_LOCK.lock().unwrap_or_else(|err| err.into_inner());
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("todos").to_str().unwrap().to_string();
+        fs::write(&path, "// work\nbuy milk\n# not a comment\n; nor this\n").unwrap();
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let texts = |todos: Vec<Todo>| todos.into_iter().map(|todo| todo.text).collect::<Vec<_>>();
+
+        env::set_var("RWELCOME_TODOS_COMMENT_CHAR", "//");
+        let todos = runtime.block_on(acquire_todos(path.clone())).unwrap();
+        assert_eq!(texts(todos), ["buy milk", "# not a comment", "; nor this"]);
+
+        // A blank prefix falls back to #.
+        env::set_var("RWELCOME_TODOS_COMMENT_CHAR", "  ");
+        let todos = runtime.block_on(acquire_todos(path));
+        env::remove_var("RWELCOME_TODOS_COMMENT_CHAR");
+        assert_eq!(texts(todos.unwrap()), ["// work", "buy milk", "; nor this"]);
+    }
+
+    #[test]
+    fn comments_survive_edits() {
+        let _lock = crate::ENV_LOCK.lock().unwrap_or_else(|err| err.into_inner());
+        env::remove_var("RWELCOME_TODOS_COMMENT_CHAR");
+        env::remove_var("RWELCOME_TODOS_TIMESTAMPS");
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("todos").to_str().unwrap().to_string();
+        fs::write(&path, "# Work todos\nbuy milk\n# they wilt by Friday\nwater plants\ncall mum\n# end of list\n").unwrap();
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+
+        let mut todos = runtime.block_on(acquire_todos(path.clone())).unwrap();
+        let edit = TodoEdit::Add("plan sprint".to_string());
+        runtime.block_on(edit_todos(&mut todos, edit, path.clone(), false)).unwrap();
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "# Work todos\nbuy milk\n# they wilt by Friday\nwater plants\ncall mum\nplan sprint\n# end of list",
+        );
+
+        // A comment above a completed todo moves down to the next one.
+        let mut todos = runtime.block_on(acquire_todos(path.clone())).unwrap();
+        runtime.block_on(edit_todos(&mut todos, TodoEdit::Done("2".to_string()), path.clone(), false)).unwrap();
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "# Work todos\nbuy milk\n# they wilt by Friday\ncall mum\nplan sprint\n# end of list",
+        );
+    }
+
+    #[test]
+    fn dry_runs_dont_write_anything() {
+        let _lock = crate::ENV_LOCK.lock().unwrap_or_else(|err| err.into_inner());
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("todos").to_str().unwrap().to_string();
+        fs::write(&path, "buy milk\nwater plants\ncall mum\n").unwrap();
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+
+        let mut todos = runtime.block_on(acquire_todos(path.clone())).unwrap();
+        let edit = TodoEdit::Done("1-2".to_string());
+        let after = runtime.block_on(edit_todos(&mut todos, edit, path.clone(), true)).unwrap();
+        assert_eq!(after.iter().map(|todo| todo.text.as_str()).collect::<Vec<_>>(), ["call mum"]);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "buy milk\nwater plants\ncall mum\n");
+        for bookkeeping in ["bak", "archive"] {
+            assert!(!Path::new(&format!("{path}.{bookkeeping}")).exists(), "{bookkeeping}");
+        }
+
+        let undo = runtime.block_on(edit_todos(&mut Vec::new(), TodoEdit::Undo, path.clone(), true));
+        assert!(undo.is_err());
+
+        // Nor is a missing list created.
+        let missing = dir.path().join("todos.work").to_str().unwrap().to_string();
+        let edit = TodoEdit::Add("plan sprint".to_string());
+        runtime.block_on(edit_todos(&mut Vec::new(), edit, missing.clone(), true)).unwrap();
+        assert!(!Path::new(&missing).exists());
+    }
+
+    #[test]
+    fn undoing_done_takes_todos_back_out_of_the_archive() {
+        let _lock = crate::ENV_LOCK.lock().unwrap_or_else(|err| err.into_inner());
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("todos").to_str().unwrap().to_string();
+        fs::write(&path, "buy milk\nwater plants\n").unwrap();
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let archived = || acquire_archived_todos(&path).unwrap().into_iter()
+            .map(|archived| archived.todo.text)
+            .collect::<Vec<_>>();
+
+        let mut todos = runtime.block_on(acquire_todos(path.clone())).unwrap();
+        runtime.block_on(edit_todos(&mut todos, TodoEdit::Done("1".to_string()), path.clone(), false)).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "water plants");
+        assert_eq!(archived(), ["buy milk"]);
+
+        runtime.block_on(edit_todos(&mut todos, TodoEdit::Undo, path.clone(), false)).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "buy milk\nwater plants\n");
+        assert!(archived().is_empty());
+
+        runtime.block_on(edit_todos(&mut todos, TodoEdit::Redo, path.clone(), false)).unwrap();
+        assert_eq!(archived(), ["buy milk"]);
+    }
+
+
+    /// `sensors -j` on an Intel laptop, with the cores cooler than the package.
+    const CORETEMP_JSON: &str = r#"{
+        "acpitz-acpi-0": {"Adapter": "ACPI interface", "temp1": {"temp1_input": 27.8, "temp1_crit": 119.0}},
+        "coretemp-isa-0000": {
+            "Adapter": "ISA adapter",
+            "Core 0": {"temp2_input": 41.0, "temp2_max": 100.0, "temp2_crit": 100.0, "temp2_crit_alarm": 0.0},
+            "Core 1": {"temp3_input": 43.0, "temp3_max": 100.0, "temp3_crit": 100.0, "temp3_crit_alarm": 0.0},
+            "Package id 0": {"temp1_input": 47.0, "temp1_max": 100.0, "temp1_crit": 100.0, "temp1_crit_alarm": 0.0}
         }
-        current_todos[idx-1] = content;
+    }"#;
+
+    /// `sensors -j` on an AMD Ryzen desktop, whose Tctl has a 10°C offset.
+    const K10TEMP_JSON: &str = r#"{
+        "k10temp-pci-00c3": {
+            "Adapter": "PCI adapter",
+            "Tctl": {"temp1_input": 58.5},
+            "Tdie": {"temp2_input": 48.5},
+            "Tccd1": {"temp3_input": 45.2}
+        },
+        "nvme-pci-0100": {"Adapter": "PCI adapter", "Composite": {"temp1_input": 38.9}}
+    }"#;
+
+    #[test]
+    fn lm_sensors_prefers_the_package_temperature() {
+        let coretemp: serde_json::Value = serde_json::from_str(CORETEMP_JSON).unwrap();
+        assert_eq!(lm_sensors_cpu_temp(&coretemp), Some(47.0));
+        let k10temp: serde_json::Value = serde_json::from_str(K10TEMP_JSON).unwrap();
+        assert_eq!(lm_sensors_cpu_temp(&k10temp), Some(48.5));
     }
 
-    else if verb == "add" {
-        let the_rest = args[3..].join(" ");
-        current_todos.push(the_rest);
+    #[test]
+    fn lm_sensors_falls_back_to_the_first_cpu_temperature() {
+        let cores_only = serde_json::json!({
+            "coretemp-isa-0000": {"Adapter": "ISA adapter", "Core 0": {"temp2_input": 41.0}, "Core 1": {"temp3_input": 43.0}},
+        });
+        assert_eq!(lm_sensors_cpu_temp(&cores_only), Some(41.0));
+        let no_cpu = serde_json::json!({"nvme-pci-0100": {"Composite": {"temp1_input": 38.9}}});
+        assert_eq!(lm_sensors_cpu_temp(&no_cpu), None);
     }
 
-    else {
-        return io_err!(format!("unexpected verb '{verb}'."));
+
+    #[test]
+    fn added_todos_can_be_undone_and_redone() {
+        let _lock = crate::ENV_LOCK.lock().unwrap_or_else(|err| err.into_inner());
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("todos").to_str().unwrap().to_string();
+        fs::write(&path, "buy milk").unwrap();
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let edit = |edit| {
+            let mut todos = runtime.block_on(acquire_todos(path.clone())).unwrap();
+            runtime.block_on(edit_todos(&mut todos, edit, path.clone(), false))
+        };
+
+        let err = edit(TodoEdit::Undo).unwrap_err();
+        assert!(err.to_string().contains("nothing to undo"), "{err}");
+        let err = edit(TodoEdit::Redo).unwrap_err();
+        assert!(err.to_string().contains("nothing to redo"), "{err}");
+
+        edit(TodoEdit::Add("water plants".to_string())).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "buy milk\nwater plants");
+        edit(TodoEdit::Undo).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "buy milk");
+        assert!(edit(TodoEdit::Undo).is_err(), "only one edit can be undone");
+        edit(TodoEdit::Redo).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "buy milk\nwater plants");
+        assert!(edit(TodoEdit::Redo).is_err());
     }
 
-    let mut data_file: File = File::create(todos_path.clone())
-                        .or_else(|_| io_err!("couldn't create your todos file."))?;
 
-    data_file.write(current_todos.join("\n").as_bytes())
-            .or_else(|_| io_err!("couldn't update your todos..."))?;
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn conntrack_usage_is_a_fraction_of_the_max() {
+        let (_root, proc_fs) = fixture();
+        assert!(acquire_conntrack_count(&proc_fs).unwrap().is_none(), "no module, no count");
+        write_fixture(&proc_fs, "/proc/sys/net/netfilter/nf_conntrack_count", "4521\n");
+        assert!(acquire_conntrack_count(&proc_fs).unwrap().is_none(), "both files are needed");
+        write_fixture(&proc_fs, "/proc/sys/net/netfilter/nf_conntrack_max", "65536\n");
+        let conntrack = acquire_conntrack_count(&proc_fs).unwrap().unwrap();
+        assert_eq!((conntrack.count, conntrack.max), (4521, 65536));
+        assert_eq!(format!("{:.1}%", conntrack.fraction() * 100.0), "6.9%");
+
+        write_fixture(&proc_fs, "/proc/sys/net/netfilter/nf_conntrack_max", "0\n");
+        assert_eq!(acquire_conntrack_count(&proc_fs).unwrap().unwrap().fraction(), 0.0);
+        write_fixture(&proc_fs, "/proc/sys/net/netfilter/nf_conntrack_max", "lots\n");
+        assert!(acquire_conntrack_count(&proc_fs).is_err_and(|err| err.kind() == io::ErrorKind::InvalidData));
+    }
+
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn entropy_is_read_in_bits() {
+        let (_root, proc_fs) = fixture();
+        write_fixture(&proc_fs, "/proc/sys/kernel/random/entropy_avail", "3847\n");
+        write_fixture(&proc_fs, "/proc/sys/kernel/random/poolsize", "4096\n");
+        assert_eq!(acquire_entropy_available(&proc_fs).unwrap(), 3847);
+        assert_eq!(acquire_entropy_poolsize(&proc_fs).unwrap(), 4096);
+        write_fixture(&proc_fs, "/proc/sys/kernel/random/entropy_avail", "\n");
+        assert_eq!(acquire_entropy_available(&proc_fs).unwrap_err().kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn uptime_is_split_into_days_hours_minutes_and_seconds() {
+        let (_root, proc_fs) = fixture();
+        let cases = [
+            ("42.71", (0, 0, 0, 42)),
+            ("848.30", (0, 0, 14, 8)),
+            ("11640.95", (0, 3, 14, 0)),
+            ("183612.50", (2, 3, 0, 12)),
+        ];
+        for (seconds, expected) in cases {
+            write_fixture(&proc_fs, "/proc/uptime", &format!("{seconds} 1234.56\n"));
+            let uptime = acquire_uptime(&proc_fs).unwrap();
+            assert_eq!((uptime.days, uptime.hours, uptime.minutes, uptime.seconds), expected, "{seconds}");
+            assert_eq!(uptime.as_seconds(), seconds.parse::<f32>().unwrap() as u64);
+        }
+    }
 
-    Ok(current_todos.to_vec())
 }
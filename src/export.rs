@@ -0,0 +1,232 @@
+use chrono::{Local, NaiveDate, Utc};
+use std::hash::Hasher;
+use fnv::FnvHasher;
+use crate::environment::Todo;
+
+/*
+ * Exported todos are iCalendar (RFC 5545) VTODO components, one per todo:
+ *
+ *   SUMMARY      the todo's text, without its due: and priority: words
+ *   DESCRIPTION  the todo's line from the todos file, as written
+ *   DTSTART      the date it was added, or today if it wasn't recorded,
+ *                left out if that's not before DUE (which it must be)
+ *   DUE          its due date, if it has one
+ */
+
+/// Escapes text for an iCalendar property value.
+fn escape_text(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+/// Folds a content line so that no line is longer than 75 bytes, as iCalendar
+/// requires. Continuation lines start with a space.
+fn fold_line(line: &str) -> String {
+    let mut folded = String::with_capacity(line.len() + 8);
+    let mut width = 0;
+    for c in line.chars() {
+        if width + c.len_utf8() > 75 {
+            folded.push_str("\r\n ");
+            width = 1;
+        }
+        folded.push(c);
+        width += c.len_utf8();
+    }
+    folded.push_str("\r\n");
+    folded
+}
+
+fn format_date(date: NaiveDate) -> String {
+    date.format("%Y%m%d").to_string()
+}
+
+/// An identifier for a todo that stays the same while its text does, so
+/// re-importing updates it rather than adding a copy. `copy` tells apart
+/// todos with the same text. It's an FNV-1a hash of the bytes, which unlike
+/// std's hashers is the same on every platform and Rust version.
+fn todo_uid(todo: &Todo, copy: usize) -> String {
+    let mut hasher = FnvHasher::default();
+    hasher.write(todo.text.as_bytes());
+    hasher.write(&(copy as u64).to_le_bytes());
+    format!("{:016x}@rwelcome", hasher.finish())
+}
+
+/// Exports `todos` as an iCalendar file with a VTODO for each todo.
+pub fn export_todos_ical(todos: &[Todo]) -> String {
+    let today = Local::now().date_naive();
+    let stamp = Utc::now().format("%Y%m%dT%H%M%SZ").to_string();
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        "PRODID:-//rwelcome//todos//EN".to_string(),
+    ];
+    for (index, todo) in todos.iter().enumerate() {
+        let copy = todos[..index].iter().filter(|earlier| earlier.text == todo.text).count();
+        let summary: Vec<&str> = todo.text.split_whitespace()
+            .filter(|word| !word.starts_with("due:") && !word.starts_with("priority:"))
+            .collect();
+        let due = todo.due();
+        let start = todo.added.unwrap_or(today);
+        lines.push("BEGIN:VTODO".to_string());
+        lines.push(format!("UID:{}", todo_uid(todo, copy)));
+        lines.push(format!("DTSTAMP:{stamp}"));
+        if due.is_none_or(|due| start < due) {
+            lines.push(format!("DTSTART;VALUE=DATE:{}", format_date(start)));
+        }
+        if let Some(due) = due {
+            lines.push(format!("DUE;VALUE=DATE:{}", format_date(due)));
+        }
+        lines.push(format!("SUMMARY:{}", escape_text(&summary.join(" "))));
+        lines.push(format!("DESCRIPTION:{}", escape_text(&todo.to_line())));
+        lines.push("END:VTODO".to_string());
+    }
+    lines.push("END:VCALENDAR".to_string());
+    lines.iter().map(|line| fold_line(line)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Reads a TEXT value as RFC 5545 defines it, failing on the characters
+    /// that must be escaped and on escapes it doesn't allow.
+    fn unescape_text(value: &str) -> String {
+        let mut text = String::new();
+        let mut chars = value.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' => match chars.next() {
+                    Some('n' | 'N') => text.push('\n'),
+                    Some(escaped @ ('\\' | ';' | ',')) => text.push(escaped),
+                    other => panic!("invalid escape \\{other:?} in {value:?}"),
+                },
+                ';' | ',' => panic!("unescaped {c:?} in {value:?}"),
+                _ => text.push(c),
+            }
+        }
+        text
+    }
+
+    /// Parses `ical` the way RFC 5545 lays it out, checking the parts a calendar
+    /// app relies on, and returns the properties of each VTODO (after unfolding,
+    /// with TEXT values unescaped) in order.
+    fn validate(ical: &str) -> Vec<Vec<(String, String)>> {
+        assert!(ical.ends_with("\r\n"), "content lines end with CRLF");
+        let physical: Vec<&str> = ical.strip_suffix("\r\n").unwrap().split("\r\n").collect();
+        for line in &physical {
+            assert!(line.len() <= 75, "line longer than 75 bytes: {line:?}");
+            assert!(!line.contains('\n') && !line.contains('\r'), "bare line break in {line:?}");
+        }
+        let mut lines = Vec::<String>::new();
+        for line in physical {
+            match line.strip_prefix(' ') {
+                Some(continued) => lines.last_mut().expect("a continuation needs a line").push_str(continued),
+                None => lines.push(line.to_string()),
+            }
+        }
+
+        let mut open = Vec::<String>::new();
+        let mut calendar = Vec::<(String, String)>::new();
+        let mut todos = Vec::new();
+        for line in lines {
+            let (name, value) = line.split_once(':').unwrap_or_else(|| panic!("no ':' in {line:?}"));
+            let mut parts = name.split(';');
+            let name = parts.next().unwrap().to_string();
+            assert!(
+                !name.is_empty() && name.chars().all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '-'),
+                "invalid property name in {line:?}",
+            );
+            for param in parts {
+                match param.split_once('=') {
+                    Some(("VALUE", "DATE")) => assert!(
+                        NaiveDate::parse_from_str(value, "%Y%m%d").is_ok() && value.len() == 8,
+                        "not a DATE: {line:?}",
+                    ),
+                    _ => panic!("unexpected parameter {param:?} in {line:?}"),
+                }
+            }
+            let value = match name.as_str() {
+                "SUMMARY" | "DESCRIPTION" => unescape_text(value),
+                _ => value.to_string(),
+            };
+            let value = value.as_str();
+            match name.as_str() {
+                "BEGIN" => {
+                    if value == "VTODO" {
+                        assert_eq!(open, ["VCALENDAR"], "VTODO outside VCALENDAR");
+                        todos.push(Vec::new());
+                    }
+                    open.push(value.to_string());
+                },
+                "END" => assert_eq!(open.pop().as_deref(), Some(value), "mismatched END:{value}"),
+                _ if open.last().map(String::as_str) == Some("VTODO") => {
+                    todos.last_mut().unwrap().push((name, value.to_string()));
+                },
+                _ => calendar.push((name, value.to_string())),
+            }
+        }
+        assert!(open.is_empty(), "unclosed {open:?}");
+        for required in ["VERSION", "PRODID"] {
+            assert_eq!(calendar.iter().filter(|(name, _)| name == required).count(), 1, "{required}");
+        }
+        assert_eq!(property(&calendar, "VERSION"), Some("2.0"));
+        let mut uids = Vec::new();
+        for todo in &todos {
+            for required in ["UID", "DTSTAMP"] {
+                assert_eq!(todo.iter().filter(|(name, _)| name == required).count(), 1, "{required} in {todo:?}");
+            }
+            for once in ["DTSTART", "DUE", "SUMMARY", "DESCRIPTION"] {
+                assert!(todo.iter().filter(|(name, _)| name == once).count() <= 1, "more than one {once} in {todo:?}");
+            }
+            let stamp = property(todo, "DTSTAMP").unwrap();
+            assert!(chrono::NaiveDateTime::parse_from_str(stamp, "%Y%m%dT%H%M%SZ").is_ok(), "DTSTAMP {stamp:?} isn't UTC");
+            let uid = property(todo, "UID").unwrap();
+            assert!(!uids.contains(&uid), "UID {uid} is used twice");
+            uids.push(uid);
+            let date = |wanted: &str| todo.iter()
+                .find(|(name, _)| name == wanted)
+                .map(|(_, value)| NaiveDate::parse_from_str(value, "%Y%m%d").unwrap());
+            if let (Some(start), Some(due)) = (date("DTSTART"), date("DUE")) {
+                assert!(start < due, "DTSTART must come before DUE in {todo:?}");
+            }
+        }
+        todos
+    }
+
+    fn property<'a>(todo: &'a [(String, String)], wanted: &str) -> Option<&'a str> {
+        todo.iter().find(|(name, _)| name == wanted).map(|(_, value)| value.as_str())
+    }
+
+    #[test]
+    fn exported_todos_are_valid_icalendar() {
+        let added = NaiveDate::from_ymd_opt(2026, 1, 2);
+        let todos = [
+            Todo { text: "buy milk, eggs; bread due:2099-03-04 priority:high".to_string(), added },
+            Todo { text: "renew passport due:2020-01-01".to_string(), added },
+            Todo { text: format!("write the {}report", "very ".repeat(30)), added: None },
+            Todo { text: "water plants".to_string(), added },
+            Todo { text: "water plants".to_string(), added },
+        ];
+        let exported = validate(&export_todos_ical(&todos));
+        assert_eq!(exported.len(), todos.len());
+
+        let milk = &exported[0];
+        assert_eq!(property(milk, "SUMMARY"), Some("buy milk, eggs; bread"));
+        assert_eq!(property(milk, "DESCRIPTION"), Some(todos[0].to_line().as_str()));
+        assert_eq!(property(milk, "DTSTART"), Some("20260102"));
+        assert_eq!(property(milk, "DUE"), Some("20990304"));
+        // Added after it was due, so there's no valid DTSTART.
+        assert_eq!(property(&exported[1], "DTSTART"), None);
+        assert_eq!(property(&exported[2], "SUMMARY").map(str::len), Some(todos[2].text.len()));
+        assert_ne!(property(&exported[3], "UID"), property(&exported[4], "UID"));
+    }
+
+    #[test]
+    fn uids_dont_change_between_runs() {
+        let todo = Todo { text: "buy milk".to_string(), added: None };
+        assert_eq!(todo_uid(&todo, 0), todo_uid(&todo, 0));
+        assert_eq!(todo_uid(&todo, 0), "37e395112b3d340e@rwelcome");
+    }
+}
@@ -0,0 +1,605 @@
+use chrono::{DateTime, Datelike, Local};
+use std::env;
+use std::fs;
+use std::io::{self, IsTerminal, Read};
+use std::path::PathBuf;
+use std::process::{Command, Output, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+use std::fmt::Display;
+use std::str::FromStr;
+
+/// Reads the environment variable `var` as a number of type `T`.
+/// If the variable is unset, `default` is returned.
+/// If it is set but can't be parsed, a warning is printed to stderr
+/// and `default` is returned.
+pub fn parse_env_number<T>(var: &str, default: T) -> T
+where
+    T: FromStr + Display,
+{
+    match env::var(var) {
+        Ok(value) => value.trim().parse().unwrap_or_else(|_| {
+            eprintln!("rwelcome: warning: {var}=\"{value}\" is not a valid number, using default {default}");
+            default
+        }),
+        Err(_) => default,
+    }
+}
+
+/// Reads the environment variable `var` as an `f64`, see `parse_env_number()`.
+pub fn parse_env_f64(var: &str, default: f64) -> f64 {
+    parse_env_number(var, default)
+}
+
+/// Reads the environment variable `var` as a string.
+/// Returns None if the variable is unset, or if it is set to an empty
+/// (or whitespace-only) value, in which case a warning is printed to stderr.
+pub fn env_non_empty(var: &str) -> Option<String> {
+    let value = env::var(var).ok()?;
+    if value.trim().is_empty() {
+        eprintln!("rwelcome: warning: {var} is set but empty, ignoring it");
+        return None;
+    }
+    Some(value)
+}
+
+/// Reads the environment variable `var`, which must be one of `allowed`
+/// (compared case-insensitively). If the variable is unset, `default` is returned.
+/// If it holds an unknown value, a warning listing the allowed values is
+/// printed to stderr and `default` is returned.
+pub fn env_one_of(var: &str, allowed: &[&str], default: &str) -> String {
+    let value = match env::var(var) {
+        Ok(value) => value,
+        Err(_) => return default.to_string(),
+    };
+    let lowered = value.trim().to_lowercase();
+    if allowed.contains(&lowered.as_str()) {
+        return lowered;
+    }
+    eprintln!(
+        "rwelcome: warning: {var}=\"{value}\" is not one of {}, using default {default}",
+        allowed.join("|")
+    );
+    default.to_string()
+}
+
+/// Whether the environment variable `var` is set to a truthy value ("1", "true" or "yes").
+pub fn env_flag(var: &str) -> bool {
+    env::var(var).is_ok_and(|value| {
+        matches!(value.trim().to_lowercase().as_str(), "1" | "true" | "yes")
+    })
+}
+
+/// Whether stdout is attached to a terminal, as opposed to a pipe or file.
+pub fn stdout_is_tty() -> bool {
+    io::stdout().is_terminal()
+}
+
+/// Detects the width of the terminal attached to stdout.
+/// Falls back to the COLUMNS environment variable, and then to 80 columns.
+pub fn terminal_width() -> usize {
+    if let Some((terminal_size::Width(width), _)) = terminal_size::terminal_size() {
+        return width as usize;
+    }
+    parse_env_number("COLUMNS", 80)
+}
+
+/// Word-wraps `text` so that no line is wider than `max_width` columns,
+/// assuming the first line is preceded by a prefix `indent` columns wide.
+/// Continuation lines are indented by `indent` spaces so they line up under
+/// the first line. Words are never split, so a single word longer than the
+/// available width gets a line to itself.
+pub fn wrap_text(text: &str, max_width: usize, indent: usize) -> Vec<String> {
+    let available = max_width.saturating_sub(indent).max(1);
+    let mut lines = Vec::<String>::new();
+    let mut current = String::new();
+    let mut current_width = 0;
+    for word in text.split_whitespace() {
+        let word_width = word.chars().count();
+        if current_width > 0 && current_width + 1 + word_width > available {
+            lines.push(current);
+            current = String::new();
+            current_width = 0;
+        }
+        if current_width > 0 {
+            current.push(' ');
+            current_width += 1;
+        }
+        current.push_str(word);
+        current_width += word_width;
+    }
+    lines.push(current);
+    let padding = " ".repeat(indent);
+    for line in lines.iter_mut().skip(1) {
+        line.insert_str(0, &padding);
+    }
+    lines
+}
+
+/// Fills in the `{user}` and `{host}` placeholders in `template`.
+pub fn fill_template(template: &str, user: &str, host: &str) -> String {
+    template.replace("{user}", user).replace("{host}", host)
+}
+
+/// Shortens `s` to at most `max` characters, appending an ellipsis if anything was cut.
+/// Counts characters rather than bytes, so multi-byte UTF-8 text is never split mid-character.
+pub fn truncate_with_ellipsis(s: &str, max: usize) -> String {
+    if s.chars().count() <= max {
+        return s.to_string();
+    }
+    let truncated: String = s.chars().take(max).collect();
+    format!("{}…", truncated.trim_end())
+}
+
+/// Formats `n` with `sep` between each group of three digits, e.g. 16384 becomes "16,384".
+pub fn format_number(n: u64, sep: char) -> String {
+    let digits = n.to_string();
+    let mut formatted = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            formatted.push(sep);
+        }
+        formatted.push(digit);
+    }
+    formatted
+}
+
+/// Formats a size in bytes with binary units, e.g. 48535150182 becomes "45.2 GiB".
+/// Whole numbers are shown without a decimal place.
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    let formatted = format!("{:.1}", size);
+    format!("{} {}", formatted.strip_suffix(".0").unwrap_or(&formatted), UNITS[unit])
+}
+
+/// The thousands separator to format numbers with: RWELCOME_NUMBER_SEP if set,
+/// otherwise `.` for a German LC_NUMERIC locale and `,` for everything else.
+pub fn number_separator() -> char {
+    if let Some(sep) = env_non_empty("RWELCOME_NUMBER_SEP") {
+        let mut chars = sep.chars();
+        match (chars.next(), chars.next()) {
+            (Some(sep), None) => return sep,
+            _ => eprintln!("rwelcome: warning: RWELCOME_NUMBER_SEP=\"{sep}\" is not a single character, ignoring it"),
+        }
+    }
+    match env::var("LC_NUMERIC") {
+        Ok(locale) if locale.contains("de_DE") => '.',
+        _ => ',',
+    }
+}
+
+/// Describes how long ago `then` was, relative to `now`:
+/// "today", "yesterday", "N days ago" for the last month, and the date itself after that.
+pub fn format_time_ago(then: DateTime<Local>, now: DateTime<Local>) -> String {
+    let days = (now.date_naive() - then.date_naive()).num_days();
+    match days {
+        i64::MIN..=0 => "today".to_string(),
+        1 => "yesterday".to_string(),
+        2..=30 => format!("{days} days ago"),
+        _ => then.format("%Y-%m-%d").to_string(),
+    }
+}
+
+/// Day (Monday first) and month names for a language, full and abbreviated.
+struct DateNames {
+    days: [&'static str; 7],
+    short_days: [&'static str; 7],
+    months: [&'static str; 12],
+    short_months: [&'static str; 12],
+}
+
+const GERMAN_DATE_NAMES: DateNames = DateNames {
+    days: ["Montag", "Dienstag", "Mittwoch", "Donnerstag", "Freitag", "Samstag", "Sonntag"],
+    short_days: ["Mo", "Di", "Mi", "Do", "Fr", "Sa", "So"],
+    months: [
+        "Januar", "Februar", "März", "April", "Mai", "Juni",
+        "Juli", "August", "September", "Oktober", "November", "Dezember",
+    ],
+    short_months: ["Jan", "Feb", "Mär", "Apr", "Mai", "Jun", "Jul", "Aug", "Sep", "Okt", "Nov", "Dez"],
+};
+
+const FRENCH_DATE_NAMES: DateNames = DateNames {
+    days: ["lundi", "mardi", "mercredi", "jeudi", "vendredi", "samedi", "dimanche"],
+    short_days: ["lun.", "mar.", "mer.", "jeu.", "ven.", "sam.", "dim."],
+    months: [
+        "janvier", "février", "mars", "avril", "mai", "juin",
+        "juillet", "août", "septembre", "octobre", "novembre", "décembre",
+    ],
+    short_months: [
+        "janv.", "févr.", "mars", "avr.", "mai", "juin",
+        "juil.", "août", "sept.", "oct.", "nov.", "déc.",
+    ],
+};
+
+/// Formats `dt` with a chrono `format` string, using the day and month names of
+/// the time locale (LC_ALL, then LC_TIME, then LANG) for %A, %a, %B and %b.
+/// German and French are translated; any other locale gets English names.
+pub fn format_datetime_locale(dt: DateTime<Local>, format: &str) -> String {
+    let locale = ["LC_ALL", "LC_TIME", "LANG"].iter()
+        .find_map(|var| env::var(var).ok().filter(|value| !value.is_empty()))
+        .unwrap_or_default();
+    let names = match locale.split(['_', '.']).next().unwrap_or("") {
+        "de" => &GERMAN_DATE_NAMES,
+        "fr" => &FRENCH_DATE_NAMES,
+        _ => return dt.format(format).to_string(),
+    };
+    let day = dt.weekday().num_days_from_monday() as usize;
+    let month = dt.month0() as usize;
+    let localized = format
+        .replace("%A", names.days[day])
+        .replace("%a", names.short_days[day])
+        .replace("%B", names.months[month])
+        .replace("%b", names.short_months[month]);
+    dt.format(&localized).to_string()
+}
+
+/// Describes `dt` relative to `now`: "Today at 14:32", "Yesterday at 14:32" or
+/// "3 days ago", and the full date (in the time locale) from a week ago on.
+pub fn format_relative_date(dt: DateTime<Local>, now: DateTime<Local>) -> String {
+    match (now.date_naive() - dt.date_naive()).num_days() {
+        0 => format!("Today at {}", dt.format("%H:%M")),
+        1 => format!("Yesterday at {}", dt.format("%H:%M")),
+        days @ 2..=6 => format!("{days} days ago"),
+        _ => format_datetime_locale(dt, "%A, %d %B %Y"),
+    }
+}
+
+/// The directory rwelcome caches data in: rwelcome inside $XDG_CACHE_HOME (or ~/.cache).
+pub fn cache_dir() -> Option<PathBuf> {
+    let cache_home = match env::var("XDG_CACHE_HOME") {
+        Ok(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(env::var("HOME").ok()?).join(".cache"),
+    };
+    Some(cache_home.join("rwelcome"))
+}
+
+/// Reads the cache file `name`, if it exists and was written less than `ttl` ago.
+pub fn read_cache(name: &str, ttl: Duration) -> Option<String> {
+    let path = cache_dir()?.join(name);
+    let age = fs::metadata(&path).ok()?.modified().ok()?.elapsed().ok()?;
+    if age > ttl {
+        return None;
+    }
+    fs::read_to_string(path).ok()
+}
+
+/// Writes `contents` to the cache file `name`. Caching is best-effort,
+/// so failures are ignored; the data will just be fetched again next time.
+pub fn write_cache(name: &str, contents: &str) {
+    if let Some(dir) = cache_dir() {
+        if fs::create_dir_all(&dir).is_ok() {
+            let _ = fs::write(dir.join(name), contents);
+        }
+    }
+}
+
+/// Runs `program` with `args`, capturing its output, and kills it if it takes
+/// longer than `timeout` (returning a TimedOut error).
+/// Returns Ok(None) if the program isn't installed.
+pub fn run_command(program: &str, args: &[&str], timeout: Duration) -> io::Result<Option<Output>> {
+    let mut child = match Command::new(program)
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+    {
+        Ok(child) => child,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(err),
+    };
+
+    // Drain the pipes on other threads, so a chatty program can't fill them up and stall.
+    let mut stdout = child.stdout.take();
+    let mut stderr = child.stderr.take();
+    let stdout_reader = thread::spawn(move || {
+        let mut buf = Vec::new();
+        if let Some(pipe) = stdout.as_mut() {
+            let _ = pipe.read_to_end(&mut buf);
+        }
+        buf
+    });
+    let stderr_reader = thread::spawn(move || {
+        let mut buf = Vec::new();
+        if let Some(pipe) = stderr.as_mut() {
+            let _ = pipe.read_to_end(&mut buf);
+        }
+        buf
+    });
+
+    let deadline = Instant::now() + timeout;
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if Instant::now() >= deadline {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                format!("{program} took longer than {}ms", timeout.as_millis()),
+            ));
+        }
+        thread::sleep(Duration::from_millis(10));
+    };
+    Ok(Some(Output {
+        status,
+        stdout: stdout_reader.join().unwrap_or_default(),
+        stderr: stderr_reader.join().unwrap_or_default(),
+    }))
+}
+
+/// Where the system files that `acquire_*` functions read live: /proc, /sys,
+/// /dev and /etc under `root`, which is `/` except when reading a copy of
+/// them, e.g. fixture files or a container's root filesystem.
+#[derive(Clone)]
+pub struct ProcFs {
+    root: PathBuf,
+}
+
+impl Default for ProcFs {
+    fn default() -> ProcFs {
+        ProcFs::new("/")
+    }
+}
+
+impl ProcFs {
+    /// Reads the system files under `root` instead of `/`.
+    pub fn new(root: impl Into<PathBuf>) -> ProcFs {
+        ProcFs { root: root.into() }
+    }
+
+    /// The path of `path`, an absolute path such as "/proc/uptime", under the root.
+    pub fn path(&self, path: &str) -> PathBuf {
+        self.root.join(path.trim_start_matches('/'))
+    }
+
+    /// /proc/sys/kernel/hostname under the root.
+    #[cfg(target_os = "linux")]
+    pub fn hostname_path(&self) -> PathBuf {
+        self.path("/proc/sys/kernel/hostname")
+    }
+
+    /// /proc/uptime under the root.
+    #[cfg(target_os = "linux")]
+    pub fn uptime_path(&self) -> PathBuf {
+        self.path("/proc/uptime")
+    }
+
+    /// /proc/meminfo under the root.
+    #[cfg(target_os = "linux")]
+    pub fn meminfo_path(&self) -> PathBuf {
+        self.path("/proc/meminfo")
+    }
+
+    /// /proc/mounts under the root.
+    #[cfg(target_os = "linux")]
+    pub fn mounts_path(&self) -> PathBuf {
+        self.path("/proc/mounts")
+    }
+
+    /// /proc/diskstats under the root.
+    #[cfg(target_os = "linux")]
+    pub fn diskstats_path(&self) -> PathBuf {
+        self.path("/proc/diskstats")
+    }
+
+    /// /sys/fs/cgroup under the root.
+    #[cfg(target_os = "linux")]
+    pub fn cgroup_dir(&self) -> PathBuf {
+        self.path("/sys/fs/cgroup")
+    }
+
+    /// /proc/pressure/memory under the root.
+    #[cfg(target_os = "linux")]
+    pub fn memory_pressure_path(&self) -> PathBuf {
+        self.path("/proc/pressure/memory")
+    }
+
+    /// /proc/sys/kernel/random under the root.
+    #[cfg(target_os = "linux")]
+    pub fn random_dir(&self) -> PathBuf {
+        self.path("/proc/sys/kernel/random")
+    }
+
+    /// /proc/sys/net/netfilter under the root.
+    #[cfg(target_os = "linux")]
+    pub fn netfilter_dir(&self) -> PathBuf {
+        self.path("/proc/sys/net/netfilter")
+    }
+
+    /// /dev/kmsg under the root.
+    #[cfg(target_os = "linux")]
+    pub fn kmsg_path(&self) -> PathBuf {
+        self.path("/dev/kmsg")
+    }
+
+    /// /proc/cpuinfo under the root.
+    pub fn cpuinfo_path(&self) -> PathBuf {
+        self.path("/proc/cpuinfo")
+    }
+
+    /// /proc/version under the root.
+    pub fn version_path(&self) -> PathBuf {
+        self.path("/proc/version")
+    }
+
+    /// /proc/`pid`/cmdline under the root.
+    pub fn cmdline_path(&self, pid: u32) -> PathBuf {
+        self.path(&format!("/proc/{pid}/cmdline"))
+    }
+
+    /// /sys/class/hwmon under the root.
+    pub fn hwmon_dir(&self) -> PathBuf {
+        self.path("/sys/class/hwmon")
+    }
+
+    /// /sys/class/thermal under the root.
+    pub fn thermal_dir(&self) -> PathBuf {
+        self.path("/sys/class/thermal")
+    }
+
+    /// /sys/class/drm under the root.
+    pub fn drm_dir(&self) -> PathBuf {
+        self.path("/sys/class/drm")
+    }
+
+    /// /etc/os-release under the root.
+    pub fn os_release_path(&self) -> PathBuf {
+        self.path("/etc/os-release")
+    }
+
+    /// /run/user/`uid` under the root.
+    pub fn user_runtime_dir(&self, uid: u32) -> PathBuf {
+        self.path(&format!("/run/user/{uid}"))
+    }
+
+    /// /run/systemd/system under the root, which exists while systemd is running.
+    pub fn systemd_run_dir(&self) -> PathBuf {
+        self.path("/run/systemd/system")
+    }
+
+    /// /var/run/docker.sock under the root.
+    pub fn docker_socket_path(&self) -> PathBuf {
+        self.path("/var/run/docker.sock")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn at(day: u32, hour: u32, minute: u32) -> DateTime<Local> {
+        Local.with_ymd_and_hms(2026, 10, day, hour, minute, 0).unwrap()
+    }
+
+    /// Runs `f` with LC_ALL set to `locale`, putting it back afterwards.
+    fn with_locale<T>(locale: &str, f: impl FnOnce() -> T) -> T {
+        let _lock = crate::ENV_LOCK.lock().unwrap_or_else(|err| err.into_inner());
+        let previous = env::var_os("LC_ALL");
+        env::set_var("LC_ALL", locale);
+        let result = f();
+        match previous {
+            Some(previous) => env::set_var("LC_ALL", previous),
+            None => env::remove_var("LC_ALL"),
+        }
+        result
+    }
+
+    #[test]
+    fn datetime_in_each_locale() {
+        let format = |locale| with_locale(locale, || format_datetime_locale(at(15, 9, 0), "%A, %d %B %Y (%a %b)"));
+        assert_eq!(format("en_GB.UTF-8"), "Thursday, 15 October 2026 (Thu Oct)");
+        assert_eq!(format("de_DE.UTF-8"), "Donnerstag, 15 Oktober 2026 (Do Okt)");
+        assert_eq!(format("fr_FR.UTF-8"), "jeudi, 15 octobre 2026 (jeu. oct.)");
+        assert_eq!(format("ja_JP.UTF-8"), "Thursday, 15 October 2026 (Thu Oct)");
+    }
+
+    #[test]
+    fn relative_date_same_day() {
+        assert_eq!(format_relative_date(at(15, 8, 5), at(15, 14, 32)), "Today at 08:05");
+    }
+
+    #[test]
+    fn relative_date_yesterday() {
+        // Yesterday by the calendar, even if it was less than a day ago.
+        assert_eq!(format_relative_date(at(14, 23, 50), at(15, 0, 10)), "Yesterday at 23:50");
+    }
+
+    #[test]
+    fn relative_date_within_the_week() {
+        assert_eq!(format_relative_date(at(13, 14, 32), at(15, 9, 0)), "2 days ago");
+        assert_eq!(format_relative_date(at(9, 14, 32), at(15, 9, 0)), "6 days ago");
+    }
+
+    #[test]
+    fn relative_date_a_week_or_more_ago() {
+        let english = with_locale("C", || format_relative_date(at(8, 14, 32), at(15, 9, 0)));
+        assert_eq!(english, "Thursday, 08 October 2026");
+        let german = with_locale("de_DE.UTF-8", || format_relative_date(at(1, 14, 32), at(15, 9, 0)));
+        assert_eq!(german, "Donnerstag, 01 Oktober 2026");
+    }
+
+    #[test]
+    fn fill_template_substitutes_every_placeholder() {
+        assert_eq!(fill_template("Welcome to {host}, {user}.", "anthony", "desktop"), "Welcome to desktop, anthony.");
+        assert_eq!(fill_template("{user}@{host} ({user})", "root", "nas"), "root@nas (root)");
+        assert_eq!(fill_template("No placeholders {here}", "root", "nas"), "No placeholders {here}");
+    }
+
+    #[test]
+    fn wrap_text_fits_exactly() {
+        assert_eq!(wrap_text("one two three", 13, 0), ["one two three"]);
+        assert_eq!(wrap_text("one two three", 18, 5), ["one two three"]);
+    }
+
+    #[test]
+    fn wrap_text_indents_continuation_lines() {
+        assert_eq!(wrap_text("one two three", 12, 0), ["one two", "three"]);
+        assert_eq!(wrap_text("one two three", 17, 5), ["one two", "     three"]);
+        assert_eq!(wrap_text("one two three four", 11, 4), ["one two", "    three", "    four"]);
+    }
+
+    #[test]
+    fn wrap_text_never_splits_words() {
+        let url = "https://example.com/a/very/long/path";
+        let text = format!("see {url} ok");
+        assert_eq!(wrap_text(&text, 20, 6), ["see".to_string(), format!("      {url}"), "      ok".to_string()]);
+    }
+
+    #[test]
+    fn truncate_ascii() {
+        assert_eq!(truncate_with_ellipsis("Intel(R) Core(TM) i9-13980HX", 8), "Intel(R)…");
+        assert_eq!(truncate_with_ellipsis("AMD Ryzen 9", 40), "AMD Ryzen 9");
+    }
+
+    #[test]
+    fn truncate_multi_byte() {
+        assert_eq!(truncate_with_ellipsis("héllo wörld", 8), "héllo wö…");
+        assert_eq!(truncate_with_ellipsis("日本語のホスト名", 3), "日本語…");
+    }
+
+    #[test]
+    fn truncate_at_the_exact_boundary() {
+        assert_eq!(truncate_with_ellipsis("rwelcome", 8), "rwelcome");
+        assert_eq!(truncate_with_ellipsis("rwelcome", 7), "rwelcom…");
+        assert_eq!(truncate_with_ellipsis("日本語", 3), "日本語");
+    }
+
+    #[test]
+    fn format_number_groups_digits_in_threes() {
+        let cases = [(0, "0"), (7, "7"), (512, "512"), (4096, "4,096"), (1048576, "1,048,576")];
+        for (n, formatted) in cases {
+            assert_eq!(format_number(n, ','), formatted);
+        }
+        assert_eq!(format_number(32768, '.'), "32.768");
+    }
+
+    #[test]
+    fn german_locales_separate_with_a_period() {
+        let _lock = crate::ENV_LOCK.lock().unwrap_or_else(|err| err.into_inner());
+        env::remove_var("RWELCOME_NUMBER_SEP");
+        let previous = env::var_os("LC_NUMERIC");
+        env::set_var("LC_NUMERIC", "de_DE.UTF-8");
+        let german = number_separator();
+        env::set_var("LC_NUMERIC", "en_US.UTF-8");
+        let english = number_separator();
+        env::set_var("RWELCOME_NUMBER_SEP", " ");
+        let blank = number_separator();
+        env::set_var("RWELCOME_NUMBER_SEP", "'");
+        let configured = number_separator();
+        env::remove_var("RWELCOME_NUMBER_SEP");
+        match previous {
+            Some(previous) => env::set_var("LC_NUMERIC", previous),
+            None => env::remove_var("LC_NUMERIC"),
+        }
+        assert_eq!((german, english, blank, configured), ('.', ',', ',', '\''));
+    }
+}
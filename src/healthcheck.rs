@@ -0,0 +1,217 @@
+use std::env;
+use std::fs;
+use std::path::Path;
+use crate::config::{self, Config};
+use crate::environment;
+use crate::ext::ProcFs;
+
+/// The outcome of one check made by `rwelcome healthcheck`.
+pub struct HealthCheckResult {
+    pub name: String,
+    pub passed: bool,
+    pub detail: String,
+}
+
+impl HealthCheckResult {
+    fn new(name: &str, result: Result<String, String>) -> HealthCheckResult {
+        let (passed, detail) = match result {
+            Ok(detail) => (true, detail),
+            Err(detail) => (false, detail),
+        };
+        HealthCheckResult { name: name.to_string(), passed, detail }
+    }
+}
+
+/// Environment variables that must hold a number when they're set.
+const NUMERIC_VARS: &[&str] = &[
+    "RWELCOME_WATCH",
+    "RWELCOME_TODOS_MAX",
+    "RWELCOME_TODOS_REMOTE_TTL_SECS",
+    "RWELCOME_PUBLIC_IP_TIMEOUT_SECS",
+    "RWELCOME_WEATHER_CACHE_TTL_SECS",
+    "RWELCOME_GPG_TIMEOUT_SECS",
+    "RWELCOME_MAX_WIDTH",
+    "RWELCOME_DISK_WARN_PCT",
+    "RWELCOME_DISK_CRIT_PCT",
+    "RWELCOME_BAR_WIDTH",
+    "RWELCOME_KERNEL_MAX_LEN",
+    "RWELCOME_CPU_MODEL_MAX_LEN",
+    "RWELCOME_CPU_TEMP_WARN",
+    "RWELCOME_CPU_TEMP_CRIT",
+    "RWELCOME_GPU_TEMP_WARN",
+    "RWELCOME_GPU_TEMP_CRIT",
+    "RWELCOME_UPTIME_MAX_DAYS",
+    "RWELCOME_UPTIME_WARN_DAYS",
+    "RWELCOME_UPTIME_CRIT_DAYS",
+    "RWELCOME_UPDATES_TIMEOUT_SECS",
+    "RWELCOME_MOTD_MAX_LINES",
+    "RWELCOME_DMESG_MAX_LINES",
+    "RWELCOME_DISK_SAMPLE_MS",
+    "RWELCOME_MAX_RENDER_TIME_MS",
+    "RWELCOME_PING_TIMEOUT_MS",
+];
+
+fn check_todos(todos_path: &str) -> Result<String, String> {
+    if environment::is_remote_todos(todos_path) {
+        return Ok(format!("{todos_path} (remote, not fetched)"));
+    }
+    // Encrypted todos aren't decrypted, which might ask for a passphrase.
+    let path = environment::stored_todos_path(todos_path).map_err(|err| err.to_string())?;
+    match fs::read(&path) {
+        Ok(_) => Ok(path),
+        Err(err) => Err(format!("{path}: {err}")),
+    }
+}
+
+fn check_cpu_temp_path(proc_fs: &ProcFs) -> Result<String, String> {
+    let path = env::var("RWELCOME_CPU_TEMP_PATH")
+        .unwrap_or_else(|_| proc_fs.hwmon_dir().join("hwmon1/temp2_input").display().to_string());
+    if Path::new(&path).exists() {
+        Ok(path)
+    } else if env::var("RWELCOME_CPU_TEMP_PATH").is_err()
+        && environment::acquire_thermal_zone_temperature(proc_fs).is_ok()
+    {
+        Ok("from a thermal zone, as there's no hwmon sensor".to_string())
+    } else {
+        Err(format!("{path} doesn't exist"))
+    }
+}
+
+fn check_weather_key(config: &Config) -> Result<String, String> {
+    if config.weather_provider == "openmeteo" {
+        return Ok("not needed for Open-Meteo".to_string());
+    }
+    match env::var("RWELCOME_WEATHER_API_KEY") {
+        Ok(key) if key.trim().is_empty() => Err("RWELCOME_WEATHER_API_KEY is set but empty".to_string()),
+        Ok(_) => Ok("set".to_string()),
+        Err(_) => Ok("not set, so the weather is skipped".to_string()),
+    }
+}
+
+fn check_config(config: &Config) -> Result<String, String> {
+    match config::check_file()? {
+        Some(path) => Ok(format!("{} ({} weather icons)", path.display(), config.weather_icons.len())),
+        None => Ok("no config file, using the defaults".to_string()),
+    }
+}
+
+fn check_numeric_vars() -> Result<String, String> {
+    let invalid: Vec<String> = NUMERIC_VARS.iter()
+        .filter_map(|var| {
+            let value = env::var(var).ok()?;
+            value.trim().parse::<f64>().is_err().then(|| format!("{var}=\"{value}\""))
+        })
+        .collect();
+    if invalid.is_empty() {
+        Ok("all valid".to_string())
+    } else {
+        Err(format!("not numbers: {}", invalid.join(", ")))
+    }
+}
+
+/// Checks rwelcome's configuration: the paths it reads from, and the
+/// environment variables and config file that control it.
+pub fn run_healthcheck(config: &Config, todos_path: &str, proc_fs: &ProcFs) -> Vec<HealthCheckResult> {
+    vec![
+        HealthCheckResult::new("todos file", check_todos(todos_path)),
+        HealthCheckResult::new("CPU temperature", check_cpu_temp_path(proc_fs)),
+        HealthCheckResult::new("weather API key", check_weather_key(config)),
+        HealthCheckResult::new("config file", check_config(config)),
+        HealthCheckResult::new("numeric settings", check_numeric_vars()),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cpu_temp_sensor_is_looked_for_under_the_root() {
+        let _lock = crate::ENV_LOCK.lock().unwrap_or_else(|err| err.into_inner());
+        let root = tempfile::tempdir().unwrap();
+        let proc_fs = ProcFs::new(root.path());
+        env::remove_var("RWELCOME_CPU_TEMP_PATH");
+        assert!(check_cpu_temp_path(&proc_fs).is_err());
+
+        let sensor = proc_fs.hwmon_dir().join("hwmon1/temp2_input");
+        fs::create_dir_all(sensor.parent().unwrap()).unwrap();
+        fs::write(&sensor, "52000\n").unwrap();
+        assert_eq!(check_cpu_temp_path(&proc_fs), Ok(sensor.display().to_string()));
+    }
+
+    #[test]
+    fn cpu_temp_falls_back_to_a_thermal_zone() {
+        let _lock = crate::ENV_LOCK.lock().unwrap_or_else(|err| err.into_inner());
+        let root = tempfile::tempdir().unwrap();
+        let proc_fs = ProcFs::new(root.path());
+        env::remove_var("RWELCOME_CPU_TEMP_PATH");
+        env::remove_var("RWELCOME_THERMAL_ZONE_PATH");
+        let zone = proc_fs.thermal_dir().join("thermal_zone0");
+        fs::create_dir_all(&zone).unwrap();
+        fs::write(zone.join("temp"), "45000\n").unwrap();
+        assert!(check_cpu_temp_path(&proc_fs).unwrap().contains("thermal zone"));
+    }
+
+    #[test]
+    fn todos_file_must_be_readable() {
+        let _lock = crate::ENV_LOCK.lock().unwrap_or_else(|err| err.into_inner());
+        env::remove_var("RWELCOME_TODOS_ENCRYPT");
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("todos").display().to_string();
+        assert!(check_todos(&path).unwrap_err().starts_with(&path));
+        fs::write(&path, "buy milk\n").unwrap();
+        assert_eq!(check_todos(&path), Ok(path));
+        assert!(check_todos("https://example.com/todos").unwrap().contains("not fetched"));
+    }
+
+    #[test]
+    fn weather_key_must_not_be_empty() {
+        let _lock = crate::ENV_LOCK.lock().unwrap_or_else(|err| err.into_inner());
+        let config = Config::default();
+        env::remove_var("RWELCOME_WEATHER_API_KEY");
+        let unset = check_weather_key(&config);
+        env::set_var("RWELCOME_WEATHER_API_KEY", " ");
+        let empty = check_weather_key(&config);
+        let open_meteo = check_weather_key(&Config { weather_provider: "openmeteo".to_string(), ..Config::default() });
+        env::set_var("RWELCOME_WEATHER_API_KEY", "s3cret");
+        let set = check_weather_key(&config);
+        env::remove_var("RWELCOME_WEATHER_API_KEY");
+        assert!(unset.is_ok());
+        assert_eq!(empty, Err("RWELCOME_WEATHER_API_KEY is set but empty".to_string()));
+        assert!(open_meteo.is_ok());
+        assert_eq!(set, Ok("set".to_string()));
+    }
+
+    #[test]
+    fn config_file_must_parse() {
+        let _lock = crate::ENV_LOCK.lock().unwrap_or_else(|err| err.into_inner());
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        env::set_var("RWELCOME_CONFIG_PATH", &path);
+        let missing = check_config(&Config::default());
+        fs::write(&path, "[weather\nunits = \"metric\"\n").unwrap();
+        let malformed = check_config(&Config::default());
+        fs::write(&path, "[weather]\nunits = \"metric\"\n").unwrap();
+        let valid = check_config(&Config::default());
+        env::remove_var("RWELCOME_CONFIG_PATH");
+        assert_eq!(missing, Ok("no config file, using the defaults".to_string()));
+        assert!(malformed.unwrap_err().starts_with("couldn't parse"));
+        assert_eq!(valid, Ok(format!("{} (0 weather icons)", path.display())));
+    }
+
+    #[test]
+    fn numeric_settings_must_be_numbers() {
+        let _lock = crate::ENV_LOCK.lock().unwrap_or_else(|err| err.into_inner());
+        for var in NUMERIC_VARS {
+            env::remove_var(var);
+        }
+        env::set_var("RWELCOME_BAR_WIDTH", " 20 ");
+        let valid = check_numeric_vars();
+        env::set_var("RWELCOME_TODOS_MAX", "ten");
+        let invalid = check_numeric_vars();
+        env::remove_var("RWELCOME_BAR_WIDTH");
+        env::remove_var("RWELCOME_TODOS_MAX");
+        assert!(valid.is_ok());
+        assert_eq!(invalid, Err("not numbers: RWELCOME_TODOS_MAX=\"ten\"".to_string()));
+    }
+}
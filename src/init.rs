@@ -0,0 +1,173 @@
+use std::env;
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::path::Path;
+
+/// Asks `question`, returning the answer, or `default` if it was left empty.
+fn prompt(input: &mut impl BufRead, output: &mut impl Write, question: &str, default: &str) -> io::Result<String> {
+    if default.is_empty() {
+        write!(output, "{question}> ")?;
+    } else {
+        write!(output, "{question} [{default}]> ")?;
+    }
+    output.flush()?;
+    let mut answer = String::new();
+    input.read_line(&mut answer)?;
+    let answer = answer.trim();
+    Ok(if answer.is_empty() { default.to_string() } else { answer.to_string() })
+}
+
+/// Asks a yes/no `question`, where an empty answer means `default`.
+fn confirm(input: &mut impl BufRead, output: &mut impl Write, question: &str, default: bool) -> io::Result<bool> {
+    let answer = prompt(input, output, &format!("{question} (y/n)"), if default { "y" } else { "n" })?;
+    Ok(answer.to_lowercase().starts_with('y'))
+}
+
+/// Expands a leading `~/` in `path` against $HOME, since nothing that reads
+/// the config file does. Other paths, and all of them without $HOME, are kept.
+fn expand_home(path: &str) -> String {
+    match (path.strip_prefix("~/"), env::var("HOME")) {
+        (Some(rest), Ok(home)) if !home.is_empty() => format!("{}/{rest}", home.trim_end_matches('/')),
+        _ => path.to_string(),
+    }
+}
+
+/// Asks the questions for `rwelcome init`, returning the config file's
+/// [env] section as TOML. `default_modules` are the modules shown by default.
+pub fn ask(input: &mut impl BufRead, output: &mut impl Write, default_modules: &[&str]) -> io::Result<String> {
+    let mut env = toml::Table::new();
+
+    writeln!(output, "The weather needs a free API key from https://www.weatherapi.com/signup.aspx,")?;
+    writeln!(output, "or none at all with RWELCOME_WEATHER_PROVIDER=openmeteo. Leave it empty to skip the weather.")?;
+    let key = prompt(input, output, "Weather API key", "")?;
+    if !key.is_empty() {
+        env.insert("RWELCOME_WEATHER_API_KEY".to_string(), key.into());
+    }
+
+    let todos_path = prompt(input, output, "Todos file (empty for ~/.local/share/rwelcome/todos)", "")?;
+    if !todos_path.is_empty() {
+        env.insert("RWELCOME_TODOS_PATH".to_string(), expand_home(&todos_path).into());
+    }
+
+    let mut modules: Vec<&str> = default_modules.to_vec();
+    if confirm(input, output, "Show the CPU temperature?", true)? {
+        let path = prompt(input, output, "CPU temperature sensor", "/sys/class/hwmon/hwmon1/temp2_input")?;
+        env.insert("RWELCOME_CPU_TEMP_PATH".to_string(), expand_home(&path).into());
+    } else {
+        modules.retain(|module| *module != "cpu-temp");
+    }
+
+    let modules = prompt(input, output, "Modules to show, in order", &modules.join(","))?;
+    env.insert("RWELCOME_MODULES".to_string(), modules.into());
+
+    let mut file = toml::Table::new();
+    file.insert("env".to_string(), env.into());
+    toml::to_string(&file).map_err(io::Error::other)
+}
+
+/// Runs `rwelcome init`: asks the questions, then writes the answers to the
+/// config file at `path`, asking first if there's one there already.
+/// Returns false if the user chose to keep their existing file.
+pub fn init(input: &mut impl BufRead, output: &mut impl Write, path: &Path, default_modules: &[&str]) -> io::Result<bool> {
+    if path.exists() {
+        writeln!(output, "{} already exists.", path.display())?;
+        if !confirm(input, output, "Overwrite it?", false)? {
+            return Ok(false);
+        }
+    }
+    let contents = ask(input, output, default_modules)?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(path, contents)?;
+    writeln!(output, "Wrote {}.", path.display())?;
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    const MODULES: &[&str] = &["uptime", "memory", "cpu-temp"];
+
+    fn env_section(contents: &str) -> toml::Table {
+        let file: toml::Table = toml::from_str(contents).unwrap();
+        assert_eq!(file.len(), 1, "only [env] is written");
+        file["env"].as_table().unwrap().clone()
+    }
+
+    #[test]
+    fn answers_become_the_env_section() {
+        let _lock = crate::ENV_LOCK.lock().unwrap_or_else(|err| err.into_inner());
+        let home = env::var_os("HOME");
+        env::set_var("HOME", "/home/tester");
+        let answers = "abc123\n~/todos\ny\n\n\n";
+        let mut output = Vec::new();
+        let contents = ask(&mut Cursor::new(answers), &mut output, MODULES).unwrap();
+        match home {
+            Some(home) => env::set_var("HOME", home),
+            None => env::remove_var("HOME"),
+        }
+        let env = env_section(&contents);
+        assert_eq!(env["RWELCOME_WEATHER_API_KEY"].as_str(), Some("abc123"));
+        assert_eq!(env["RWELCOME_TODOS_PATH"].as_str(), Some("/home/tester/todos"));
+        assert_eq!(env["RWELCOME_CPU_TEMP_PATH"].as_str(), Some("/sys/class/hwmon/hwmon1/temp2_input"));
+        assert_eq!(env["RWELCOME_MODULES"].as_str(), Some("uptime,memory,cpu-temp"));
+        assert!(String::from_utf8(output).unwrap().contains("Weather API key> "));
+    }
+
+    #[test]
+    fn only_a_leading_tilde_is_expanded() {
+        let _lock = crate::ENV_LOCK.lock().unwrap_or_else(|err| err.into_inner());
+        let home = env::var_os("HOME");
+        env::set_var("HOME", "/home/tester/");
+        let expanded = ["~/todos", "~", "~other/todos", "/srv/~/todos"].map(expand_home);
+        env::remove_var("HOME");
+        let homeless = expand_home("~/todos");
+        match home {
+            Some(home) => env::set_var("HOME", home),
+            None => env::remove_var("HOME"),
+        }
+        assert_eq!(expanded, ["/home/tester/todos", "~", "~other/todos", "/srv/~/todos"]);
+        assert_eq!(homeless, "~/todos");
+    }
+
+    #[test]
+    fn empty_answers_are_left_out() {
+        let contents = ask(&mut Cursor::new("\n\nn\nuptime\n"), &mut Vec::new(), MODULES).unwrap();
+        let env = env_section(&contents);
+        assert!(!env.contains_key("RWELCOME_WEATHER_API_KEY"));
+        assert!(!env.contains_key("RWELCOME_TODOS_PATH"));
+        assert!(!env.contains_key("RWELCOME_CPU_TEMP_PATH"));
+        assert_eq!(env["RWELCOME_MODULES"].as_str(), Some("uptime"));
+    }
+
+    #[test]
+    fn declining_cpu_temp_drops_it_from_the_modules() {
+        let contents = ask(&mut Cursor::new("\n\nn\n\n"), &mut Vec::new(), MODULES).unwrap();
+        assert_eq!(env_section(&contents)["RWELCOME_MODULES"].as_str(), Some("uptime,memory"));
+    }
+
+    #[test]
+    fn an_existing_file_is_kept_unless_confirmed() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("rwelcome/config.toml");
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, "# mine\n").unwrap();
+
+        assert!(!init(&mut Cursor::new("\n"), &mut Vec::new(), &path, MODULES).unwrap());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "# mine\n");
+
+        assert!(init(&mut Cursor::new("y\n\n\nn\n\n"), &mut Vec::new(), &path, MODULES).unwrap());
+        assert!(env_section(&fs::read_to_string(&path).unwrap()).contains_key("RWELCOME_MODULES"));
+    }
+
+    #[test]
+    fn the_config_directory_is_created() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("new/rwelcome/config.toml");
+        assert!(init(&mut Cursor::new("\n\nn\n\n"), &mut Vec::new(), &path, MODULES).unwrap());
+        assert!(path.exists());
+    }
+}
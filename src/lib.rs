@@ -0,0 +1,59 @@
+//! rwelcome's system info acquisition, for use from other crates.
+//!
+//! The `acquire_*` functions each read one piece of information, such as
+//! the uptime or memory usage, and return it as a typed struct:
+//!
+//! ```no_run
+//! use rwelcome::{acquire_memory_info, acquire_uptime, ProcFs};
+//!
+//! let proc_fs = ProcFs::default();
+//! let uptime = acquire_uptime(&proc_fs).unwrap();
+//! let memory = acquire_memory_info(&proc_fs).unwrap();
+//! println!("up {} days, {} kB of memory in use", uptime.days, memory.used_excluding_caches());
+//! ```
+//!
+//! Like the rwelcome binary, they're configured through `RWELCOME_*`
+//! environment variables, and read the system through a [`ProcFs`], which
+//! can point somewhere other than `/` to read a captured system instead.
+#![warn(missing_docs)]
+
+/// The `acquire_*` functions, and the todo lists.
+pub mod environment;
+/// Helpers shared by the acquisition functions and the binary, e.g. for
+/// reading `RWELCOME_*` variables and running commands.
+pub mod ext;
+/// Encrypting and decrypting todo lists with gpg.
+#[cfg(feature = "encrypt")]
+pub mod encrypt;
+#[cfg(any(test, target_os = "macos", target_os = "freebsd"))]
+mod sysctl;
+
+/// Serializes the tests that set environment variables, which are process-wide.
+#[cfg(test)]
+static ENV_LOCK: std::sync::Mutex<()> = std::sync::Mutex::new(());
+
+pub use environment::{
+    acquire_archived_todos, acquire_conntrack_count, acquire_cpu_model, acquire_cpu_temperature,
+    acquire_current_user, acquire_default_todos, acquire_disk_throughput, acquire_disk_usage, acquire_dmesg_errors,
+    acquire_entropy_available, acquire_entropy_poolsize, acquire_fortune, acquire_git_status,
+    acquire_hostname, acquire_kernel_version, acquire_last_package_update,
+    acquire_lm_sensors_data, acquire_memory_info, acquire_memory_pressure, acquire_motd,
+    acquire_nfs_mounts, acquire_nvidia_gpu_stats, acquire_os_info, acquire_pending_updates,
+    acquire_ping_latencies, acquire_running_containers, acquire_screen_resolution,
+    acquire_sensors_all, acquire_systemd_failed_units, acquire_terminal,
+    acquire_thermal_zone_temperature, acquire_todo_lists, acquire_todos, acquire_uptime,
+    acquire_vnstat_traffic, acquire_zfs_pool_health,
+};
+#[cfg(feature = "weather")]
+pub use environment::acquire_public_ip;
+#[cfg(target_os = "linux")]
+pub use environment::{
+    acquire_cgroup_memory_limit, acquire_cgroup_memory_usage, acquire_disk_io, acquire_in_container,
+    acquire_root_device, DiskIo,
+};
+pub use environment::{
+    Aggregate, ArchivedTodo, ConntrackCount, ContainerInfo, CpuTempInfo, DiskThroughput,
+    DiskUsage, GitStatus, GpuStats, MemInfo, NfsMount, OsInfo, PingResult, PressureInfo,
+    SensorReading, SensorUnit, Todo, UpdateInfo, UptimeInfo, VnstatInfo, ZfsPool,
+};
+pub use ext::ProcFs;
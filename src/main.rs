@@ -1,147 +1,2164 @@
+#[cfg(feature = "weather")]
 mod weather;
-mod environment;
+mod config;
+mod theme;
+mod render;
+mod backup;
+mod healthcheck;
+mod init;
+mod export;
+mod stats;
+mod completions;
+mod announce;
 
+/// Serializes the tests that set environment variables, which are process-wide.
+#[cfg(test)]
+static ENV_LOCK: std::sync::Mutex<()> = std::sync::Mutex::new(());
+
+#[cfg(feature = "weather")]
+use std::collections::HashMap;
 use std::env;
+use std::fs;
+use clap::{CommandFactory, FromArgMatches, Parser, Subcommand};
 use colored::Colorize;
-use tokio;
 use std::io;
-use weather::WeatherResponse;
+use std::time::Duration;
+#[cfg(feature = "weather")]
+use weather::{WeatherLocation, WeatherResponse};
+use config::Config;
+#[cfg(feature = "weather")]
+use config::WeatherUnits;
+use rwelcome::{environment, ext};
+use environment::{SortKey, Todo, TodoEdit};
+use render::{Align, LabelAlign, LabeledLine, SystemSnapshot};
+use theme::Theme;
+use ext::ProcFs;
+#[cfg(feature = "weather")]
+use render::WeatherSnapshot;
 
 struct Rwelcome {
+    config: Config,
+    proc_fs: ProcFs,
     username: String,
-    maybe_weather_response: Option<reqwest::Result<WeatherResponse>>,
-    todos: io::Result<Vec<String>>,
+    #[cfg(feature = "weather")]
+    maybe_weather_response: Option<io::Result<WeatherResponse>>,
+    #[cfg(feature = "weather")]
+    maybe_second_weather: Option<WeatherResponse>,
+    #[cfg(feature = "weather")]
+    maybe_public_ip: Option<io::Result<String>>,
+    maybe_disk_io: Option<io::Result<environment::DiskThroughput>>,
+    maybe_ping: Option<io::Result<Vec<environment::PingResult>>>,
+    /// The modules that run commands, acquired up front like the ones above
+    /// so they count against the time budget. None when they're turned off.
+    maybe_updates: Option<io::Result<Option<environment::UpdateInfo>>>,
+    maybe_git: Option<io::Result<Option<environment::GitStatus>>>,
+    maybe_zfs: Option<io::Result<Option<Vec<environment::ZfsPool>>>>,
+    maybe_nfs: Option<io::Result<Vec<environment::NfsMount>>>,
+    maybe_gpu: Option<io::Result<Option<Vec<environment::GpuStats>>>>,
+    maybe_failed_units: Option<io::Result<Option<Vec<String>>>>,
+    maybe_fortune: Option<String>,
+    /// The modules that read files that may hang or run commands too, so they
+    /// count against the time budget rather than holding up the render.
+    maybe_vnstat: Option<io::Result<Option<environment::VnstatInfo>>>,
+    maybe_resolution: Option<io::Result<Option<String>>>,
+    maybe_containers: Option<io::Result<Option<environment::ContainerInfo>>>,
+    maybe_cpu_temp: Option<io::Result<environment::CpuTempInfo>>,
+    maybe_disk: Option<io::Result<environment::DiskUsage>>,
+    maybe_dmesg: Option<io::Result<Vec<String>>>,
+    /// The modules to show, in order.
+    modules: Vec<String>,
+    /// Whether any module was left out for taking longer than its time budget.
+    timed_out: bool,
+    todos: io::Result<Vec<Todo>>,
+}
+
+/// Command line arguments. With no subcommand, rwelcome shows the welcome screen.
+#[derive(Parser)]
+#[command(version, about = "A welcome screen for your terminal: system info, weather and todos.")]
+struct Cli {
+    /// Use the named todo list instead of the default one.
+    #[arg(long, global = true, value_name = "NAME")]
+    list: Option<String>,
+    /// Re-render every SECONDS seconds until Ctrl-C (or set RWELCOME_WATCH).
+    #[arg(long, value_name = "SECONDS")]
+    watch: Option<u64>,
+    /// Show what an edit would leave in the todos file, without writing it.
+    #[arg(long, global = true)]
+    dry_run: bool,
+    /// Print the resolved configuration, and where each setting came from, as TOML.
+    #[arg(long)]
+    print_config: bool,
+    /// With --print-config, print JSON instead.
+    #[arg(long, requires = "print_config")]
+    json: bool,
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Print a single metric as a plain value, for scripts and status bars.
+    Show {
+        module: Option<String>,
+    },
+    /// List the available todo lists.
+    Lists,
+    /// Write all of rwelcome's data (todos, config and cache) to a .tar.gz archive.
+    Backup {
+        /// Where to write the archive, instead of stdout.
+        path: Option<String>,
+    },
+    /// Check that rwelcome's paths, environment variables and config file are valid.
+    Healthcheck,
+    /// Create a config file by answering a few questions.
+    Init,
+    /// Export the todos for other applications, e.g. as an iCalendar file.
+    Export {
+        #[arg(long, default_value = "ical", value_parser = ["ical"])]
+        format: String,
+        /// Where to write the export, instead of stdout.
+        #[arg(long, value_name = "PATH")]
+        output: Option<String>,
+    },
+    /// Restore the data from an archive made by `backup`.
+    Restore {
+        path: String,
+    },
+    /// Print the shell completion script, or install it with --install.
+    Completions {
+        shell: completions::Shell,
+        /// Write the script where the shell will find it, instead of printing it.
+        #[arg(long)]
+        install: bool,
+    },
+    /// Change the todo list, or open it in $EDITOR if no action is given.
+    #[command(visible_alias = "todo")]
+    Edit {
+        #[command(subcommand)]
+        action: Option<EditAction>,
+    },
+    /// Add a todo (short for `edit add`).
+    Add {
+        #[arg(required = true)]
+        text: Vec<String>,
+    },
+    /// Complete todos (short for `edit done`).
+    Done {
+        #[arg(required = true)]
+        indices: Vec<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum EditAction {
+    /// Add a todo, e.g. `rwelcome edit add Get bagels`.
+    Add {
+        #[arg(required = true)]
+        text: Vec<String>,
+    },
+    /// Complete todos by number, e.g. `2` or `1,3-5`.
+    #[command(visible_alias = "check")]
+    Done {
+        #[arg(required = true)]
+        indices: Vec<String>,
+    },
+    /// Replace the text of a todo, e.g. `rwelcome edit fix 2 Get more bagels`.
+    Fix {
+        index: String,
+        #[arg(required = true)]
+        text: Vec<String>,
+    },
+    /// Move a todo to another position, e.g. `rwelcome edit move 5 1`.
+    Move {
+        from: String,
+        to: String,
+    },
+    /// Add a todo at a position, e.g. `rwelcome edit insert 1 Pay rent`.
+    Insert {
+        index: String,
+        #[arg(required = true)]
+        text: Vec<String>,
+    },
+    /// Remove every todo, without marking them as done.
+    Clear,
+    /// Show the todos containing some text, e.g. `rwelcome edit search bagels`.
+    Search {
+        #[arg(required = true)]
+        query: Vec<String>,
+    },
+    /// Revert the last edit.
+    Undo,
+    /// Re-apply the last undone edit.
+    Redo,
+    /// Show how many todos were added and completed this week, this month and ever.
+    Stats,
+}
+
+fn main() -> Result<(), String> {
+    let help = format!("Color schemes (RWELCOME_COLOR_SCHEME): {}", theme::list_color_schemes().join(", "));
+    let cli = match Cli::from_arg_matches(&Cli::command().after_help(help).get_matches()) {
+        Ok(cli) => cli,
+        Err(err) => err.exit(),
+    };
+    let proc_fs = ProcFs::default();
+    if greeting_only(&cli, &proc_fs) {
+        return Ok(());
+    }
+    /*
+     * The config file's [env] section is applied while there's still only one
+     * thread, since setting environment variables isn't safe alongside others
+     * that might be reading them. The runtime's threads only start after.
+     */
+    config::apply_env_defaults();
+    let runtime = tokio::runtime::Runtime::new()
+        .map_err(|err| format!("rwelcome: error: couldn't start: {err}"))?;
+    let result = runtime.block_on(run(cli, proc_fs));
+    // Modules that ran out of time may still be running; they're left behind rather than waited for.
+    runtime.shutdown_background();
+    result
+}
+
+async fn run(cli: Cli, proc_fs: ProcFs) -> Result<(), String> {
+    if cli.print_config {
+        let config = Config::load();
+        if cli.json {
+            println!("{:#}", config.to_json());
+        } else {
+            print!("{}", config.to_toml());
+        }
+        return Ok(());
+    }
+    let edit = match cli.command {
+        Some(Command::Show { module }) => return show_single_module(&proc_fs, module.as_deref().unwrap_or("")),
+        Some(Command::Lists) => return show_todo_lists(),
+        Some(Command::Backup { path }) => return run_backup(path.as_deref()),
+        Some(Command::Restore { path }) => return run_restore(&path),
+        Some(Command::Healthcheck) => return show_healthcheck(cli.list.as_deref(), &proc_fs),
+        Some(Command::Init) => return run_init(cli.list.as_deref()),
+        Some(Command::Completions { shell, install }) => return run_completions(shell, install),
+        Some(Command::Edit { action: None }) => Some(TodoEdit::Editor),
+        Some(Command::Edit { action: Some(EditAction::Add { text }) })
+        | Some(Command::Add { text }) => Some(TodoEdit::Add(text.join(" "))),
+        Some(Command::Edit { action: Some(EditAction::Done { indices }) })
+        | Some(Command::Done { indices }) => Some(TodoEdit::Done(indices.join(" "))),
+        Some(Command::Edit { action: Some(EditAction::Fix { index, text }) }) => {
+            Some(TodoEdit::Fix(index, text.join(" ")))
+        },
+        Some(Command::Edit { action: Some(EditAction::Move { from, to }) }) => Some(TodoEdit::Move(from, to)),
+        Some(Command::Edit { action: Some(EditAction::Insert { index, text }) }) => {
+            Some(TodoEdit::Insert(index, text.join(" ")))
+        },
+        Some(Command::Edit { action: Some(EditAction::Clear) }) => Some(TodoEdit::Clear),
+        Some(Command::Edit { action: Some(EditAction::Search { query }) }) => {
+            return show_todo_search(cli.list.as_deref(), &query.join(" ")).await;
+        },
+        Some(Command::Edit { action: Some(EditAction::Undo) }) => Some(TodoEdit::Undo),
+        Some(Command::Edit { action: Some(EditAction::Redo) }) => Some(TodoEdit::Redo),
+        Some(Command::Edit { action: Some(EditAction::Stats) }) => return show_todo_stats(cli.list.as_deref()).await,
+        Some(Command::Export { output, .. }) => return run_export(cli.list.as_deref(), output.as_deref()).await,
+        None => None,
+    };
+    let todos_path = environment::todos_list_path(&base_todos_path(), cli.list.as_deref())
+        .map_err(|err| err.to_string())?;
+    if cli.dry_run {
+        return match edit {
+            Some(edit) => preview_edit(edit, todos_path).await,
+            None => Err("rwelcome: error: --dry-run needs an edit to preview, e.g. done 3.".to_string()),
+        };
+    }
+    /*
+     * Only --watch conflicts with an edit; RWELCOME_WATCH is meant for the
+     * greeting, so edits just ignore it.
+     */
+    if edit.is_some() && cli.watch.is_some() {
+        return Err("rwelcome: error: watch mode can't be combined with other verbs.".to_string());
+    }
+    let interval = match edit {
+        Some(_) => 0,
+        None => cli.watch.unwrap_or_else(|| ext::parse_env_number("RWELCOME_WATCH", 0)),
+    };
+    // Only the plain greeting announces a login, never edits or watch mode.
+    let announce = if edit.is_none() && interval == 0 {
+        let username = environment::acquire_current_user().unwrap_or_else(|| "unknown".to_string());
+        let hostname = environment::acquire_hostname(&proc_fs).unwrap_or_else(|_| "unknown".to_string());
+        announce::spawn_ssh_announce(&username, &hostname)
+    } else {
+        None
+    };
+    if interval == 0 {
+        let ctx = load(edit, todos_path, cli.list.is_none(), proc_fs).await?;
+        // Clear only after everything is acquired, and never when piped.
+        if ext::env_flag("RWELCOME_CLEAR") && ext::stdout_is_tty() {
+            clear_screen();
+        }
+        render(ctx);
+        finish_announce(announce).await;
+        return Ok(());
+    }
+
+    /*
+     * Watch mode: re-render every `interval` seconds until Ctrl-C.
+     * The screen is cleared only once everything has been acquired,
+     * so there's no flash of blank screen between renders.
+     */
+    let ctrl_c = tokio::signal::ctrl_c();
+    tokio::pin!(ctrl_c);
+    loop {
+        tokio::select! {
+            result = async {
+                let ctx = load(None, todos_path.clone(), cli.list.is_none(), proc_fs.clone()).await?;
+                clear_screen();
+                render(ctx);
+                tokio::time::sleep(Duration::from_secs(interval)).await;
+                Ok::<(), String>(())
+            } => result?,
+            _ = &mut ctrl_c => return Ok(()),
+        }
+    }
+}
+
+/*
+ * RWELCOME_GREETING_ONLY=1 prints just the greeting, skipping everything
+ * else rwelcome would read, so it's near-instant: the config file, the todos,
+ * the modules and the login announcement. Only the hostname is read, so the
+ * variable has to be set in the environment rather than the config file.
+ */
+fn greeting_only(cli: &Cli, proc_fs: &ProcFs) -> bool {
+    let plain = cli.command.is_none() && cli.watch.is_none() && !cli.print_config && !cli.dry_run;
+    if !plain || !ext::env_flag("RWELCOME_GREETING_ONLY") {
+        return false;
+    }
+    let username = environment::acquire_current_user().unwrap_or_else(|| "unknown".to_string());
+    let hostname = environment::acquire_hostname(proc_fs).unwrap_or_else(|_| "unknown".to_string());
+    render_greeting(&username, &display_hostname(&hostname), &theme::load_theme());
+    true
+}
+
+/// Wait for the login notification to be sent, so exiting doesn't cancel it.
+/// It's already had as long as rendering took, and gives up after 3 seconds.
+async fn finish_announce(announce: Option<tokio::task::JoinHandle<()>>) {
+    if let Some(announce) = announce {
+        let _ = announce.await;
+    }
 }
 
-#[tokio::main]
-async fn main() -> Result<(), String> {
-    let ctx = load().await?;
-    render(ctx);
+/// Print what `edit` would do to the todo list, for --dry-run.
+async fn preview_edit(edit: TodoEdit, todos_path: String) -> Result<(), String> {
+    let mut current_todos = environment::acquire_todos(todos_path.clone()).await
+        .map_err(|err| format!("rwelcome: error: couldn't read your todos: {err}"))?;
+    environment::edit_todos(&mut current_todos, edit, todos_path, true).await
+        .map_err(|err| err.to_string())?;
     Ok(())
 }
 
-/// Neatly format a list of todos to stdout.
-pub fn show_todos(todos: Vec<String>) {
-    if todos.is_empty() {
-        println!("{}: none!", "Todos".bright_blue());
-        return;
+// Clear the terminal and move the cursor to the top-left corner.
+fn clear_screen() {
+    print!("\x1b[2J\x1b[H");
+}
+
+/// The path of the default todo list: RWELCOME_TODOS_PATH if set,
+/// otherwise ~/.local/share/rwelcome/todos.
+fn base_todos_path() -> String {
+    ext::env_non_empty("RWELCOME_TODOS_PATH").unwrap_or_else(default_todos_path)
+}
+
+/// Where the default todo list lives when RWELCOME_TODOS_PATH isn't set.
+fn default_todos_path() -> String {
+    let username = environment::acquire_current_user().unwrap_or_else(|| "unknown".to_string());
+    format!("/home/{username}/.local/share/rwelcome/todos")
+}
+
+/// Reads the todo list at `todos_path`. The default list is created on first
+/// run, while named lists are left for an edit to create, so a mistyped
+/// --list doesn't leave a new list behind.
+async fn read_todos(todos_path: String, default_list: bool) -> io::Result<Vec<Todo>> {
+    if default_list {
+        environment::acquire_default_todos(todos_path).await
+    } else {
+        environment::acquire_todos(todos_path).await
     }
-    println!("{}:", "Todos".bright_blue());
-    for (index, todo) in todos.iter().enumerate() {
-        println!("  {}. {}", index + 1, todo);
+}
+
+/// Print the names of the available todo lists, for `rwelcome lists`.
+fn show_todo_lists() -> Result<(), String> {
+    let lists = environment::acquire_todo_lists(&base_todos_path())
+        .map_err(|err| format!("rwelcome: error: couldn't read your todo lists: {err}"))?;
+    for list in lists {
+        println!("{list}");
     }
+    Ok(())
 }
 
-// Send N hyphens to stdout, where N equals `length`.
-fn draw_line(length: usize) {
-    let mut i = 0;
-    loop {
-        if length == i {
-            println!();
-            break;
+/// Write a backup archive to `path`, or to stdout, for `rwelcome backup`.
+fn run_backup(path: Option<&str>) -> Result<(), String> {
+    let result = match path {
+        Some(path) => fs::File::create(path).and_then(|file| backup::backup(file, &base_todos_path())),
+        None => {
+            if ext::stdout_is_tty() {
+                return Err("rwelcome: error: refusing to write an archive to the terminal; redirect it or give a path.".to_string());
+            }
+            backup::backup(io::stdout().lock(), &base_todos_path())
+        },
+    };
+    let count = result.map_err(|err| format!("rwelcome: error: couldn't write the backup: {err}"))?;
+    eprintln!("Backed up {count} files.");
+    Ok(())
+}
+
+/// Restore a backup archive, for `rwelcome restore <path>`.
+fn run_restore(path: &str) -> Result<(), String> {
+    let count = backup::restore(path, &base_todos_path()).map_err(|err| {
+        if err.to_string().starts_with("rwelcome: error:") {
+            err.to_string()
+        } else {
+            format!("rwelcome: error: couldn't restore {path}: {err}")
         }
-        print!("-");
-        i += 1;
+    })?;
+    match count {
+        Some(count) => println!("Restored {count} files."),
+        None => println!("Nothing was restored."),
+    }
+    Ok(())
+}
+
+/// Print a summary of the todos added and completed, for `rwelcome edit stats`.
+async fn show_todo_stats(list: Option<&str>) -> Result<(), String> {
+    let todos_path = environment::todos_list_path(&base_todos_path(), list)
+        .map_err(|err| err.to_string())?;
+    let active = read_todos(todos_path.clone(), list.is_none()).await
+        .map_err(|err| format!("rwelcome: error: couldn't read your todos: {err}"))?;
+    let archived = environment::acquire_archived_todos(&todos_path)
+        .map_err(|err| format!("rwelcome: error: couldn't read your completed todos: {err}"))?;
+    println!("{}", stats::compute_todo_stats(&active, &archived, chrono::Local::now().date_naive()));
+    Ok(())
+}
+
+/// Print the todos containing `query`, ignoring case, with their numbers,
+/// for `rwelcome edit search`.
+async fn show_todo_search(list: Option<&str>, query: &str) -> Result<(), String> {
+    let todos_path = environment::todos_list_path(&base_todos_path(), list)
+        .map_err(|err| err.to_string())?;
+    let todos = read_todos(todos_path, list.is_none()).await
+        .map_err(|err| format!("rwelcome: error: couldn't read your todos: {err}"))?;
+    let matches = environment::search_todos(&todos, query);
+    if matches.is_empty() {
+        return Err(format!("rwelcome: error: no todos contain \"{query}\"."));
+    }
+    show_todos("Todos", &matches, matches.len(), &theme::load_theme());
+    Ok(())
+}
+
+/// Export the todos as an iCalendar file, to `output` or stdout, for `rwelcome export`.
+async fn run_export(list: Option<&str>, output: Option<&str>) -> Result<(), String> {
+    let todos_path = environment::todos_list_path(&base_todos_path(), list)
+        .map_err(|err| err.to_string())?;
+    let todos = read_todos(todos_path, list.is_none()).await
+        .map_err(|err| format!("rwelcome: error: couldn't read your todos: {err}"))?;
+    let ical = export::export_todos_ical(&todos);
+    match output {
+        Some(path) => fs::write(path, ical)
+            .map_err(|err| format!("rwelcome: error: couldn't write {path}: {err}"))?,
+        None => print!("{ical}"),
+    }
+    Ok(())
+}
+
+/// Print or install the completion script for `shell`, for `rwelcome completions`.
+fn run_completions(shell: completions::Shell, install: bool) -> Result<(), String> {
+    let mut cmd = Cli::command();
+    let script = completions::generate(shell, &mut cmd);
+    if !install {
+        print!("{script}");
+        return Ok(());
+    }
+    let version = cmd.get_version().unwrap_or_default();
+    let message = completions::install(shell, cmd.get_name(), version, &script)
+        .map_err(|err| format!("rwelcome: error: couldn't install the {} completions: {err}", shell.name()))?;
+    println!("{message}");
+    Ok(())
+}
+
+/// Create a config file interactively, for `rwelcome init`,
+/// then run the health checks against it.
+fn run_init(list: Option<&str>) -> Result<(), String> {
+    let path = config::config_path()
+        .ok_or("rwelcome: error: couldn't work out where the config file goes, is $HOME set?")?;
+    let written = init::init(&mut io::stdin().lock(), &mut io::stdout(), &path, DEFAULT_MODULES)
+        .map_err(|err| format!("rwelcome: error: couldn't write {}: {err}", path.display()))?;
+    if !written {
+        println!("Kept the existing config file.");
+        return Ok(());
+    }
+    /*
+     * The runtime's threads are running, so the new file's [env] section
+     * can't be applied here. It's read without being applied to find the
+     * todos, and the health checks run in a fresh rwelcome that applies it.
+     */
+    let base_todos_path = ext::env_non_empty("RWELCOME_TODOS_PATH")
+        .or_else(|| config::file_env().remove("RWELCOME_TODOS_PATH").filter(|path| !path.trim().is_empty()))
+        .unwrap_or_else(default_todos_path);
+    let todos_path = environment::todos_list_path(&base_todos_path, list)
+        .map_err(|err| format!("rwelcome: error: {err}"))?;
+    if !environment::is_remote_todos(&todos_path) {
+        environment::create_todos_file(&todos_path)
+            .map_err(|err| format!("rwelcome: error: couldn't create {todos_path}: {err}"))?;
+    }
+    println!();
+    let exe = env::current_exe()
+        .map_err(|err| format!("rwelcome: error: couldn't run the health checks: {err}"))?;
+    let mut healthcheck = std::process::Command::new(exe);
+    healthcheck.arg("healthcheck");
+    if let Some(list) = list {
+        healthcheck.args(["--list", list]);
+    }
+    let status = healthcheck.status()
+        .map_err(|err| format!("rwelcome: error: couldn't run the health checks: {err}"))?;
+    if !status.success() {
+        return Err("rwelcome: error: the new config file didn't pass its health checks.".to_string());
+    }
+    Ok(())
+}
+
+/// Print the result of each health check, for `rwelcome healthcheck`.
+/// Fails if any of the checks did.
+fn show_healthcheck(list: Option<&str>, proc_fs: &ProcFs) -> Result<(), String> {
+    let todos_path = environment::todos_list_path(&base_todos_path(), list)
+        .map_err(|err| err.to_string())?;
+    let results = healthcheck::run_healthcheck(&Config::load(), &todos_path, proc_fs);
+    for result in &results {
+        let status = if result.passed { "OK  ".green() } else { "FAIL".red() };
+        println!("{} {}: {}", status, result.name, result.detail);
+    }
+    let failed = results.iter().filter(|result| !result.passed).count();
+    if failed > 0 {
+        return Err(format!("rwelcome: error: {failed} health check(s) failed."));
+    }
+    Ok(())
+}
+
+/// The module names accepted by `rwelcome show <module>`.
+const SHOW_MODULES: &[&str] = &["user", "hostname", "uptime", "memory", "os", "kernel", "cpu", "cpu-temp"];
+
+/// Print a single metric as a plain value (no label, no color), for use in
+/// scripts and status bars, e.g. `mem=$(rwelcome show memory)`.
+fn show_single_module(proc_fs: &ProcFs, module_name: &str) -> Result<(), String> {
+    let value = match module_name {
+        "user" => environment::acquire_current_user()
+            .ok_or_else(|| "couldn't determine the current user".to_string()),
+        "hostname" => environment::acquire_hostname(proc_fs).map_err(|e| e.to_string()),
+        "uptime" => environment::acquire_uptime(proc_fs)
+            .map(|uptime| uptime.as_seconds().to_string())
+            .map_err(|e| e.to_string()),
+        "memory" => environment::acquire_memory_info(proc_fs)
+            .map(|mem| {
+                let sep = ext::number_separator();
+                format!(
+                    "{} MiB / {} MiB",
+                    ext::format_number(mem.used_excluding_caches() / 1024, sep),
+                    ext::format_number(mem.total / 1024, sep),
+                )
+            })
+            .map_err(|e| e.to_string()),
+        "os" => environment::acquire_os_info(proc_fs)
+            .map(|os| match os.version {
+                Some(version) => format!("{} {}", os.name, version),
+                None => os.name,
+            })
+            .map_err(|e| e.to_string()),
+        "kernel" => environment::acquire_kernel_version(proc_fs, false).map_err(|e| e.to_string()),
+        "cpu" => environment::acquire_cpu_model(proc_fs).map_err(|e| e.to_string()),
+        "cpu-temp" => environment::acquire_cpu_temperature(proc_fs)
+            .map(|temp| format!("{:.1}", temp.value))
+            .map_err(|e| e.to_string()),
+        _ => {
+            eprintln!("valid modules: {}", SHOW_MODULES.join(", "));
+            return Err(format!("rwelcome: error: unknown module '{module_name}'."));
+        },
+    };
+    match value {
+        Ok(value) => {
+            println!("{value}");
+            Ok(())
+        },
+        Err(err) => Err(format!("rwelcome: error: {err}")),
     }
 }
 
-async fn load() -> Result<Rwelcome, String> {
+/// Neatly format a list of todos to stdout, under the heading `label`.
+/// `todos` holds the items to display with their numbers in the file, and may have
+/// been capped to fewer than `total` items; in that case the header says how many
+/// are being shown.
+/// With RWELCOME_TODOS_SHOW_COUNT=1 the header always includes the count,
+/// and with RWELCOME_TODOS_SHOW_DATE=1 each todo shows the date it was added.
+pub fn show_todos(label: &str, todos: &[(usize, &Todo)], total: usize, theme: &Theme) {
+    let show_count = ext::env_flag("RWELCOME_TODOS_SHOW_COUNT");
+    let label = theme::paint(label, theme.label);
+    if todos.len() < total {
+        println!("{} (showing {} of {}):", label, todos.len(), total);
+    } else if show_count {
+        println!("{} ({}):", label, total);
+    } else if todos.is_empty() {
+        println!("{}: none!", label);
+    } else {
+        println!("{}:", label);
+    }
+    let width = render::effective_width();
+    let today = chrono::Local::now().date_naive();
+    let use_color = theme::colors_enabled();
+    let show_date = ext::env_flag("RWELCOME_TODOS_SHOW_DATE");
+    for (number, todo) in todos {
+        let prefix = format!("  {}. ", number);
+        let date = match todo.added {
+            Some(added) if show_date => format!("{} ", added.format("%Y-%m-%d")),
+            _ => String::new(),
+        };
+        let due = todo.due();
+        let text = match theme::due_date_label(due, today) {
+            Some(label) if !use_color => format!("{label} {}", todo.text),
+            _ => todo.text.clone(),
+        };
+        let text = ext::wrap_text(&text, width, prefix.len() + date.len()).join("\n");
+        let text = match theme::due_date_color(due, today) {
+            Some(color) if color == colored::Color::Red => text.color(color).bold(),
+            Some(color) => text.color(color),
+            None => text.normal(),
+        };
+        println!("{}{}{}", prefix, date.dimmed(), text);
+    }
+}
+
+// Format uptime using its two most significant units,
+// e.g. "42s", "14m 08s", "3h 14m" or "2d 3h".
+fn format_uptime(uptime: &environment::UptimeInfo) -> String {
+    if uptime.days > 0 {
+        format!("{}d {}h", uptime.days, uptime.hours)
+    } else if uptime.hours > 0 {
+        format!("{}h {}m", uptime.hours, uptime.minutes)
+    } else if uptime.minutes > 0 {
+        format!("{}m {:02}s", uptime.minutes, uptime.seconds)
+    } else {
+        format!("{}s", uptime.seconds)
+    }
+}
+
+/// Formats the uptime, which RWELCOME_UPTIME_WARN_DAYS turns yellow as a reminder
+/// to reboot, and RWELCOME_UPTIME_CRIT_DAYS (twice that by default) red.
+/// A warning threshold of 0, the default, turns this off.
+fn color_uptime(uptime: &environment::UptimeInfo, theme: &Theme) -> String {
+    let warn_days = ext::parse_env_number("RWELCOME_UPTIME_WARN_DAYS", 0);
+    if warn_days == 0 {
+        return format_uptime(uptime);
+    }
+    let crit_days = ext::parse_env_number("RWELCOME_UPTIME_CRIT_DAYS", warn_days * 2);
+    render::threshold_color(
+        &format_uptime(uptime),
+        uptime.days as f64,
+        Some(warn_days as f64),
+        Some(crit_days as f64),
+        theme,
+    ).to_string()
+}
+
+// Print a line of `ch` as wide as text displayed `displayed_len` columns wide,
+// but never wider than the effective output width.
+fn draw_separator(displayed_len: usize, ch: char) {
+    let length = displayed_len.min(render::effective_width());
+    println!("{}", ch.to_string().repeat(length));
+}
+
+#[cfg(feature = "weather")]
+// Format a weather response compactly, e.g. "🌤️  18°C Brighton".
+fn format_weather_short(weather: &WeatherResponse, icons: &HashMap<String, String>, units: WeatherUnits) -> String {
+    format!(
+        "{}  {} {}",
+        weather::condition_to_icon(&weather.current.condition.text, icons),
+        weather::format_weather(weather, units).temperature,
+        weather::display_location(&weather.location),
+    )
+}
+
+#[cfg(feature = "weather")]
+// Format the air quality of a weather response, e.g. "42 (Good)" in green.
+// The category and color both come from the AQI shown, not weatherapi.com's own index.
+fn format_aqi(weather: &WeatherResponse) -> Option<colored::ColoredString> {
+    let aqi = weather::pm2_5_to_aqi(weather.current.air_quality.as_ref()?.pm2_5);
+    let index = weather::aqi_to_epa_index(aqi);
+    let reading = format!("{} ({})", aqi, weather::epa_category(index));
+    Some(match index {
+        1 => reading.green(),
+        2 => reading.yellow(),
+        3 => reading.truecolor(255, 165, 0),
+        _ => reading.red(),
+    })
+}
+
+/*
+ * If we have an API key, acquire weather from the provider chosen by
+ * RWELCOME_WEATHER_PROVIDER (weatherapi.com by default). Open-Meteo
+ * doesn't need a key.
+ * Returns the response for the main location, and the response for the
+ * second location (RWELCOME_WEATHER_LOCATION_2) if it succeeded. Each
+ * location's response is cached on its own, see `weather::fetch_cached()`.
+ */
+#[cfg(feature = "weather")]
+async fn load_weather(config: &Config) -> (Option<io::Result<WeatherResponse>>, Option<WeatherResponse>) {
+    match weather::build_provider(config) {
+        Some(provider) => {
+            let location = match (config.lat, config.lon) {
+                (Some(lat), Some(lon)) => WeatherLocation::Coordinates { lat, lon },
+                _ => WeatherLocation::Name(
+                    ext::env_non_empty("RWELCOME_WEATHER_LOCATION").unwrap_or_else(|| "Brighton".to_string()),
+                ),
+            };
+            /*
+             * A second location is fetched concurrently with the first.
+             * If it fails, it's simply left out of the display.
+             */
+            match ext::env_non_empty("RWELCOME_WEATHER_LOCATION_2") {
+                Some(second_location) => {
+                    let second_location = WeatherLocation::parse(&second_location);
+                    let (first, second) = tokio::join!(
+                        weather::fetch_cached(provider.as_ref(), &config.weather_provider, &location),
+                        weather::fetch_cached(provider.as_ref(), &config.weather_provider, &second_location),
+                    );
+                    (Some(first), second.ok())
+                },
+                None => (Some(weather::fetch_cached(provider.as_ref(), &config.weather_provider, &location).await), None),
+            }
+        },
+        None => (None, None),
+    }
+}
+
+async fn load(edit: Option<TodoEdit>, todos_path: String, default_list: bool, proc_fs: ProcFs) -> Result<Rwelcome, String> {
+    let config = Config::load();
     let username = environment::acquire_current_user().unwrap_or_else(|| "unknown".to_string());
 
-    let default_todos_path = format!("/home/{username}/.local/share/rwelcome/todos");
-    let todos_path = env::var("RWELCOME_TODOS_PATH").unwrap_or(default_todos_path);
+    /*
+     * Disk I/O is measured over RWELCOME_DISK_SAMPLE_MS milliseconds (500 by
+     * default), of RWELCOME_DISK_IO_DEVICE or else the root filesystem's device.
+     */
+    let disk_io = async {
+        if !ext::env_flag("RWELCOME_SHOW_DISK_IO") {
+            return None;
+        }
+        let sample = Duration::from_millis(ext::parse_env_number("RWELCOME_DISK_SAMPLE_MS", 500));
+        let device = match ext::env_non_empty("RWELCOME_DISK_IO_DEVICE") {
+            Some(device) => device,
+            #[cfg(target_os = "linux")]
+            None => match environment::acquire_root_device(&proc_fs) {
+                Ok(device) => device,
+                Err(err) => return Some(Err(err)),
+            },
+            #[cfg(not(target_os = "linux"))]
+            None => String::new(),
+        };
+        Some(environment::acquire_disk_throughput(&proc_fs, &device, sample).await)
+    };
+
+    /*
+     * RWELCOME_PING_HOSTS is a comma-separated list of hosts to ping, each
+     * optionally named, e.g. "gateway=192.168.1.1,dns=8.8.8.8". Each gets
+     * RWELCOME_PING_TIMEOUT_MS milliseconds (1000 by default) to answer.
+     */
+    let ping = async {
+        let hosts = environment::parse_ping_hosts(&ext::env_non_empty("RWELCOME_PING_HOSTS")?);
+        let timeout = Duration::from_millis(ext::parse_env_number("RWELCOME_PING_TIMEOUT_MS", 1000));
+        Some(environment::acquire_ping_latencies(hosts, timeout).await)
+    };
+
+    let budget = Duration::from_millis(ext::parse_env_number("RWELCOME_MAX_RENDER_TIME_MS", 5000));
+
+    /*
+     * The modules that run commands (and NFS, whose check can hang on a
+     * stale mount) block, so each gets a thread of its own. Those that are
+     * turned off aren't run at all.
+     */
+    let modules = module_names();
+    let shown = |name: &str, flag: Option<&str>| {
+        modules.iter().any(|module| module == name) && flag.is_none_or(ext::env_flag)
+    };
+    let updates_proc_fs = proc_fs.clone();
+    let updates = acquire_blocking(shown("updates", Some("RWELCOME_SHOW_UPDATES")), move || {
+        environment::acquire_pending_updates(&updates_proc_fs)
+    });
+    let git = acquire_blocking(shown("git", Some("RWELCOME_SHOW_GIT")), || {
+        // RWELCOME_GIT_REPO_PATH picks the repository, by default the home directory.
+        let path = ext::env_non_empty("RWELCOME_GIT_REPO_PATH")
+            .or_else(|| env::var("HOME").ok())
+            .unwrap_or_else(|| ".".to_string());
+        environment::acquire_git_status(&path)
+    });
+    let zfs = acquire_blocking(shown("zfs", Some("RWELCOME_SHOW_ZFS")), environment::acquire_zfs_pool_health);
+    let nfs_proc_fs = proc_fs.clone();
+    let nfs = acquire_blocking(shown("nfs", Some("RWELCOME_SHOW_NFS")), move || environment::acquire_nfs_mounts(&nfs_proc_fs));
+    let gpu = acquire_blocking(shown("gpu", Some("RWELCOME_SHOW_GPU")), environment::acquire_nvidia_gpu_stats);
+    let failed_units_proc_fs = proc_fs.clone();
+    let failed_units = acquire_blocking(shown("failed-units", None), move || {
+        environment::acquire_systemd_failed_units(&failed_units_proc_fs)
+    });
+    let fortune = acquire_blocking(ext::env_flag("RWELCOME_FORTUNE"), || {
+        let path = ext::env_non_empty("RWELCOME_FORTUNE_PATH");
+        environment::acquire_fortune(path.as_deref(), ext::env_flag("RWELCOME_FORTUNE_SHORT"))
+    });
+    let vnstat = acquire_blocking(shown("vnstat", Some("RWELCOME_SHOW_VNSTAT")), || {
+        // RWELCOME_VNSTAT_IFACE picks the network interface, eth0 by default.
+        let iface = ext::env_non_empty("RWELCOME_VNSTAT_IFACE").unwrap_or_else(|| "eth0".to_string());
+        environment::acquire_vnstat_traffic(&iface)
+    });
+    let resolution_proc_fs = proc_fs.clone();
+    let resolution = acquire_blocking(shown("resolution", Some("RWELCOME_SHOW_RESOLUTION")), move || {
+        environment::acquire_screen_resolution(&resolution_proc_fs)
+    });
+    let containers_proc_fs = proc_fs.clone();
+    let containers = acquire_blocking(shown("containers", Some("RWELCOME_SHOW_CONTAINERS")), move || {
+        environment::acquire_running_containers(&containers_proc_fs)
+    });
+    // The compact line shows the CPU temperature and disk usage whatever the modules.
+    let compact = ext::env_flag("RWELCOME_COMPACT");
+    let cpu_temp_proc_fs = proc_fs.clone();
+    let cpu_temp = acquire_blocking(compact || shown("cpu-temp", None), move || {
+        environment::acquire_cpu_temperature(&cpu_temp_proc_fs)
+    });
+    let disk = acquire_blocking(
+        ext::env_flag("RWELCOME_SHOW_DISK") && (compact || shown("disk", None)),
+        || environment::acquire_disk_usage(&disk_path()),
+    );
+    let dmesg_proc_fs = proc_fs.clone();
+    let dmesg = acquire_blocking(shown("dmesg", Some("RWELCOME_SHOW_DMESG_ERRORS")), move || {
+        // RWELCOME_DMESG_MAX_LINES caps how many of the latest errors are shown.
+        let max_lines = ext::parse_env_number("RWELCOME_DMESG_MAX_LINES", 3);
+        environment::acquire_dmesg_errors(&dmesg_proc_fs, max_lines)
+    });
+
+    /*
+     * The todos are read on a thread of their own too, since decrypting them
+     * may wait on gpg. An edit needs them whatever the time, so it waits.
+     */
+    if edit.is_some() && environment::is_remote_todos(&todos_path) {
+        return Err("rwelcome: error: remote todo lists are read-only.".to_string());
+    }
+    let todos_budget = if edit.is_some() { Duration::ZERO } else { budget / 2 };
+    let todos = {
+        let todos_path = todos_path.clone();
+        let runtime = tokio::runtime::Handle::current();
+        acquire_blocking(true, move || runtime.block_on(read_todos(todos_path, default_list)))
+    };
 
     /*
-     * If we have an API key, acquire weather from Open Weather API.
+     * Acquire the network-based info (weather and public IP) concurrently,
+     * along with the disk I/O sample, the pings, the modules above and the
+     * todos, which are mostly spent waiting. They're all polled by the one
+     * join, so they all start straight away.
      *
      * Do this before everything else, so that it's ready by the time
      * we go to render.
+     *
+     * RWELCOME_MAX_RENDER_TIME_MS (5000 by default, 0 for no limit) caps how
+     * long this takes. Weather may need two round trips, so it gets the whole
+     * budget; the rest get half of it. Whatever isn't done by then isn't shown.
      */
-    let maybe_weather_response = match env::var("RWELCOME_WEATHER_API_KEY") {
-        Ok(key) => Some(weather::acquire(key).await),
-        Err(_) => None,
+    #[cfg(feature = "weather")]
+    let network = async {
+        tokio::join!(
+            within_budget(budget, load_weather(&config)),
+            within_budget(budget / 2, async {
+                if ext::env_flag("RWELCOME_SHOW_PUBLIC_IP") {
+                    Some(environment::acquire_public_ip().await)
+                } else {
+                    None
+                }
+            }),
+        )
+    };
+    #[cfg(not(feature = "weather"))]
+    let network = async {};
+    let (
+        network, maybe_disk_io, maybe_ping, updates, git, zfs, nfs, gpu, failed_units, fortune,
+        vnstat, resolution, containers, cpu_temp, disk, dmesg, todos,
+    ) = tokio::join!(
+        network,
+        within_budget(budget / 2, disk_io),
+        within_budget(budget / 2, ping),
+        within_budget(budget / 2, updates),
+        within_budget(budget / 2, git),
+        within_budget(budget / 2, zfs),
+        within_budget(budget / 2, nfs),
+        within_budget(budget / 2, gpu),
+        within_budget(budget / 2, failed_units),
+        within_budget(budget / 2, fortune),
+        within_budget(budget / 2, vnstat),
+        within_budget(budget / 2, resolution),
+        within_budget(budget / 2, containers),
+        within_budget(budget / 2, cpu_temp),
+        within_budget(budget / 2, disk),
+        within_budget(budget / 2, dmesg),
+        within_budget(todos_budget, todos),
+    );
+    let timed_out = maybe_disk_io.is_none() || maybe_ping.is_none() || updates.is_none() || git.is_none()
+        || zfs.is_none() || nfs.is_none() || gpu.is_none() || failed_units.is_none() || fortune.is_none()
+        || vnstat.is_none() || resolution.is_none() || containers.is_none() || cpu_temp.is_none()
+        || disk.is_none() || dmesg.is_none() || todos.is_none();
+    #[cfg(feature = "weather")]
+    let (weather, maybe_public_ip) = network;
+    #[cfg(not(feature = "weather"))]
+    let () = network;
+    #[cfg(feature = "weather")]
+    let timed_out = timed_out || weather.is_none() || maybe_public_ip.is_none();
+    #[cfg(feature = "weather")]
+    let ((maybe_weather_response, maybe_second_weather), maybe_public_ip) =
+        (weather.unwrap_or((None, None)), maybe_public_ip.flatten());
+    let maybe_disk_io = maybe_disk_io.flatten();
+    let maybe_ping = maybe_ping.flatten();
+    let mut todos = match todos {
+        Some(Some(todos)) => todos,
+        Some(None) => Err(io::Error::other("reading them failed")),
+        None => Err(io::Error::new(io::ErrorKind::TimedOut, "they took too long to read")),
     };
 
     /*
-     * If the RWELCOME_TODOS environment variable is present,
-     * parse the todos file into memory for rendering later.
+     * Apply the edit given on the command line, if any.
      */
-    let mut todos = environment::acquire_todos(todos_path.clone()).await;
-
-    /*
-     * Handle arguments
-    */
-    let mut args: Vec<String> = env::args().collect();
-    if args.len() > 1 {
-        let given_arg = &args[1];
-        if given_arg != "edit" {
-            return Err(format!("rwelcome: error: {given_arg} is not a valid verb."));
-        }
+    if let Some(edit) = edit {
         let mut current_todos = match todos {
             Ok(todos) => todos,
-            Err(err) => return Err(format!("{}", err)),
+            Err(err) => return Err(format!("rwelcome: error: couldn't read your todos: {err}")),
         };
-        let wants_editor = args.len() == 2;
-        todos = environment::edit_todos(
-            &mut current_todos,
-            wants_editor,
-            &mut args,
-            todos_path.clone()
-        ).await; 
+        // A failed edit is fatal, so scripts can tell it didn't happen.
+        let edited = environment::edit_todos(&mut current_todos, edit, todos_path.clone(), false).await
+            .map_err(|err| err.to_string())?;
+        todos = Ok(edited);
+    }
+    Ok(Rwelcome{
+        config,
+        proc_fs,
+        username,
+        #[cfg(feature = "weather")]
+        maybe_weather_response,
+        #[cfg(feature = "weather")]
+        maybe_second_weather,
+        #[cfg(feature = "weather")]
+        maybe_public_ip,
+        maybe_disk_io,
+        maybe_ping,
+        maybe_updates: updates.flatten(),
+        maybe_git: git.flatten(),
+        maybe_zfs: zfs.flatten(),
+        maybe_nfs: nfs.flatten(),
+        maybe_gpu: gpu.flatten(),
+        maybe_failed_units: failed_units.flatten(),
+        maybe_fortune: fortune.flatten().flatten(),
+        maybe_vnstat: vnstat.flatten(),
+        maybe_resolution: resolution.flatten(),
+        maybe_containers: containers.flatten(),
+        maybe_cpu_temp: cpu_temp.flatten(),
+        maybe_disk: disk.flatten(),
+        maybe_dmesg: dmesg.flatten(),
+        modules,
+        timed_out,
+        todos,
+    })
+}
+
+/// The filesystem the disk module shows: RWELCOME_DISK_PATH, by default the root.
+fn disk_path() -> String {
+    ext::env_non_empty("RWELCOME_DISK_PATH").unwrap_or_else(|| "/".to_string())
+}
+
+/// Runs the blocking `acquire` on a thread of its own if `enabled`.
+/// Gives None if it isn't, or if the thread panicked.
+async fn acquire_blocking<T: Send + 'static>(enabled: bool, acquire: impl FnOnce() -> T + Send + 'static) -> Option<T> {
+    if !enabled {
+        return None;
+    }
+    tokio::task::spawn_blocking(acquire).await.ok()
+}
+
+/// Runs `future` for at most `budget`, or without a limit if it's zero.
+/// Returns None if it ran out of time.
+async fn within_budget<T>(budget: Duration, future: impl std::future::Future<Output = T>) -> Option<T> {
+    if budget.is_zero() {
+        return Some(future.await);
     }
-    Ok(Rwelcome{ username, maybe_weather_response, todos })
+    tokio::time::timeout(budget, future).await.ok()
+}
+
+/// With RWELCOME_BARS=1, a bar showing `used` out of `total` to go after a value,
+/// RWELCOME_BAR_WIDTH cells wide. Otherwise an empty string.
+fn bar_suffix(used: f64, total: f64, theme: &Theme) -> String {
+    if !ext::env_flag("RWELCOME_BARS") {
+        return String::new();
+    }
+    let width = ext::parse_env_number("RWELCOME_BAR_WIDTH", 15);
+    format!(" {}", render::render_bar(used, total, width, theme))
+}
+
+/// The system info modules, in display order.
+/// RWELCOME_MODULES can reorder them or leave some out, e.g. "kernel,uptime,memory".
+const DEFAULT_MODULES: &[&str] = &[
+    "uptime", "memory", "pressure", "hugepages", "conntrack", "entropy", "os", "last-update", "kernel", "cpu", "cpu-temp", "gpu",
+    "resolution", "public-ip", "datetime", "containers", "updates", "failed-units", "zfs", "git", "disk", "disk-io", "ping", "vnstat", "nfs", "terminal", "dmesg",
+    "sensors",
+];
+
+fn module_names() -> Vec<String> {
+    let configured = match ext::env_non_empty("RWELCOME_MODULES") {
+        Some(configured) => configured,
+        None => return DEFAULT_MODULES.iter().map(|name| name.to_string()).collect(),
+    };
+    configured
+        .split(',')
+        .map(|name| name.trim().to_lowercase())
+        .filter(|name| {
+            let known = DEFAULT_MODULES.contains(&name.as_str());
+            if !known {
+                eprintln!(
+                    "rwelcome: warning: unknown module \"{name}\" in RWELCOME_MODULES, expected one of {}",
+                    DEFAULT_MODULES.join(",")
+                );
+            }
+            known
+        })
+        .collect()
+}
+
+/// Acquire and format a system info module that may show several lines,
+/// such as one per ZFS pool. Otherwise the same as `render_module()`.
+fn render_module_lines(name: &str, ctx: &Rwelcome) -> Option<Result<Vec<LabeledLine>, LabeledLine>> {
+    let proc_fs = &ctx.proc_fs;
+    let lines = match name {
+        "memory" => match environment::acquire_memory_info(proc_fs) {
+            Ok(mem) => Ok(memory_lines(&mem, cgroup_memory(proc_fs), &ctx.config.label_memory, &ctx.config.theme)),
+            Err(err) => Err(LabeledLine::new(&ctx.config.label_memory, err.to_string())),
+        },
+        "zfs" => match &ctx.maybe_zfs {
+            Some(Ok(Some(pools))) => Ok(pools.iter()
+                .map(|pool| {
+                    let health = if pool.health == "ONLINE" { pool.health.green() } else { theme::paint(&pool.health, ctx.config.theme.error) };
+                    LabeledLine::new("zfs", format!("{} {}", pool.name, health))
+                })
+                .collect()),
+            Some(Err(err)) => Err(LabeledLine::new("zfs", err.to_string())),
+            _ => return None,
+        },
+        // Always shown, but only when something has failed.
+        "failed-units" => match &ctx.maybe_failed_units {
+            Some(Ok(Some(units))) if !units.is_empty() => {
+                Ok(vec![LabeledLine::new("⚠ Failed services", theme::paint(&units.join(", "), ctx.config.theme.error).bold().to_string())])
+            },
+            Some(Err(err)) => Err(LabeledLine::new("Failed services", err.to_string())),
+            _ => return None,
+        },
+        "gpu" => match &ctx.maybe_gpu {
+            Some(Ok(Some(gpus))) if !gpus.is_empty() => {
+                // The temperature turns yellow at RWELCOME_GPU_TEMP_WARN and red at RWELCOME_GPU_TEMP_CRIT.
+                let warn = ext::parse_env_f64("RWELCOME_GPU_TEMP_WARN", 80.0);
+                let crit = ext::parse_env_f64("RWELCOME_GPU_TEMP_CRIT", 95.0);
+                let sep = ext::number_separator();
+                let numbered = gpus.len() > 1;
+                Ok(gpus.iter().enumerate()
+                    .map(|(index, gpu)| {
+                        let label = if numbered { format!("GPU {index}") } else { "GPU".to_string() };
+                        let temp = format!("{:.0}°C", gpu.temp_c);
+                        LabeledLine::new(&label, format!(
+                            "{}, {}%, {} MiB / {} MiB{}, {}",
+                            gpu.name,
+                            gpu.utilization_pct,
+                            ext::format_number(gpu.mem_used_mib, sep),
+                            ext::format_number(gpu.mem_total_mib, sep),
+                            bar_suffix(gpu.mem_used_mib as f64, gpu.mem_total_mib as f64, &ctx.config.theme),
+                            render::threshold_color(&temp, gpu.temp_c, Some(warn), Some(crit), &ctx.config.theme),
+                        ))
+                    })
+                    .collect())
+            },
+            Some(Err(err)) => Err(LabeledLine::new("GPU", err.to_string())),
+            _ => return None,
+        },
+        "nfs" => match &ctx.maybe_nfs {
+            Some(Ok(mounts)) if mounts.is_empty() => return None,
+            Some(Ok(mounts)) => Ok(mounts.iter()
+                .map(|mount| {
+                    let line = format!("{} on {}", mount.remote, mount.local);
+                    let line = if mount.reachable { line.green() } else { theme::paint(&format!("{line} (stale)"), ctx.config.theme.error) };
+                    LabeledLine::new("NFS", line.to_string())
+                })
+                .collect()),
+            Some(Err(err)) => Err(LabeledLine::new("NFS", err.to_string())),
+            None => return None,
+        },
+        "dmesg" => match &ctx.maybe_dmesg {
+            Some(Ok(errors)) if errors.is_empty() => return None,
+            Some(Ok(errors)) => Ok(errors.iter()
+                .map(|error| LabeledLine::new("Kernel", theme::paint(error, ctx.config.theme.error).to_string()))
+                .collect()),
+            Some(Err(err)) => Err(LabeledLine::new("Kernel", err.to_string())),
+            None => return None,
+        },
+        "sensors" if ext::env_flag("RWELCOME_SHOW_ALL_SENSORS") => match environment::acquire_sensors_all(proc_fs) {
+            Ok(sensors) if sensors.is_empty() => return None,
+            Ok(sensors) => {
+                // One line per hwmon device, its sensors side by side.
+                let mut lines: Vec<LabeledLine> = Vec::new();
+                for sensor in sensors {
+                    let precision = match sensor.unit {
+                        environment::SensorUnit::Celsius => 1,
+                        environment::SensorUnit::Rpm => 0,
+                        environment::SensorUnit::Volts => 2,
+                    };
+                    let reading = format!(
+                        "{} {:.*}{}", sensor.sensor_label, precision, sensor.value, sensor.unit.symbol(),
+                    );
+                    match lines.last_mut() {
+                        Some(line) if line.label == sensor.hwmon_name => {
+                            line.value = format!("{}, {}", line.value, reading);
+                        },
+                        _ => lines.push(LabeledLine::new(&sensor.hwmon_name, reading)),
+                    }
+                }
+                Ok(lines)
+            },
+            Err(err) => Err(LabeledLine::new("Sensors", err.to_string())),
+        },
+        "vnstat" => match &ctx.maybe_vnstat {
+            Some(Ok(Some(traffic))) => Ok(vec![
+                LabeledLine::new("Traffic today", format!(
+                    "↓ {:.1} GiB  ↑ {:.1} GiB", traffic.daily_rx_gib, traffic.daily_tx_gib,
+                )),
+                LabeledLine::new("Traffic this month", format!(
+                    "↓ {:.1} GiB  ↑ {:.1} GiB", traffic.monthly_rx_gib, traffic.monthly_tx_gib,
+                )),
+            ]),
+            Some(Err(err)) => Err(LabeledLine::new("Traffic", err.to_string())),
+            _ => return None,
+        },
+        _ => return render_module(name, ctx).map(|line| line.map(|line| vec![line])),
+    };
+    Some(lines)
+}
+
+/// The memory used by and available to the cgroup rwelcome runs in, in kB, if
+/// it has a limit. RWELCOME_CGROUP_MEM=1 or 0 turns this on or off; by default
+/// it's on inside containers, where /proc/meminfo describes the host.
+#[cfg(target_os = "linux")]
+fn cgroup_memory(proc_fs: &ProcFs) -> Option<(Option<u64>, u64)> {
+    let enabled = match env::var("RWELCOME_CGROUP_MEM") {
+        Ok(_) => ext::env_flag("RWELCOME_CGROUP_MEM"),
+        Err(_) => environment::acquire_in_container(proc_fs),
+    };
+    if !enabled {
+        return None;
+    }
+    let limit = environment::acquire_cgroup_memory_limit(proc_fs).ok()??;
+    let usage = environment::acquire_cgroup_memory_usage(proc_fs).ok().flatten();
+    Some((usage.map(|usage| usage / 1024), limit / 1024))
+}
+
+/// There are no cgroups elsewhere.
+#[cfg(not(target_os = "linux"))]
+fn cgroup_memory(_proc_fs: &ProcFs) -> Option<(Option<u64>, u64)> {
+    None
+}
+
+/// Formats the memory figures, or the container's when `cgroup` has its usage
+/// (if known) and limit in kB, see `cgroup_memory()`.
+fn memory_lines(mem: &environment::MemInfo, cgroup: Option<(Option<u64>, u64)>, label: &str, theme: &Theme) -> Vec<LabeledLine> {
+    /*
+     * RWELCOME_MEMORY_MODE picks what counts as used memory:
+     * "available" (default) treats reclaimable caches as free,
+     * "free" only treats physically unused memory as free.
+     * When the mode is set explicitly, it's shown after the figures.
+     */
+    let mode = ext::env_one_of("RWELCOME_MEMORY_MODE", &["available", "free"], "available");
+    let used = if mode == "free" { mem.used_including_caches() } else { mem.used_excluding_caches() };
+    let suffix = match env::var("RWELCOME_MEMORY_MODE") {
+        Ok(_) if mode == "free" => " (free)",
+        Ok(_) => " (avail)",
+        Err(_) => "",
+    };
+    // In a memory-limited container, its own figures replace the host's.
+    let (used, total, cgroup) = match cgroup {
+        Some((usage, limit)) => (usage.unwrap_or(used).min(limit), limit, " (cgroup)"),
+        None => (used, mem.total, ""),
+    };
+    /*
+     * RWELCOME_MEMORY_BREAKDOWN=1 shows the caches separately, for the whole
+     * system: used, cache and total, then the cache split into the page cache
+     * and buffers on a second line.
+     */
+    if ext::env_flag("RWELCOME_MEMORY_BREAKDOWN") && cgroup.is_empty() {
+        let gib = |kb: u64| format!("{:.1} GiB", kb as f64 / (1024.0 * 1024.0));
+        return vec![
+            LabeledLine::new(label, format!(
+                "{} used  |  {} cache  |  {} total{}{}",
+                gib(used),
+                gib(mem.cached_kb + mem.buffers_kb),
+                gib(total),
+                suffix,
+                bar_suffix(used as f64, total as f64, theme),
+            )),
+            LabeledLine::new("Cache", format!(
+                "{} page cache  |  {} buffers",
+                gib(mem.cached_kb),
+                gib(mem.buffers_kb),
+            )),
+        ];
+    }
+    let sep = ext::number_separator();
+    vec![LabeledLine::new(label, format!(
+        "{} MiB / {} MiB{}{}{}",
+        ext::format_number(used / 1024, sep),
+        ext::format_number(total / 1024, sep),
+        suffix,
+        cgroup,
+        bar_suffix(used as f64, total as f64, theme),
+    ))]
+}
+
+/// Acquire and format a single system info module as a label and value.
+/// Returns Some(Err(..)) with the module's label and the error if acquisition
+/// failed, or None when the module has nothing to show.
+fn render_module(name: &str, ctx: &Rwelcome) -> Option<Result<LabeledLine, LabeledLine>> {
+    let proc_fs = &ctx.proc_fs;
+    let line = match name {
+        "uptime" => match environment::acquire_uptime(proc_fs) {
+            Ok(uptime) => {
+                // RWELCOME_SHOW_IDLE=1 adds how idle the CPU has been since boot.
+                let idle = match uptime.idletime_pct {
+                    Some(pct) if ext::env_flag("RWELCOME_SHOW_IDLE") => format!(" (idle {:.0}%)", pct),
+                    _ => String::new(),
+                };
+                // The bar fills up over RWELCOME_UPTIME_MAX_DAYS.
+                let max_days = ext::parse_env_f64("RWELCOME_UPTIME_MAX_DAYS", 30.0);
+                let bar = bar_suffix(uptime.as_seconds() as f64, max_days * 86400.0, &ctx.config.theme);
+                let formatted = color_uptime(&uptime, &ctx.config.theme);
+                Ok(LabeledLine::new(&ctx.config.label_uptime, format!("{}{}{}", formatted, idle, bar)))
+            },
+            Err(err) => Err(LabeledLine::new(&ctx.config.label_uptime, err.to_string())),
+        },
+        "pressure" if ext::env_flag("RWELCOME_SHOW_PRESSURE") => match environment::acquire_memory_pressure(proc_fs) {
+            Ok(Some(pressure)) => Ok(LabeledLine::new("Mem pressure", format!(
+                "{:.1}%/{:.1}% (10s some/full)", pressure.some_avg10, pressure.full_avg10,
+            ))),
+            Ok(None) => return None,
+            Err(err) => Err(LabeledLine::new("Mem pressure", err.to_string())),
+        },
+        "hugepages" if ext::env_flag("RWELCOME_SHOW_HUGEPAGES") => match environment::acquire_memory_info(proc_fs) {
+            Ok(mem) => Ok(LabeledLine::new("HugePages", format_hugepages(&mem)?)),
+            Err(err) => Err(LabeledLine::new("HugePages", err.to_string())),
+        },
+        "conntrack" if ext::env_flag("RWELCOME_SHOW_CONNTRACK") => match environment::acquire_conntrack_count(proc_fs) {
+            Ok(Some(conntrack)) => {
+                let fraction = conntrack.fraction();
+                let pct = format!("({:.1}%)", fraction * 100.0);
+                Ok(LabeledLine::new("Conntrack", format!(
+                    "{} / {} {}", conntrack.count, conntrack.max, render::color_usage(&pct, fraction, &ctx.config.theme),
+                )))
+            },
+            Ok(None) => return None,
+            Err(err) => Err(LabeledLine::new("Conntrack", err.to_string())),
+        },
+        "entropy" if ext::env_flag("RWELCOME_SHOW_ENTROPY") => match environment::acquire_entropy_available(proc_fs) {
+            Ok(bits) => {
+                let bits_text = render::entropy_color(&format!("{bits} bits"), bits, &ctx.config.theme);
+                // The pool's size is only for the percentage, so do without it if it can't be read.
+                match environment::acquire_entropy_poolsize(proc_fs) {
+                    Ok(poolsize) if poolsize > 0 => Ok(LabeledLine::new("Entropy", format!(
+                        "{} ({:.0}%)", bits_text, bits as f64 / poolsize as f64 * 100.0,
+                    ))),
+                    _ => Ok(LabeledLine::new("Entropy", bits_text.to_string())),
+                }
+            },
+            Err(err) => Err(LabeledLine::new("Entropy", err.to_string())),
+        },
+        "os" => match environment::acquire_os_info(proc_fs) {
+            Ok(os) => match os.version {
+                Some(version) => Ok(LabeledLine::new("OS", format!("{} {}", os.name, version))),
+                None => Ok(LabeledLine::new("OS", os.name)),
+            },
+            Err(err) => Err(LabeledLine::new("OS", err.to_string())),
+        },
+        "last-update" => match environment::acquire_last_package_update(proc_fs) {
+            Ok(Some(when)) => {
+                // RWELCOME_DATE_RELATIVE=1 shows e.g. "Yesterday at 14:32" rather than "1 day ago".
+                let now = chrono::Local::now();
+                let when = if ext::env_flag("RWELCOME_DATE_RELATIVE") {
+                    ext::format_relative_date(when, now)
+                } else {
+                    ext::format_time_ago(when, now)
+                };
+                Ok(LabeledLine::new("Last update", when))
+            },
+            Ok(None) => return None,
+            Err(err) => Err(LabeledLine::new("Last update", err.to_string())),
+        },
+        "kernel" => {
+            let kernel_full = ext::env_flag("RWELCOME_KERNEL_FULL");
+            match environment::acquire_kernel_version(proc_fs, kernel_full) {
+                Ok(version) => {
+                    let version = if kernel_full { version } else { format!("Linux {version}") };
+                    // RWELCOME_KERNEL_MAX_LEN of 0 (the default) means no limit.
+                    let version = match ext::parse_env_number("RWELCOME_KERNEL_MAX_LEN", 0) {
+                        0 => version,
+                        max_len => ext::truncate_with_ellipsis(&version, max_len),
+                    };
+                    Ok(LabeledLine::new(&ctx.config.label_kernel, version))
+                },
+                Err(err) => Err(LabeledLine::new(&ctx.config.label_kernel, err.to_string())),
+            }
+        },
+        "cpu" => match environment::acquire_cpu_model(proc_fs) {
+            Ok(model) => {
+                let max_len = ext::parse_env_number("RWELCOME_CPU_MODEL_MAX_LEN", 40);
+                Ok(LabeledLine::new("CPU", ext::truncate_with_ellipsis(&model, max_len)))
+            },
+            Err(err) => Err(LabeledLine::new("CPU", err.to_string())),
+        },
+        "cpu-temp" => match &ctx.maybe_cpu_temp {
+            Some(Ok(temp)) => {
+                let warn = ext::parse_env_f64("RWELCOME_CPU_TEMP_WARN", 80.0);
+                // The bar is full, and the reading red, at RWELCOME_CPU_TEMP_CRIT.
+                let crit = ext::parse_env_f64("RWELCOME_CPU_TEMP_CRIT", 100.0);
+                let reading = format!("{:.1}°C", temp.value);
+                let reading = render::threshold_color(&reading, temp.value, Some(warn), Some(crit), &ctx.config.theme);
+                // RWELCOME_CPU_TEMP_SHOW_SENSORS=1 says how many sensors went into the reading.
+                let sensors = if temp.sensor_count > 1 && ext::env_flag("RWELCOME_CPU_TEMP_SHOW_SENSORS") {
+                    format!(" ({} of {} sensors)", temp.aggregate.name(), temp.sensor_count)
+                } else {
+                    String::new()
+                };
+                let bar = bar_suffix(temp.value, crit, &ctx.config.theme);
+                // RWELCOME_CPU_TEMP_SHOW_LABEL=1 names the sensor, e.g. "CPU temp (Package id 0)".
+                let label = match &temp.label {
+                    Some(sensor) if ext::env_flag("RWELCOME_CPU_TEMP_SHOW_LABEL") => {
+                        format!("{} ({})", ctx.config.label_cpu_temp, sensor)
+                    },
+                    _ => ctx.config.label_cpu_temp.clone(),
+                };
+                Ok(LabeledLine::new(&label, format!("{}{}{}", reading, sensors, bar)))
+            },
+            Some(Err(err)) => Err(LabeledLine::new(&ctx.config.label_cpu_temp, err.to_string())),
+            None => return None,
+        },
+        "datetime" if ext::env_flag("RWELCOME_SHOW_DATETIME") => {
+            Ok(LabeledLine::new("Date", format_datetime(chrono::Local::now())))
+        },
+        "resolution" => match &ctx.maybe_resolution {
+            Some(Ok(Some(resolution))) => Ok(LabeledLine::new("Resolution", resolution.replace('x', "×"))),
+            Some(Err(err)) => Err(LabeledLine::new("Resolution", err.to_string())),
+            _ => return None,
+        },
+        "terminal" if ext::env_flag("RWELCOME_SHOW_TERMINAL") => match environment::acquire_terminal(proc_fs) {
+            Some(terminal) => Ok(LabeledLine::new("Terminal", terminal)),
+            None => return None,
+        },
+        "updates" => match &ctx.maybe_updates {
+            Some(Ok(Some(updates))) => {
+                let security = format!("{} security", updates.security);
+                let security = if updates.security > 0 { theme::paint(&security, ctx.config.theme.error) } else { security.normal() };
+                Ok(LabeledLine::new("Updates", format!("{} ({})", updates.total, security)))
+            },
+            Some(Err(err)) => Err(LabeledLine::new("Updates", err.to_string())),
+            _ => return None,
+        },
+        "git" => match &ctx.maybe_git {
+            Some(Ok(Some(git))) => Ok(LabeledLine::new("Dotfiles", format_git_status(git, &ctx.config.theme))),
+            Some(Err(err)) => Err(LabeledLine::new("Dotfiles", err.to_string())),
+            _ => return None,
+        },
+        "containers" => match &ctx.maybe_containers {
+            Some(Ok(Some(containers))) => Ok(LabeledLine::new("Containers", format_container_counts(containers))),
+            Some(Err(err)) => Err(LabeledLine::new("Containers", err.to_string())),
+            _ => return None,
+        },
+        "disk" => {
+            let label = format!("Disk {}", disk_path());
+            match &ctx.maybe_disk {
+                Some(Ok(disk)) => {
+                    /*
+                     * RWELCOME_SHOW_INODES=1 adds inode usage, since running out of
+                     * inodes is as bad as running out of space.
+                     */
+                    let inodes = match format_inode_usage(disk, &ctx.config.theme) {
+                        Some(inodes) if ext::env_flag("RWELCOME_SHOW_INODES") => format!(" (inodes: {inodes})"),
+                        _ => String::new(),
+                    };
+                    let usage = format!("{} / {}", ext::format_bytes(disk.used), ext::format_bytes(disk.total));
+                    let pct = if disk.total > 0 { disk.used as f64 / disk.total as f64 * 100.0 } else { 0.0 };
+                    Ok(LabeledLine::new(&label, format!(
+                        "{}{}{}",
+                        render::disk_usage_color(&usage, pct, &ctx.config.theme),
+                        inodes,
+                        bar_suffix(disk.used as f64, disk.total as f64, &ctx.config.theme),
+                    )))
+                },
+                Some(Err(err)) => Err(LabeledLine::new(&label, err.to_string())),
+                None => return None,
+            }
+        },
+        "disk-io" => match &ctx.maybe_disk_io {
+            Some(Ok(disk_io)) => {
+                // RWELCOME_SHOW_DISK_IOPS=1 adds the number of reads and writes.
+                let ops = if ext::env_flag("RWELCOME_SHOW_DISK_IOPS") {
+                    format!(" ({:.0} r/s, {:.0} w/s)", disk_io.reads_per_sec, disk_io.writes_per_sec)
+                } else {
+                    String::new()
+                };
+                Ok(LabeledLine::new("Disk I/O", format!(
+                    "↓ {:.1} MB/s  ↑ {:.1} MB/s{}",
+                    disk_io.read_bytes_per_sec / 1_000_000.0,
+                    disk_io.write_bytes_per_sec / 1_000_000.0,
+                    ops,
+                )))
+            },
+            Some(Err(err)) => Err(LabeledLine::new("Disk I/O", err.to_string())),
+            None => return None,
+        },
+        "ping" => match &ctx.maybe_ping {
+            Some(Ok(pings)) if !pings.is_empty() => {
+                let pings: Vec<String> = pings.iter()
+                    .map(|ping| match ping.latency_ms {
+                        Some(latency) => format!("{} {:.1}ms", ping.name, latency),
+                        None => format!("{} {}", ping.name, theme::paint("timeout", ctx.config.theme.error)),
+                    })
+                    .collect();
+                Ok(LabeledLine::new("Ping", pings.join("  ")))
+            },
+            Some(Err(err)) => Err(LabeledLine::new("Ping", err.to_string())),
+            _ => return None,
+        },
+        #[cfg(feature = "weather")]
+        "public-ip" => match &ctx.maybe_public_ip {
+            Some(Ok(ip)) => Ok(LabeledLine::new("Public IP", ip.clone())),
+            Some(Err(err)) => Err(LabeledLine::new("Public IP", err.to_string())),
+            None => return None,
+        },
+        _ => return None,
+    };
+    Some(line)
+}
+
+/// Gather the system info into a snapshot, for the output modes that use one.
+fn snapshot(ctx: &Rwelcome, hostname: &str) -> SystemSnapshot {
+    let proc_fs = &ctx.proc_fs;
+    let mem = environment::acquire_memory_info(proc_fs).ok();
+    SystemSnapshot {
+        username: ctx.username.clone(),
+        hostname: hostname.to_string(),
+        hostname_alias: ctx.config.hostname_alias.clone(),
+        uptime_seconds: environment::acquire_uptime(proc_fs).ok().map(|uptime| uptime.as_seconds()),
+        memory_used_kb: mem.as_ref().map(|mem| mem.used_excluding_caches()),
+        memory_total_kb: mem.as_ref().map(|mem| mem.total),
+        cpu_temp_c: match &ctx.maybe_cpu_temp {
+            Some(Ok(temp)) => Some(temp.value),
+            _ => None,
+        },
+        disk_used_pct: match &ctx.maybe_disk {
+            Some(Ok(disk)) if disk.total > 0 => Some(disk.used as f64 / disk.total as f64 * 100.0),
+            _ => None,
+        },
+        #[cfg(feature = "weather")]
+        weather: match &ctx.maybe_weather_response {
+            Some(Ok(weather)) => Some(WeatherSnapshot {
+                icon: weather::condition_to_icon(&weather.current.condition.text, &ctx.config.weather_icons).to_string(),
+                temperature: weather::format_weather(weather, ctx.config.weather_units).temperature,
+                location: weather.location.name.clone(),
+            }),
+            _ => None,
+        },
+        #[cfg(not(feature = "weather"))]
+        weather: None,
+        todo_count: ctx.todos.as_ref().ok().map(Vec::len),
+    }
+}
+
+/// Formats the running containers of each engine that's available, e.g. `Docker: 2  Podman: 1`.
+fn format_container_counts(containers: &environment::ContainerInfo) -> String {
+    let counts: Vec<String> = [("Docker", containers.docker), ("Podman", containers.podman)]
+        .into_iter()
+        .filter_map(|(engine, count)| count.map(|count| format!("{engine}: {count}")))
+        .collect();
+    counts.join("  ")
+}
+
+/// Formats the huge pages in use out of those reserved, e.g. "512 / 1024 (2 MiB pages)",
+/// or None when none are reserved.
+fn format_hugepages(mem: &environment::MemInfo) -> Option<String> {
+    if mem.hugepages_total == 0 {
+        return None;
+    }
+    Some(format!(
+        "{} / {} ({} pages)",
+        mem.hugepages_total.saturating_sub(mem.hugepages_free),
+        mem.hugepages_total,
+        ext::format_bytes(mem.hugepage_size_kb * 1024),
+    ))
+}
+
+/// Formats the inodes in use out of the total, e.g. "250,112 / 1,245,184", colored
+/// by how full they are. Filesystems that allocate inodes dynamically report a
+/// total of 0, so there's nothing to show for them.
+fn format_inode_usage(disk: &environment::DiskUsage, theme: &Theme) -> Option<String> {
+    if disk.inodes_total == 0 {
+        return None;
+    }
+    let sep = ext::number_separator();
+    let counts = format!(
+        "{} / {}",
+        ext::format_number(disk.inodes_used, sep),
+        ext::format_number(disk.inodes_total, sep),
+    );
+    let fraction = disk.inodes_used as f64 / disk.inodes_total as f64;
+    Some(render::color_usage(&counts, fraction, theme).to_string())
+}
+
+/// The datetime module's value for `now`. RWELCOME_DATE_RELATIVE=1 shows it
+/// relative to today, i.e. "Today at 14:32"; otherwise RWELCOME_DATETIME_FORMAT
+/// takes chrono's strftime-style format.
+fn format_datetime(now: chrono::DateTime<chrono::Local>) -> String {
+    if ext::env_flag("RWELCOME_DATE_RELATIVE") {
+        return ext::format_relative_date(now, now);
+    }
+    let format = ext::env_non_empty("RWELCOME_DATETIME_FORMAT")
+        .unwrap_or_else(|| "%A, %d %B %Y %H:%M".to_string());
+    ext::format_datetime_locale(now, &format)
+}
+
+/// Describes a Git working tree, e.g. "main ✓" in green when it's clean,
+/// or "main ✗ (3 modified)" in the theme's warning color when it isn't.
+fn format_git_status(git: &environment::GitStatus, theme: &Theme) -> String {
+    if git.modified == 0 {
+        format!("{} ✓", git.branch).green().to_string()
+    } else {
+        theme::paint(&format!("{} ✗ ({} modified)", git.branch, git.modified), theme.warning).to_string()
+    }
+}
+
+/// Formats what went wrong with the section labelled `label`, the label in the
+/// theme's error color. With RWELCOME_HIDE_VALUES, `redacted` replaces the error,
+/// since errors can give values away, e.g. a request URL with the API key in it.
+fn error_line(label: &str, err: &dyn std::fmt::Display, redacted: Option<&str>, theme: &Theme) -> String {
+    match redacted {
+        Some(redacted) => format!("{}: {}", theme::paint(label, theme.error), redacted),
+        None => format!("{}: {}", theme::paint(label, theme.error), err),
+    }
+}
+
+/// Puts each module's error among the output `rows`, each of which holds
+/// `lines_per_row` module lines, as Err. An error goes before the first row
+/// holding a line from after its position, the number of lines before it.
+fn interleave_errors<E>(rows: Vec<String>, lines_per_row: usize, errors: Vec<(usize, E)>) -> Vec<Result<String, E>> {
+    let mut errors = errors.into_iter().peekable();
+    let mut output = Vec::new();
+    for (index, row) in rows.into_iter().enumerate() {
+        while let Some((_, err)) = errors.next_if(|(position, _)| *position <= index * lines_per_row) {
+            output.push(Err(err));
+        }
+        output.push(Ok(row));
+    }
+    output.extend(errors.map(|(_, err)| Err(err)));
+    output
+}
+
+/// The hostname to show: RWELCOME_HOSTNAME_ALIAS if set, for machines with
+/// names like `ip-172-31-14-23`. `{host}` in templates is always the real one.
+/// Like `Config::hostname_alias`, for the greeting, which skips loading the config.
+fn display_hostname(hostname: &str) -> String {
+    ext::env_non_empty("RWELCOME_HOSTNAME_ALIAS").unwrap_or_else(|| hostname.to_string())
+}
+
+/// The `user@host` greeting, each part in its own color from `theme`.
+fn format_greeting(username: &str, hostname: &str, theme: &Theme) -> String {
+    // RWELCOME_GREETING_PREFIX goes before the username, e.g. "👋".
+    let prefix = match env::var("RWELCOME_GREETING_PREFIX") {
+        Ok(prefix) if !prefix.is_empty() => format!("{prefix} "),
+        _ => String::new(),
+    };
+    // NO_COLOR wins over RWELCOME_COLOR_USER and friends.
+    let color = |color: Option<colored::Color>| color.filter(|_| theme::colors_enabled());
+    format!(
+        "{}{}{}{}",
+        prefix,
+        theme::paint(username, color(theme.user)),
+        theme::paint("@", color(theme.at)),
+        theme::paint(hostname, color(theme.host)),
+    )
+}
+
+/// Print the `user@host` greeting and the separator under it,
+/// returning the separator's length.
+fn render_greeting(username: &str, hostname: &str, theme: &Theme) -> usize {
+    let greeting = format_greeting(username, hostname, theme);
+    println!("{}", greeting);
+    // Measure what's displayed: no color codes, and emoji take up two columns.
+    let line_length = render::visible_len(&greeting);
+    draw_separator(line_length, '-');
+    line_length
 }
 
 fn render(ctx: Rwelcome) {
+    let proc_fs = &ctx.proc_fs;
+    let hostname = environment::acquire_hostname(proc_fs).unwrap_or_else(|_| "unknown".to_string());
+    if ext::env_flag("RWELCOME_COMPACT") {
+        println!("{}", render::render_compact(&snapshot(&ctx, &hostname), &ctx.config.theme));
+        return;
+    }
+    /*
+     * RWELCOME_HIDE_VALUES=1 keeps the labels and layout but shows every value,
+     * the hostname and the todos as `***` (or RWELCOME_REDACT_STRING), e.g. for
+     * screen sharing.
+     */
+    let redacted = render::redaction();
     println!();
-    let hostname = environment::acquire_hostname().unwrap_or_else(|_| "unknown".to_string());
-    println!("{}@{}", ctx.username.purple(), hostname);
-    let line_length = ctx.username.len() + hostname.len() + 1;
-    draw_line(line_length);
-    match environment::acquire_uptime() {
-        Ok((hours, minutes)) => println!("{}: {}h {}m", "Uptime".bright_blue(), hours, minutes),
-        Err(err) => eprintln!("{}: {}", "Uptime".red(), err)
-    }
-    match environment::acquire_memory_info() {
-        Ok((used, total)) => println!("{}: {} MiB / {} MiB", "Memory".bright_blue(), used / 1000, total / 1000),
-        Err(err) => eprintln!("{}: {}", "Memory".red(), err),
-    }
-    match environment::acquire_kernel_version() {
-        Ok(version) => println!("{}: Linux {}", "Kernel".bright_blue(), version),
-        Err(err) => eprintln!("{}: {}", "Kernel".red(), err)
-    }
-    match environment::acquire_cpu_temperature() {
-        Ok(temp) => println!("{}: {:.1}°C", "CPU temp".bright_blue(), temp),
-        Err(err) => eprintln!("{}: {}", "CPU temp".red(), err)
+    let shown_hostname = redacted.clone()
+        .or_else(|| ctx.config.hostname_alias.clone())
+        .unwrap_or_else(|| hostname.clone());
+    let line_length = render_greeting(&ctx.username, &shown_hostname, &ctx.config.theme);
+    /*
+     * RWELCOME_MOTD_PATH adds a message of the day under the greeting,
+     * up to RWELCOME_MOTD_MAX_LINES lines. A missing file is skipped.
+     */
+    if let Some(path) = ext::env_non_empty("RWELCOME_MOTD_PATH") {
+        let max_lines = ext::parse_env_number("RWELCOME_MOTD_MAX_LINES", 10);
+        if let Ok(lines) = environment::acquire_motd(&path, max_lines) {
+            for line in lines {
+                match &redacted {
+                    Some(redacted) => println!("{}", redacted),
+                    None => println!("{}", ext::fill_template(&line, &ctx.username, &hostname)),
+                }
+            }
+        }
+    }
+    let mut infos = Vec::<LabeledLine>::new();
+    // Each failed module's error, with how many lines came before it.
+    let mut errors = Vec::<(usize, LabeledLine)>::new();
+    for name in &ctx.modules {
+        match render_module_lines(name, &ctx) {
+            Some(Ok(lines)) => infos.extend(lines),
+            Some(Err(err)) => errors.push((infos.len(), err)),
+            None => {},
+        }
+    }
+    let align = match ext::env_one_of("RWELCOME_ALIGN", &["left", "right"], "left").as_str() {
+        "right" => Align::Right,
+        _ => Align::Left,
+    };
+    // RWELCOME_LABEL_ALIGN=left lines up the colons, and right lines up the ends of the labels.
+    let label_align = match ext::env_one_of("RWELCOME_LABEL_ALIGN", &["none", "left", "right"], "none").as_str() {
+        "left" => LabelAlign::Left,
+        "right" => LabelAlign::Right,
+        _ => LabelAlign::None,
+    };
+    if let Some(redacted) = &redacted {
+        render::redact_values(&mut infos, redacted);
+    }
+    let lines = render::render_aligned(&infos, align, label_align, &ctx.config.theme);
+    let (rows, lines_per_row) = if ext::env_one_of("RWELCOME_LAYOUT", &["single", "two-column"], "single") == "two-column" {
+        let width = render::effective_width();
+        // Pair lines up in order; an odd one out spans both columns.
+        let rows = lines.chunks(2)
+            .map(|pair| match pair {
+                [left, right] => render::render_two_column(left, right, width),
+                [only] => only.clone(),
+                _ => unreachable!(),
+            })
+            .collect();
+        (rows, 2)
+    } else {
+        (lines, 1)
+    };
+    // Errors go to stderr, but in their module's place among the lines.
+    for row in interleave_errors(rows, lines_per_row, errors) {
+        match row {
+            Ok(line) => println!("{}", line),
+            Err(err) => eprintln!("{}", error_line(&err.label, &err.value, redacted.as_deref(), &ctx.config.theme)),
+        }
     }
     println!();
-    println!("{}@real", "life".purple());
-    draw_line(line_length);
+    println!("{}@real", theme::paint("life", ctx.config.theme.user));
+    draw_separator(line_length, '-');
+    #[cfg(feature = "weather")]
     if let Some(weather_response) = ctx.maybe_weather_response {
-        match weather_response {
-            Ok(weather) => {
+        match (weather_response, ctx.maybe_second_weather) {
+            (Ok(_), _) if redacted.is_some() => println!(
+                "{}: {}",
+                theme::paint(&ctx.config.label_weather, ctx.config.theme.label),
+                redacted.as_deref().unwrap_or_default(),
+            ),
+            (Ok(weather), Some(second)) => {
+                println!(
+                    "{}: {}  |  {}",
+                    theme::paint(&ctx.config.label_weather, ctx.config.theme.label),
+                    format_weather_short(&weather, &ctx.config.weather_icons, ctx.config.weather_units),
+                    format_weather_short(&second, &ctx.config.weather_icons, ctx.config.weather_units),
+                );
+                if ext::env_flag("RWELCOME_SHOW_WEATHER_AQI") {
+                    if let (Some(first), Some(second)) = (format_aqi(&weather), format_aqi(&second)) {
+                        println!("{}: {}  |  {}", theme::paint("AQI", ctx.config.theme.label), first, second);
+                    }
+                }
+            },
+            (Ok(weather), None) => {
                 let the_condition = weather.current.condition.text.to_lowercase();
-                let emoji = if the_condition == "cloudy" { "☁️" }
-                                else if the_condition.contains("fog") { "☁️" }
-                                else if the_condition.contains("mist") { "☁️" }
-                                else if the_condition.contains("sunny") { "🌤️" }
-                                else if the_condition.contains("rain") { "🌧️" }
-                                else { "🌥️" };
+                let emoji = weather::condition_to_icon(&the_condition, &ctx.config.weather_icons);
+                let formatted = weather::format_weather(&weather, ctx.config.weather_units);
                 println!(
-                    "{}: {}°C and {} in {} {}",
-                    "Weather".bright_blue(),
-                    weather.current.temp_c,
+                    "{}: {} and {} in {} {}",
+                    theme::paint(&ctx.config.label_weather, ctx.config.theme.label),
+                    formatted.temperature,
                     the_condition,
-                    weather.location.name,
+                    weather::display_location(&weather.location),
                     emoji,
                 );
+                // RWELCOME_WEATHER_DETAILS=1 adds the wind, pressure, precipitation and visibility.
+                if ext::env_flag("RWELCOME_WEATHER_DETAILS") {
+                    println!(
+                        "  wind {}, pressure {}, precipitation {}, visibility {}",
+                        formatted.wind, formatted.pressure, formatted.precipitation, formatted.visibility,
+                    );
+                }
+                // RWELCOME_SHOW_WEATHER_AQI=1 adds the air quality, which only weatherapi.com gives.
+                if let (true, Some(reading)) = (ext::env_flag("RWELCOME_SHOW_WEATHER_AQI"), format_aqi(&weather)) {
+                    println!("{}: {}", theme::paint("AQI", ctx.config.theme.label), reading);
+                }
             },
-            Err(err) => eprintln!("{}: {}", "Weather".red(), err),
+            (Err(err), _) => eprintln!("{}", error_line(&ctx.config.label_weather, &err, redacted.as_deref(), &ctx.config.theme)),
         }
     }
     match ctx.todos {
-        Ok(todos) => show_todos(todos),
-        Err(err)  => eprintln!("{}: {}", "Todos".red(), err),
+        Ok(todos) if redacted.is_some() => println!(
+            "{} ({}): {}",
+            theme::paint(&ctx.config.label_todos, ctx.config.theme.label),
+            todos.len(),
+            redacted.as_deref().unwrap_or_default(),
+        ),
+        Ok(todos) => {
+            // Todos keep their numbers from the file, whatever order they're shown in.
+            let mut numbered: Vec<(usize, &Todo)> = todos.iter().enumerate()
+                .map(|(index, todo)| (index + 1, todo))
+                .collect();
+            let sort = ext::env_one_of("RWELCOME_TODOS_SORT", &["file", "alpha", "priority", "due", "added"], "file");
+            let key = match sort.as_str() {
+                "alpha" => Some(SortKey::Alpha),
+                "priority" => Some(SortKey::Priority),
+                "due" => Some(SortKey::Due),
+                "added" => Some(SortKey::Added),
+                _ => None,
+            };
+            if let Some(key) = key {
+                environment::sort_todos(&mut numbered, key);
+            }
+            // RWELCOME_TODOS_MAX caps how many todos are displayed; 0 means no cap.
+            let max = ext::parse_env_number("RWELCOME_TODOS_MAX", 0);
+            let shown = if max == 0 { todos.len() } else { max.min(todos.len()) };
+            show_todos(&ctx.config.label_todos, &numbered[..shown], todos.len(), &ctx.config.theme);
+        },
+        // A timeout is already mentioned at the end.
+        Err(err) if err.kind() == io::ErrorKind::TimedOut => {},
+        Err(err)  => eprintln!("{}", error_line(&ctx.config.label_todos, &err, redacted.as_deref(), &ctx.config.theme)),
+    }
+    /*
+     * RWELCOME_FORTUNE=1 ends with a fortune cookie, a short one with
+     * RWELCOME_FORTUNE_SHORT=1, from the RWELCOME_FORTUNE_PATH fortunes file
+     * if set. Lines too wide for the screen are wrapped; the rest are kept
+     * as they are, since fortunes are often laid out by hand.
+     */
+    if let Some(fortune) = &ctx.maybe_fortune {
+        let width = render::effective_width();
+        println!();
+        for line in fortune.lines() {
+            if render::visible_len(line) > width {
+                println!("{}", ext::wrap_text(line, width, 0).join("\n"));
+            } else {
+                println!("{}", line);
+            }
+        }
+    }
+    if ctx.timed_out {
+        println!("{}", "(some modules timed out)".dimmed());
     }
     println!();
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(target_os = "linux")]
+    use std::fs::{File, FileTimes};
+    #[cfg(target_os = "linux")]
+    use std::path::Path;
+    #[cfg(target_os = "linux")]
+    use std::time::SystemTime;
+
+    #[cfg(target_os = "linux")]
+    fn age(path: &Path) {
+        let old = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000);
+        let times = FileTimes::new().set_accessed(old).set_modified(old);
+        File::options().write(true).open(path).unwrap().set_times(times).unwrap();
+    }
+
+    #[cfg(target_os = "linux")]
+    fn accessed(path: &Path) -> SystemTime {
+        fs::metadata(path).unwrap().accessed().unwrap()
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn greeting_only_reads_nothing_but_the_hostname() {
+        let _lock = ENV_LOCK.lock().unwrap_or_else(|err| err.into_inner());
+        let root = tempfile::tempdir().unwrap();
+        let hostname = root.path().join("proc/sys/kernel/hostname");
+        fs::create_dir_all(hostname.parent().unwrap()).unwrap();
+        fs::write(&hostname, "fixture\n").unwrap();
+        let config = root.path().join("config.toml");
+        fs::write(&config, "[env]\nRWELCOME_SHOW_UPTIME = \"1\"\n").unwrap();
+        let todos = root.path().join("todos");
+        fs::write(&todos, "buy milk\n").unwrap();
+        let uptime = root.path().join("proc/uptime");
+        fs::write(&uptime, "100.0 200.0\n").unwrap();
+        for path in [&hostname, &config, &todos, &uptime] {
+            age(path);
+        }
+
+        env::set_var("RWELCOME_GREETING_ONLY", "1");
+        env::set_var("RWELCOME_CONFIG_PATH", &config);
+        env::set_var("RWELCOME_TODOS_PATH", &todos);
+        let cli = Cli::try_parse_from(["rwelcome"]).unwrap();
+        let shown = greeting_only(&cli, &ProcFs::new(root.path()));
+        for name in ["RWELCOME_GREETING_ONLY", "RWELCOME_CONFIG_PATH", "RWELCOME_TODOS_PATH"] {
+            env::remove_var(name);
+        }
+
+        assert!(shown);
+        if accessed(&hostname) == SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000) {
+            // This filesystem doesn't record reads, so there's nothing to check.
+            return;
+        }
+        for path in [&config, &todos, &uptime] {
+            assert_eq!(accessed(path), SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000), "{} was read", path.display());
+        }
+    }
+
+    #[test]
+    fn greeting_colors_come_from_the_environment() {
+        let _lock = ENV_LOCK.lock().unwrap_or_else(|err| err.into_inner());
+        for var in ["NO_COLOR", "RWELCOME_COLOR_SCHEME", "RWELCOME_GREETING_PREFIX"] {
+            env::remove_var(var);
+        }
+        env::set_var("RWELCOME_COLOR_USER", "green");
+        env::set_var("RWELCOME_COLOR_AT", "bright black");
+        env::set_var("RWELCOME_COLOR_HOST", "cyan");
+        colored::control::set_override(true);
+        let colored = format_greeting("anthony", "desktop", &theme::load_theme());
+        env::set_var("NO_COLOR", "1");
+        let plain = format_greeting("anthony", "desktop", &theme::load_theme());
+        colored::control::unset_override();
+        for var in ["NO_COLOR", "RWELCOME_COLOR_USER", "RWELCOME_COLOR_AT", "RWELCOME_COLOR_HOST"] {
+            env::remove_var(var);
+        }
+        assert_eq!(colored, "\x1b[32manthony\x1b[0m\x1b[90m@\x1b[0m\x1b[36mdesktop\x1b[0m");
+        assert_eq!(plain, "anthony@desktop");
+    }
+
+    #[test]
+    fn separator_is_as_wide_as_the_greeting_looks() {
+        let _lock = ENV_LOCK.lock().unwrap_or_else(|err| err.into_inner());
+        colored::control::set_override(true);
+        let mut widths = Vec::new();
+        for prefix in ["", "hi", "👋", "👋🎉"] {
+            env::set_var("RWELCOME_GREETING_PREFIX", prefix);
+            widths.push(render_greeting("anthony", "desktop", &Theme::default()));
+        }
+        env::remove_var("RWELCOME_GREETING_PREFIX");
+        colored::control::unset_override();
+        // "anthony@desktop" is 15 columns; emoji are two columns each.
+        assert_eq!(widths, [15, 18, 18, 20]);
+    }
+
+    #[test]
+    fn errors_use_the_themes_error_color() {
+        let _lock = ENV_LOCK.lock().unwrap_or_else(|err| err.into_inner());
+        colored::control::set_override(true);
+        let theme = Theme { error: Some(colored::Color::Magenta), ..Theme::default() };
+        let line = error_line("Uptime", &"no such file", None, &theme);
+        let (magenta, red) = ("Uptime".magenta().to_string(), "Uptime".red().to_string());
+        colored::control::unset_override();
+        assert_eq!(line, format!("{magenta}: no such file"));
+        assert!(!line.contains(&red));
+    }
+
+    #[cfg(feature = "weather")]
+    #[test]
+    fn aqi_category_matches_the_number_shown() {
+        let mut weather = WeatherResponse { location: Default::default(), current: Default::default() };
+        assert!(format_aqi(&weather).is_none());
+        // weatherapi.com's own index can disagree with the AQI worked out from PM2.5.
+        let air = weather::AirQualityInfo { pm2_5: 40.0, us_epa_index: 1, ..Default::default() };
+        weather.current.air_quality = Some(air);
+        let reading = format_aqi(&weather).unwrap();
+        assert_eq!(&*reading, "112 (Unhealthy for sensitive groups)");
+        assert_eq!(reading.fgcolor(), Some(colored::Color::TrueColor { r: 255, g: 165, b: 0 }));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn memory_is_shown_with_or_without_the_breakdown() {
+        let _lock = ENV_LOCK.lock().unwrap_or_else(|err| err.into_inner());
+        let root = tempfile::tempdir().unwrap();
+        let proc_fs = ProcFs::new(root.path());
+        fs::create_dir_all(proc_fs.path("/proc")).unwrap();
+        fs::write(proc_fs.path("/proc/meminfo"), concat!(
+            "MemTotal:        8388608 kB\n",
+            "MemFree:         1048576 kB\n",
+            "MemAvailable:    3984588 kB\n",
+            "Buffers:          209715 kB\n",
+            "Cached:          1677722 kB\n",
+        )).unwrap();
+        let mem = environment::acquire_memory_info(&proc_fs).unwrap();
+        assert_eq!((mem.cached_kb, mem.buffers_kb), (1677722, 209715));
+        for var in ["RWELCOME_MEMORY_MODE", "RWELCOME_BARS", "RWELCOME_MEMORY_BREAKDOWN"] {
+            env::remove_var(var);
+        }
+        env::set_var("RWELCOME_NUMBER_SEP", ",");
+        let text = |lines: Vec<LabeledLine>| lines.into_iter()
+            .map(|line| format!("{}: {}", line.label, line.value))
+            .collect::<Vec<_>>();
+
+        let plain = text(memory_lines(&mem, None, "Memory", &Theme::default()));
+        env::set_var("RWELCOME_MEMORY_BREAKDOWN", "1");
+        let breakdown = text(memory_lines(&mem, None, "Memory", &Theme::default()));
+        let in_cgroup = text(memory_lines(&mem, Some((Some(524288), 1048576)), "Memory", &Theme::default()));
+        env::remove_var("RWELCOME_MEMORY_BREAKDOWN");
+        env::remove_var("RWELCOME_NUMBER_SEP");
+
+        assert_eq!(plain, ["Memory: 4,300 MiB / 8,192 MiB"]);
+        assert_eq!(breakdown, [
+            "Memory: 4.2 GiB used  |  1.8 GiB cache  |  8.0 GiB total",
+            "Cache: 1.6 GiB page cache  |  0.2 GiB buffers",
+        ]);
+        // The caches are the host's, so a container only gets its own figures.
+        assert_eq!(in_cgroup, ["Memory: 512 MiB / 1,024 MiB (cgroup)"]);
+    }
+
+    #[test]
+    fn uptime_shows_its_two_largest_units() {
+        let cases = [(42, "42s"), (848, "14m 08s"), (11640, "3h 14m"), (183612, "2d 3h")];
+        for (seconds, shown) in cases {
+            assert_eq!(format_uptime(&environment::UptimeInfo::from_seconds(seconds)), shown);
+        }
+    }
+
+    #[test]
+    fn datetime_can_be_relative() {
+        use chrono::TimeZone;
+        let _lock = ENV_LOCK.lock().unwrap_or_else(|err| err.into_inner());
+        let now = chrono::Local.with_ymd_and_hms(2026, 10, 15, 14, 32, 0).unwrap();
+        env::set_var("RWELCOME_DATETIME_FORMAT", "%Y-%m-%d %H:%M");
+        let absolute = format_datetime(now);
+        env::set_var("RWELCOME_DATE_RELATIVE", "1");
+        let relative = format_datetime(now);
+        env::remove_var("RWELCOME_DATE_RELATIVE");
+        env::remove_var("RWELCOME_DATETIME_FORMAT");
+        assert_eq!(absolute, "2026-10-15 14:32");
+        assert_eq!(relative, "Today at 14:32");
+    }
+
+    #[test]
+    fn git_status_is_green_when_clean_and_yellow_when_dirty() {
+        let _lock = ENV_LOCK.lock().unwrap_or_else(|err| err.into_inner());
+        colored::control::set_override(true);
+        let clean = format_git_status(&environment::GitStatus { branch: "main".to_string(), modified: 0 }, &Theme::default());
+        let dirty = format_git_status(&environment::GitStatus { branch: "main".to_string(), modified: 3 }, &Theme::default());
+        colored::control::unset_override();
+        assert_eq!(clean, "\x1b[32mmain ✓\x1b[0m");
+        assert_eq!(dirty, "\x1b[33mmain ✗ (3 modified)\x1b[0m");
+    }
+
+    #[test]
+    fn inode_counts_are_grouped_in_thousands() {
+        let _lock = ENV_LOCK.lock().unwrap_or_else(|err| err.into_inner());
+        env::set_var("RWELCOME_NUMBER_SEP", ",");
+        colored::control::set_override(false);
+        let disk = |inodes_used, inodes_total| environment::DiskUsage { used: 0, total: 0, inodes_used, inodes_total };
+        let ext4 = format_inode_usage(&disk(250112, 1245184), &Theme::default());
+        let btrfs = format_inode_usage(&disk(0, 0), &Theme::default());
+        colored::control::set_override(true);
+        let nearly_full = format_inode_usage(&disk(9000, 10000), &Theme::default());
+        colored::control::unset_override();
+        env::remove_var("RWELCOME_NUMBER_SEP");
+        assert_eq!(ext4.as_deref(), Some("250,112 / 1,245,184"));
+        assert_eq!(btrfs, None);
+        assert_eq!(nearly_full.as_deref(), Some("\x1b[31m9,000 / 10,000\x1b[0m"));
+    }
+
+    #[test]
+    fn uptime_turns_yellow_then_red() {
+        let _lock = ENV_LOCK.lock().unwrap_or_else(|err| err.into_inner());
+        env::remove_var("RWELCOME_UPTIME_CRIT_DAYS");
+        colored::control::set_override(true);
+        let theme = Theme::default();
+        let color = |days: u64| color_uptime(&environment::UptimeInfo::from_seconds(days * 86400), &theme);
+        let off = color(400);
+        env::set_var("RWELCOME_UPTIME_WARN_DAYS", "90");
+        let colored: Vec<String> = [89, 90, 91, 179, 180].into_iter().map(color).collect();
+        env::remove_var("RWELCOME_UPTIME_WARN_DAYS");
+        colored::control::unset_override();
+        assert_eq!(off, "400d 0h");
+        assert_eq!(colored, [
+            "89d 0h",
+            "\x1b[33m90d 0h\x1b[0m",
+            "\x1b[33m91d 0h\x1b[0m",
+            "\x1b[33m179d 0h\x1b[0m",
+            "\x1b[31m180d 0h\x1b[0m",
+        ]);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn hugepages_are_shown_only_when_reserved() {
+        let root = tempfile::tempdir().unwrap();
+        let proc_fs = ProcFs::new(root.path());
+        fs::create_dir_all(proc_fs.path("/proc")).unwrap();
+        let meminfo = concat!(
+            "MemTotal:       32768000 kB\n",
+            "MemFree:         8192000 kB\n",
+            "MemAvailable:   16384000 kB\n",
+            "Buffers:          409600 kB\n",
+            "Cached:          6144000 kB\n",
+            "HugePages_Total:    1024\n",
+            "HugePages_Free:      512\n",
+            "HugePages_Rsvd:        0\n",
+            "Hugepagesize:       2048 kB\n",
+        );
+        fs::write(proc_fs.path("/proc/meminfo"), meminfo).unwrap();
+        let mem = environment::acquire_memory_info(&proc_fs).unwrap();
+        assert_eq!((mem.hugepages_total, mem.hugepages_free, mem.hugepage_size_kb), (1024, 512, 2048));
+        assert_eq!(format_hugepages(&mem).as_deref(), Some("512 / 1024 (2 MiB pages)"));
+
+        let none_reserved = meminfo.replace("HugePages_Total:    1024", "HugePages_Total:       0");
+        fs::write(proc_fs.path("/proc/meminfo"), none_reserved.replace("HugePages_Free:      512", "HugePages_Free:        0")).unwrap();
+        assert_eq!(format_hugepages(&environment::acquire_memory_info(&proc_fs).unwrap()), None);
+    }
+
+    #[test]
+    fn containers_are_counted_per_engine() {
+        let both = environment::ContainerInfo { docker: Some(2), podman: Some(1) };
+        assert_eq!(format_container_counts(&both), "Docker: 2  Podman: 1");
+        let podman = environment::ContainerInfo { docker: None, podman: Some(0) };
+        assert_eq!(format_container_counts(&podman), "Podman: 0");
+    }
+
+    #[test]
+    fn hidden_values_hide_errors_too() {
+        let err = "error sending request for url (https://api.weatherapi.com/v1/current.json?key=s3cret&q=Brighton)";
+        let line = error_line("Weather", &err, Some("***"), &Theme { error: None, ..Theme::default() });
+        assert_eq!(line, "Weather: ***");
+        assert!(!line.contains("s3cret"));
+    }
+
+    #[test]
+    fn module_errors_keep_their_place() {
+        let rows = vec!["Uptime: 1h".to_string(), "Memory: 1 GiB".to_string()];
+        let errors = vec![(0, "OS: broken".to_string()), (1, "CPU: broken".to_string()), (2, "Disk: broken".to_string())];
+        assert_eq!(interleave_errors(rows, 1, errors), [
+            Err("OS: broken".to_string()),
+            Ok("Uptime: 1h".to_string()),
+            Err("CPU: broken".to_string()),
+            Ok("Memory: 1 GiB".to_string()),
+            Err("Disk: broken".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn module_errors_dont_split_a_row() {
+        let rows = vec!["Uptime: 1h  Memory: 1 GiB".to_string(), "OS: Arch".to_string()];
+        let errors = vec![(1, "CPU: broken".to_string())];
+        assert_eq!(interleave_errors(rows, 2, errors), [
+            Ok("Uptime: 1h  Memory: 1 GiB".to_string()),
+            Err("CPU: broken".to_string()),
+            Ok("OS: Arch".to_string()),
+        ]);
+    }
+
+    #[tokio::test]
+    async fn blocking_modules_are_cut_off_at_the_budget() {
+        let start = std::time::Instant::now();
+        let slow = acquire_blocking(true, || std::thread::sleep(Duration::from_millis(500)));
+        assert!(within_budget(Duration::from_millis(20), slow).await.is_none());
+        assert!(start.elapsed() < Duration::from_millis(400));
+
+        let quick = acquire_blocking(true, || 42);
+        assert_eq!(within_budget(Duration::from_millis(500), quick).await, Some(Some(42)));
+    }
+
+    #[tokio::test]
+    async fn turned_off_modules_are_not_run() {
+        let skipped = acquire_blocking(false, || -> u32 { panic!("shouldn't run") });
+        assert_eq!(within_budget(Duration::ZERO, skipped).await, Some(None));
+    }
+
+    #[test]
+    fn dry_run_is_found_after_the_edit() {
+        let cli = Cli::try_parse_from(["rwelcome", "edit", "done", "3", "--dry-run"]).unwrap();
+        assert!(cli.dry_run);
+        let cli = Cli::try_parse_from(["rwelcome", "--dry-run", "edit", "clear"]).unwrap();
+        assert!(cli.dry_run);
+        assert!(!Cli::try_parse_from(["rwelcome", "done", "3"]).unwrap().dry_run);
+    }
+
+    #[test]
+    fn greeting_only_is_ignored_with_a_subcommand() {
+        let _lock = ENV_LOCK.lock().unwrap_or_else(|err| err.into_inner());
+        env::set_var("RWELCOME_GREETING_ONLY", "1");
+        let cli = Cli::try_parse_from(["rwelcome", "lists"]).unwrap();
+        let shown = greeting_only(&cli, &ProcFs::default());
+        env::remove_var("RWELCOME_GREETING_ONLY");
+        assert!(!shown);
+    }
+
+    /*
+     * reqwest reads the proxy variables once per process, so the slow
+     * network is set up in a child running just this test.
+     */
+    #[cfg(all(feature = "weather", unix))]
+    #[test]
+    fn slow_sources_all_wait_together_within_the_budget() {
+        use std::os::unix::fs::PermissionsExt;
+        use std::process::Command;
+
+        if env::var_os("RWELCOME_SLOW_LOAD_CHILD").is_none() {
+            let _lock = ENV_LOCK.lock().unwrap_or_else(|err| err.into_inner());
+            // A proxy that accepts connections and never answers them.
+            let proxy = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+            let root = tempfile::tempdir().unwrap();
+            let git = root.path().join("git");
+            fs::write(&git, "#!/bin/sh\nsleep 5\n").unwrap();
+            fs::set_permissions(&git, fs::Permissions::from_mode(0o755)).unwrap();
+            let status = Command::new(env::current_exe().unwrap())
+                .args(["--exact", "tests::slow_sources_all_wait_together_within_the_budget"])
+                .env("RWELCOME_SLOW_LOAD_CHILD", "1")
+                .env("XDG_CACHE_HOME", root.path())
+                .env("HTTPS_PROXY", format!("http://{}", proxy.local_addr().unwrap()))
+                .env("PATH", format!("{}:{}", root.path().display(), env::var("PATH").unwrap_or_default()))
+                .env("RWELCOME_CONFIG_PATH", root.path().join("config.toml"))
+                .env("RWELCOME_TODOS_PATH", root.path().join("todos"))
+                .env("RWELCOME_WEATHER_API_KEY", "key")
+                .env("RWELCOME_SHOW_GIT", "1")
+                .env("RWELCOME_MAX_RENDER_TIME_MS", "1000")
+                .status()
+                .unwrap();
+            assert!(status.success());
+            return;
+        }
+
+        let todos_path = env::var("RWELCOME_TODOS_PATH").unwrap();
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let started = std::time::Instant::now();
+        let ctx = runtime.block_on(load(None, todos_path, false, ProcFs::default())).unwrap();
+        let elapsed = started.elapsed();
+        assert!(ctx.timed_out);
+        // The weather gets the whole second; git's half runs alongside it.
+        assert!(elapsed < Duration::from_millis(1300), "took {elapsed:?}");
+        runtime.shutdown_background();
+    }
+}
@@ -0,0 +1,381 @@
+use colored::{ColoredString, Colorize};
+use serde::Serialize;
+use std::env;
+use unicode_width::UnicodeWidthChar;
+use crate::ext;
+use crate::theme::{self, Theme};
+
+/// Everything rwelcome knows about the system at one point in time,
+/// shared by output modes that don't go through the full render.
+#[derive(Serialize, Default)]
+pub struct SystemSnapshot {
+    pub username: String,
+    pub hostname: String,
+    /// Shown instead of `hostname` when set, from RWELCOME_HOSTNAME_ALIAS.
+    pub hostname_alias: Option<String>,
+    pub uptime_seconds: Option<u64>,
+    pub memory_used_kb: Option<u64>,
+    pub memory_total_kb: Option<u64>,
+    pub cpu_temp_c: Option<f64>,
+    /// How full the RWELCOME_DISK_PATH filesystem is, when RWELCOME_SHOW_DISK is set.
+    pub disk_used_pct: Option<f64>,
+    pub weather: Option<WeatherSnapshot>,
+    pub todo_count: Option<usize>,
+}
+
+#[derive(Serialize)]
+pub struct WeatherSnapshot {
+    pub icon: String,
+    /// The temperature with its unit, e.g. "18°C".
+    pub temperature: String,
+    pub location: String,
+}
+
+/// Renders the snapshot on a single line, e.g.
+/// `user@host | up 2h14m | mem 5.3/8G | cpu 44°C | ☁️  18°C Brighton | 3 todos`.
+/// Missing fields are left out. The separator can be changed with RWELCOME_COMPACT_SEP.
+pub fn render_compact(snapshot: &SystemSnapshot, theme: &Theme) -> String {
+    let sep = env::var("RWELCOME_COMPACT_SEP").unwrap_or_else(|_| "|".to_string());
+    let redacted = redaction();
+    let hide = |value: String| redacted.clone().unwrap_or(value);
+    let hostname = snapshot.hostname_alias.as_ref().unwrap_or(&snapshot.hostname);
+    let label = |text: &str| theme::paint(text, theme.label);
+    let mut fields = vec![format!("{}@{}", theme::paint(&snapshot.username, theme.user), hide(hostname.clone()))];
+    if let Some(seconds) = snapshot.uptime_seconds {
+        let (days, hours, minutes) = (seconds / 86400, (seconds % 86400) / 3600, (seconds % 3600) / 60);
+        let uptime = if days > 0 {
+            format!("{days}d{hours}h")
+        } else {
+            format!("{hours}h{minutes}m")
+        };
+        fields.push(format!("{} {}", label("up"), hide(uptime)));
+    }
+    if let (Some(used), Some(total)) = (snapshot.memory_used_kb, snapshot.memory_total_kb) {
+        let to_gib = |kb: u64| kb as f64 / 1024.0 / 1024.0;
+        fields.push(format!("{} {}", label("mem"), hide(format!("{:.1}/{:.0}G", to_gib(used), to_gib(total)))));
+    }
+    if let Some(temp) = snapshot.cpu_temp_c {
+        fields.push(format!("{} {}", label("cpu"), hide(format!("{:.0}°C", temp))));
+    }
+    if let Some(pct) = snapshot.disk_used_pct {
+        let usage = disk_usage_color(&format!("{pct:.0}%"), pct, theme);
+        fields.push(format!("{} {}", label("disk"), hide(usage)));
+    }
+    if let Some(weather) = &snapshot.weather {
+        fields.push(hide(format!("{}  {} {}", weather.icon, weather.temperature, weather.location)));
+    }
+    if let Some(count) = snapshot.todo_count {
+        fields.push(format!("{} {}", count, label("todos")));
+    }
+    fields.join(&format!(" {sep} "))
+}
+
+/// What to show instead of every value when RWELCOME_HIDE_VALUES=1, e.g. while
+/// screen sharing: RWELCOME_REDACT_STRING, or `***`. None when values are shown.
+pub fn redaction() -> Option<String> {
+    if !ext::env_flag("RWELCOME_HIDE_VALUES") {
+        return None;
+    }
+    Some(ext::env_non_empty("RWELCOME_REDACT_STRING").unwrap_or_else(|| "***".to_string()))
+}
+
+/// Replaces the value of each of `lines` with `redacted`, keeping the labels.
+pub fn redact_values(lines: &mut [LabeledLine], redacted: &str) {
+    for line in lines {
+        line.value = redacted.to_string();
+    }
+}
+
+/// A single line of system info, e.g. `Uptime: 2h 14m`.
+pub struct LabeledLine {
+    pub label: String,
+    pub value: String,
+}
+
+impl LabeledLine {
+    pub fn new(label: &str, value: String) -> LabeledLine {
+        LabeledLine { label: label.to_string(), value }
+    }
+}
+
+/// How `render_aligned()` lays out labels and values.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Align {
+    /// `Label: value`, with no padding.
+    Left,
+    /// Labels padded to the longest one, and values right-aligned.
+    Right,
+}
+
+/// How `render_aligned()` pads the labels themselves.
+#[derive(Clone, Copy, PartialEq)]
+pub enum LabelAlign {
+    /// `Label: value`, with no padding.
+    None,
+    /// Labels padded on the right to the longest one, so the colons line up.
+    Left,
+    /// Labels padded on the left to the longest one, so they end in a column.
+    Right,
+}
+
+/// Puts the colon after `label`, padded with `padding` spaces as `align` says.
+fn align_label(label: &str, padding: usize, align: LabelAlign) -> String {
+    let padding = " ".repeat(padding);
+    match align {
+        LabelAlign::None => format!("{}:", label),
+        LabelAlign::Left => format!("{}{}:", label, padding),
+        LabelAlign::Right => format!("{}{}:", padding, label),
+    }
+}
+
+/// Formats `(label, value)` pairs as `label: value` lines, padding the labels
+/// to the longest one as `align` says, e.g. for Left:
+///
+/// ```text
+/// Uptime  : 2h 14m
+/// CPU temp: 44°C
+/// ```
+pub fn render_label_value_pairs(pairs: &[(String, String)], align: LabelAlign) -> Vec<String> {
+    let label_width = pairs.iter().map(|(label, _)| visible_len(label)).max().unwrap_or(0);
+    pairs.iter()
+        .map(|(label, value)| format!("{} {}", align_label(label, label_width - visible_len(label), align), value))
+        .collect()
+}
+
+/// Formats labeled lines, coloring the labels and values with `theme`. Widths are
+/// measured with `visible_len()`, so color codes don't throw off the alignment.
+pub fn render_aligned(lines: &[LabeledLine], align: Align, label_align: LabelAlign, theme: &Theme) -> Vec<String> {
+    let label_width = lines.iter().map(|line| visible_len(&line.label)).max().unwrap_or(0);
+    let value_width = lines.iter().map(|line| visible_len(&line.value)).max().unwrap_or(0);
+    let pairs: Vec<(String, String)> = lines.iter()
+        .map(|line| {
+            let mut padding = 0;
+            if align == Align::Right {
+                padding += value_width - visible_len(&line.value);
+                // Values line up anyway once the labels are padded.
+                if label_align == LabelAlign::None {
+                    padding += label_width - visible_len(&line.label);
+                }
+            }
+            let value = theme::paint(&line.value, theme.value);
+            (theme::paint(&line.label, theme.label).to_string(), format!("{}{}", " ".repeat(padding), value))
+        })
+        .collect();
+    render_label_value_pairs(&pairs, label_align)
+}
+
+/// Measures how many columns `s` takes up on screen, ignoring ANSI
+/// escape sequences such as color codes. Wide characters like emoji and
+/// CJK count as two columns.
+pub fn visible_len(s: &str) -> usize {
+    let mut len = 0;
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            // Skip a CSI sequence like "\x1b[1;34m" up to its final letter.
+            for c in chars.by_ref() {
+                if c.is_ascii_alphabetic() {
+                    break;
+                }
+            }
+            continue;
+        }
+        len += c.width().unwrap_or(0);
+    }
+    len
+}
+
+/// The number of columns rwelcome's output may use: the terminal width,
+/// capped by RWELCOME_MAX_WIDTH (which can't go below 40).
+pub fn effective_width() -> usize {
+    let terminal_width = ext::terminal_width();
+    match env::var("RWELCOME_MAX_WIDTH") {
+        Ok(_) => ext::parse_env_number("RWELCOME_MAX_WIDTH", terminal_width).max(40).min(terminal_width),
+        Err(_) => terminal_width,
+    }
+}
+
+/// Lays out two lines side by side, padding `left` out to half of `width`.
+/// If `left` is too wide for that, the columns are separated by two spaces instead.
+pub fn render_two_column(left: &str, right: &str, width: usize) -> String {
+    let column_width = width / 2;
+    let padding = column_width.saturating_sub(visible_len(left)).max(2);
+    format!("{}{}{}", left, " ".repeat(padding), right)
+}
+
+/// Renders how much of `total` is `used` as a bar `width` cells wide, followed by
+/// the percentage, e.g. `████████░░░░░░░ 53%`. The bar turns the theme's warning
+/// color from 60% and its error color from 85%. Anything over `total` is shown as a full bar.
+pub fn render_bar(used: f64, total: f64, width: usize, theme: &Theme) -> String {
+    let fraction = if total > 0.0 { (used / total).clamp(0.0, 1.0) } else { 0.0 };
+    let filled = (fraction * width as f64).round() as usize;
+    let bar = format!("{}{}", "█".repeat(filled), "░".repeat(width - filled));
+    format!("{} {:.0}%", color_usage(&bar, fraction, theme), fraction * 100.0)
+}
+
+/// Colors `text` with the theme's warning color once `value` reaches `warn`, and its
+/// error color once it reaches `crit`. Either threshold can be left out, and below
+/// both the text is left alone.
+pub fn threshold_color(text: &str, value: f64, warn: Option<f64>, crit: Option<f64>, theme: &Theme) -> ColoredString {
+    match (warn, crit) {
+        (_, Some(crit)) if value >= crit => theme::paint(text, theme.error),
+        (Some(warn), _) if value >= warn => theme::paint(text, theme.warning),
+        _ => text.normal(),
+    }
+}
+
+/// Colors `text` for a filesystem that's `pct` percent full: in the warning color
+/// from RWELCOME_DISK_WARN_PCT (80 by default), and in the error color with a ⚠
+/// after it from RWELCOME_DISK_CRIT_PCT (95 by default).
+pub fn disk_usage_color(text: &str, pct: f64, theme: &Theme) -> String {
+    let warn = ext::parse_env_f64("RWELCOME_DISK_WARN_PCT", 80.0);
+    let crit = ext::parse_env_f64("RWELCOME_DISK_CRIT_PCT", 95.0);
+    let colored = threshold_color(text, pct, Some(warn), Some(crit), theme);
+    if pct >= crit {
+        format!("{colored} ⚠")
+    } else {
+        colored.to_string()
+    }
+}
+
+/// Colors `text` by how much entropy the kernel has, in `bits`: in the theme's
+/// error color below 64, where reading /dev/random may block, its warning color
+/// below 256 and green otherwise.
+pub fn entropy_color(text: &str, bits: u32, theme: &Theme) -> ColoredString {
+    match bits {
+        b if b >= 256 => text.green(),
+        b if b >= 64 => theme::paint(text, theme.warning),
+        _ => theme::paint(text, theme.error),
+    }
+}
+
+/// Colors `text` by how full something is, as a `fraction` between 0 and 1:
+/// green, then the theme's warning color from 60% and its error color from 85%,
+/// the same as `render_bar()`.
+pub fn color_usage(text: &str, fraction: f64, theme: &Theme) -> ColoredString {
+    match fraction {
+        f if f >= 0.85 => theme::paint(text, theme.error),
+        f if f >= 0.6 => theme::paint(text, theme.warning),
+        _ => text.green(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use colored::Color;
+
+    fn pairs() -> Vec<(String, String)> {
+        [("Uptime", "2h 14m"), ("CPU temp", "44°C"), ("温度", "21°C")].iter()
+            .map(|(label, value)| (label.to_string(), value.to_string()))
+            .collect()
+    }
+
+    /// "温度" is two wide characters, so it takes up four columns.
+    #[test]
+    fn labels_are_padded_to_the_longest() {
+        assert_eq!(render_label_value_pairs(&pairs(), LabelAlign::None), [
+            "Uptime: 2h 14m",
+            "CPU temp: 44°C",
+            "温度: 21°C",
+        ]);
+        assert_eq!(render_label_value_pairs(&pairs(), LabelAlign::Left), [
+            "Uptime  : 2h 14m",
+            "CPU temp: 44°C",
+            "温度    : 21°C",
+        ]);
+        assert_eq!(render_label_value_pairs(&pairs(), LabelAlign::Right), [
+            "  Uptime: 2h 14m",
+            "CPU temp: 44°C",
+            "    温度: 21°C",
+        ]);
+    }
+
+    #[test]
+    fn no_labels_render_nothing() {
+        assert!(render_label_value_pairs(&[], LabelAlign::Left).is_empty());
+    }
+
+    #[test]
+    fn right_aligned_values_end_in_a_column() {
+        // Another test turns colors on for a moment.
+        let _lock = crate::ENV_LOCK.lock().unwrap_or_else(|err| err.into_inner());
+        let theme = Theme { label: None, value: None, ..Theme::default() };
+        let lines: Vec<LabeledLine> = pairs().into_iter()
+            .map(|(label, value)| LabeledLine::new(&label, value))
+            .collect();
+        assert_eq!(render_aligned(&lines, Align::Right, LabelAlign::None, &theme), [
+            "Uptime:   2h 14m",
+            "CPU temp:   44°C",
+            "温度:       21°C",
+        ]);
+        assert_eq!(render_aligned(&lines, Align::Right, LabelAlign::Right, &theme), [
+            "  Uptime: 2h 14m",
+            "CPU temp:   44°C",
+            "    温度:   21°C",
+        ]);
+    }
+
+    #[test]
+    fn visible_len_of_ascii() {
+        assert_eq!(visible_len(""), 0);
+        assert_eq!(visible_len("anthony@desktop"), 15);
+    }
+
+    #[test]
+    fn visible_len_of_wide_and_accented_characters() {
+        assert_eq!(visible_len("josé@café"), 9);
+        assert_eq!(visible_len("👋 アンソニー@desktop"), 21);
+    }
+
+    #[test]
+    fn visible_len_skips_color_codes() {
+        assert_eq!(visible_len("\x1b[35manthony\x1b[0m\x1b[37m@\x1b[0mdesktop"), 15);
+        assert_eq!(visible_len("\x1b[1;38;2;38;139;210mUptime:\x1b[0m 2h"), 10);
+    }
+
+    #[test]
+    fn entropy_is_colored_by_how_low_it_is() {
+        let colors: Vec<_> = [0, 63, 64, 255, 256, 3847].iter()
+            .map(|bits| entropy_color("", *bits, &Theme::default()).fgcolor())
+            .collect();
+        assert_eq!(colors, [
+            Some(Color::Red), Some(Color::Red),
+            Some(Color::Yellow), Some(Color::Yellow),
+            Some(Color::Green), Some(Color::Green),
+        ]);
+    }
+
+    #[test]
+    fn usage_is_colored_from_the_theme() {
+        let theme = theme::ColorScheme::Nord.theme();
+        let colors: Vec<_> = [0.5, 0.6, 0.85].iter()
+            .map(|fraction| color_usage("", *fraction, &theme).fgcolor())
+            .collect();
+        assert_eq!(colors, [Some(Color::Green), theme.warning, theme.error]);
+        assert_eq!(entropy_color("", 63, &theme).fgcolor(), theme.error);
+    }
+
+    #[test]
+    fn disk_usage_is_colored_at_the_thresholds() {
+        let _lock = crate::ENV_LOCK.lock().unwrap_or_else(|err| err.into_inner());
+        env::remove_var("RWELCOME_DISK_WARN_PCT");
+        env::remove_var("RWELCOME_DISK_CRIT_PCT");
+        colored::control::set_override(true);
+        let theme = Theme::default();
+        let colored: Vec<String> = [79.0, 80.0, 94.0, 95.0, 96.0].iter()
+            .map(|pct| disk_usage_color("x", *pct, &theme))
+            .collect();
+        env::set_var("RWELCOME_DISK_WARN_PCT", "50");
+        env::set_var("RWELCOME_DISK_CRIT_PCT", "70");
+        let configured: Vec<String> = [49.0, 50.0, 70.0].iter()
+            .map(|pct| disk_usage_color("x", *pct, &theme))
+            .collect();
+        env::remove_var("RWELCOME_DISK_WARN_PCT");
+        env::remove_var("RWELCOME_DISK_CRIT_PCT");
+        colored::control::unset_override();
+        let (plain, yellow, red) = ("x", "\x1b[33mx\x1b[0m", "\x1b[31mx\x1b[0m ⚠");
+        assert_eq!(colored, [plain, yellow, yellow, red, red]);
+        assert_eq!(configured, [plain, yellow, red]);
+    }
+}
@@ -0,0 +1,151 @@
+use chrono::{Datelike, Days, NaiveDate};
+use std::fmt;
+use crate::environment::{ArchivedTodo, Todo};
+
+/// How many todos were added and completed over some period.
+#[derive(Default)]
+pub struct Counts {
+    pub added: usize,
+    pub done: usize,
+}
+
+/// A summary of a todo list's history, for `rwelcome edit stats`.
+pub struct TodoStats {
+    pub this_week: Counts,
+    pub this_month: Counts,
+    pub all_time: Counts,
+    /// The open todo that was added the longest ago, and how many days ago that was.
+    pub oldest_open: Option<(String, i64)>,
+}
+
+/// Computes the stats for the `active` todos and the `archived` (completed) ones.
+/// Weeks start on Monday, and `today` decides which week and month it is.
+/// Only todos with a recorded date count towards how many were added in the
+/// week and month, but every todo counts towards the total.
+pub fn compute_todo_stats(active: &[Todo], archived: &[ArchivedTodo], today: NaiveDate) -> TodoStats {
+    let week_start = today - Days::new(today.weekday().num_days_from_monday() as u64);
+    let month_start = today.with_day(1).unwrap_or(today);
+    let added_dates = active.iter()
+        .chain(archived.iter().map(|archived| &archived.todo))
+        .filter_map(|todo| todo.added);
+    let done_dates = archived.iter().map(|archived| archived.completed);
+    let count = |since: NaiveDate| Counts {
+        added: added_dates.clone().filter(|date| *date >= since).count(),
+        done: done_dates.clone().filter(|date| *date >= since).count(),
+    };
+    let oldest_open = active.iter()
+        .filter_map(|todo| todo.added.map(|added| (added, todo)))
+        .min_by_key(|(added, _)| *added)
+        .map(|(added, todo)| (todo.text.clone(), (today - added).num_days()));
+    TodoStats {
+        this_week: count(week_start),
+        this_month: count(month_start),
+        all_time: Counts { added: active.len() + archived.len(), done: archived.len() },
+        oldest_open,
+    }
+}
+
+impl fmt::Display for Counts {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} added, {} done", self.added, self.done)
+    }
+}
+
+impl fmt::Display for TodoStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "This week: {} | This month: {} | All time: {}",
+            self.this_week, self.this_month, self.all_time,
+        )?;
+        if let Some((text, days)) = &self.oldest_open {
+            let unit = if *days == 1 { "day" } else { "days" };
+            write!(f, " | Oldest open: {text} ({days} {unit})")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2026, month, day).unwrap()
+    }
+
+    fn todo(text: &str, added: Option<NaiveDate>) -> Todo {
+        Todo { text: text.to_string(), added }
+    }
+
+    fn done(text: &str, added: Option<NaiveDate>, completed: NaiveDate) -> ArchivedTodo {
+        ArchivedTodo { todo: todo(text, added), completed }
+    }
+
+    /// 2026-10-15 is a Thursday, so its week starts on Monday the 12th.
+    #[test]
+    fn weeks_start_on_monday_and_months_on_the_first() {
+        let active = [
+            todo("monday", Some(date(10, 12))),
+            todo("sunday", Some(date(10, 11))),
+            todo("first", Some(date(10, 1))),
+            todo("last month", Some(date(9, 30))),
+        ];
+        let archived = [
+            done("this week", None, date(10, 12)),
+            done("this month", None, date(10, 11)),
+            done("last month", None, date(9, 30)),
+        ];
+        let stats = compute_todo_stats(&active, &archived, date(10, 15));
+        assert_eq!((stats.this_week.added, stats.this_week.done), (1, 1));
+        assert_eq!((stats.this_month.added, stats.this_month.done), (3, 2));
+        assert_eq!((stats.all_time.added, stats.all_time.done), (7, 3));
+    }
+
+    /// On Thursday 2026-10-01 the week started in September, before the month.
+    #[test]
+    fn a_week_can_start_before_the_month() {
+        let active = [todo("monday", Some(date(9, 28))), todo("today", Some(date(10, 1)))];
+        let stats = compute_todo_stats(&active, &[], date(10, 1));
+        assert_eq!(stats.this_week.added, 2);
+        assert_eq!(stats.this_month.added, 1);
+    }
+
+    #[test]
+    fn undated_todos_only_count_all_time() {
+        let active = [todo("undated", None)];
+        let archived = [done("undated and done", None, date(10, 15))];
+        let stats = compute_todo_stats(&active, &archived, date(10, 15));
+        assert_eq!(stats.this_week.added, 0);
+        assert_eq!(stats.this_month.added, 0);
+        assert_eq!(stats.all_time.added, 2);
+        assert_eq!(stats.oldest_open, None);
+    }
+
+    #[test]
+    fn oldest_open_is_the_earliest_dated_active_todo() {
+        let active = [
+            todo("newer", Some(date(10, 10))),
+            todo("undated", None),
+            todo("oldest", Some(date(10, 3))),
+        ];
+        let archived = [done("older but done", Some(date(9, 1)), date(10, 14))];
+        let stats = compute_todo_stats(&active, &archived, date(10, 15));
+        assert_eq!(stats.oldest_open, Some(("oldest".to_string(), 12)));
+    }
+
+    #[test]
+    fn stats_are_shown_on_one_line() {
+        let active = [todo("renew passport", Some(date(10, 14)))];
+        let archived = [done("buy milk", Some(date(10, 13)), date(10, 14))];
+        let stats = compute_todo_stats(&active, &archived, date(10, 15));
+        assert_eq!(
+            stats.to_string(),
+            "This week: 2 added, 1 done | This month: 2 added, 1 done | All time: 2 added, 1 done \
+             | Oldest open: renew passport (1 day)",
+        );
+        let later = compute_todo_stats(&active, &[], date(10, 17));
+        assert!(later.to_string().ends_with("| Oldest open: renew passport (3 days)"));
+        assert!(!compute_todo_stats(&[], &[], date(10, 15)).to_string().contains("Oldest open"));
+    }
+}
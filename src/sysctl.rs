@@ -0,0 +1,194 @@
+use std::io;
+#[cfg(any(target_os = "macos", target_os = "freebsd"))]
+use std::ffi::CString;
+#[cfg(any(target_os = "macos", target_os = "freebsd"))]
+use std::ptr;
+
+/// Reads sysctls by name. `Libc` reads the system's; tests read made-up ones,
+/// so the code using them can be tested on any platform.
+pub trait Sysctl {
+    /// Reads an integer sysctl, which may be either 32 or 64 bits wide.
+    fn read_u64(&self, name: &str) -> io::Result<u64>;
+    /// Reads a string sysctl.
+    fn read_string(&self, name: &str) -> io::Result<String>;
+    /// Reads the seconds field of a `struct timeval` sysctl, such as "kern.boottime".
+    fn read_timeval_secs(&self, name: &str) -> io::Result<i64>;
+}
+
+/// The running system's sysctls, read with sysctlbyname(3).
+#[cfg(any(target_os = "macos", target_os = "freebsd"))]
+pub struct Libc;
+
+#[cfg(any(target_os = "macos", target_os = "freebsd"))]
+impl Sysctl for Libc {
+    fn read_u64(&self, name: &str) -> io::Result<u64> {
+        read_u64(name)
+    }
+
+    fn read_string(&self, name: &str) -> io::Result<String> {
+        read_string(name)
+    }
+
+    fn read_timeval_secs(&self, name: &str) -> io::Result<i64> {
+        read_timeval_secs(name)
+    }
+}
+
+/// The sysctls giving the total memory in bytes and the number of free pages.
+#[cfg(target_os = "macos")]
+pub const MEMORY_SYSCTLS: (&str, &str) = ("hw.memsize", "vm.page_free_count");
+#[cfg(target_os = "freebsd")]
+pub const MEMORY_SYSCTLS: (&str, &str) = ("hw.physmem", "vm.stats.vm.v_free_count");
+
+/// The hostname, from kern.hostname.
+pub fn hostname(sysctl: &impl Sysctl) -> io::Result<String> {
+    sysctl.read_string("kern.hostname")
+}
+
+/// How many seconds it's been since kern.boottime, as of `now` (in seconds since the epoch).
+pub fn uptime_seconds(sysctl: &impl Sysctl, now: u64) -> io::Result<u64> {
+    let boot = sysctl.read_timeval_secs("kern.boottime")?;
+    Ok(now.saturating_sub(boot.max(0) as u64))
+}
+
+/// The total and free memory in kB, from the sysctls `names` (see
+/// `MEMORY_SYSCTLS`) for the total bytes and free pages, and hw.pagesize.
+pub fn memory_kb(sysctl: &impl Sysctl, names: (&str, &str)) -> io::Result<(u64, u64)> {
+    let (total, free_pages) = names;
+    let total_bytes = sysctl.read_u64(total)?;
+    let free_bytes = sysctl.read_u64(free_pages)? * sysctl.read_u64("hw.pagesize")?;
+    Ok((total_bytes / 1024, free_bytes / 1024))
+}
+
+#[cfg(any(target_os = "macos", target_os = "freebsd"))]
+/// Reads the raw bytes of the sysctl named `name`, e.g. "kern.hostname".
+fn read_raw(name: &str) -> io::Result<Vec<u8>> {
+    let cname = CString::new(name)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+
+    // Ask for the size first, then for the value itself.
+    let mut len: libc::size_t = 0;
+    let ret = unsafe {
+        libc::sysctlbyname(cname.as_ptr(), ptr::null_mut(), &mut len, ptr::null_mut(), 0)
+    };
+    if ret != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    let mut buf = vec![0u8; len];
+    let ret = unsafe {
+        libc::sysctlbyname(
+            cname.as_ptr(),
+            buf.as_mut_ptr() as *mut libc::c_void,
+            &mut len,
+            ptr::null_mut(),
+            0,
+        )
+    };
+    if ret != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    buf.truncate(len);
+    Ok(buf)
+}
+
+#[cfg(any(target_os = "macos", target_os = "freebsd"))]
+/// Reads an integer sysctl, which may be either 32 or 64 bits wide.
+fn read_u64(name: &str) -> io::Result<u64> {
+    let buf = read_raw(name)?;
+    match buf.len() {
+        4 => Ok(u32::from_ne_bytes(buf[..4].try_into().unwrap()) as u64),
+        8 => Ok(u64::from_ne_bytes(buf[..8].try_into().unwrap())),
+        _ => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("unexpected size for sysctl {name}"),
+        )),
+    }
+}
+
+#[cfg(any(target_os = "macos", target_os = "freebsd"))]
+/// Reads a NUL-terminated string sysctl.
+fn read_string(name: &str) -> io::Result<String> {
+    let buf = read_raw(name)?;
+    let end = buf.iter().position(|b| *b == 0).unwrap_or(buf.len());
+    Ok(String::from_utf8_lossy(&buf[..end]).trim().to_string())
+}
+
+#[cfg(any(target_os = "macos", target_os = "freebsd"))]
+/// Reads the seconds field of a `struct timeval` sysctl, such as "kern.boottime".
+fn read_timeval_secs(name: &str) -> io::Result<i64> {
+    let buf = read_raw(name)?;
+    if buf.len() < std::mem::size_of::<libc::timeval>() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("unexpected size for sysctl {name}"),
+        ));
+    }
+    let tv: libc::timeval = unsafe { ptr::read_unaligned(buf.as_ptr() as *const libc::timeval) };
+    Ok(tv.tv_sec as i64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    /// Made-up sysctls, as the numbers or strings they'd be read as.
+    struct MockSysctl(HashMap<&'static str, &'static str>);
+
+    impl MockSysctl {
+        fn read(&self, name: &str) -> io::Result<&'static str> {
+            self.0.get(name).copied().ok_or_else(|| io::Error::from(io::ErrorKind::NotFound))
+        }
+    }
+
+    impl Sysctl for MockSysctl {
+        fn read_u64(&self, name: &str) -> io::Result<u64> {
+            self.read(name)?.parse().map_err(|_| io::Error::from(io::ErrorKind::InvalidData))
+        }
+
+        fn read_string(&self, name: &str) -> io::Result<String> {
+            self.read(name).map(str::to_string)
+        }
+
+        fn read_timeval_secs(&self, name: &str) -> io::Result<i64> {
+            self.read(name)?.parse().map_err(|_| io::Error::from(io::ErrorKind::InvalidData))
+        }
+    }
+
+    fn mac() -> MockSysctl {
+        MockSysctl(HashMap::from([
+            ("kern.hostname", "mbp.local"),
+            ("kern.boottime", "1760000000"),
+            ("hw.memsize", "17179869184"),
+            ("vm.page_free_count", "131072"),
+            ("hw.pagesize", "16384"),
+        ]))
+    }
+
+    #[test]
+    fn hostname_is_kern_hostname() {
+        assert_eq!(hostname(&mac()).unwrap(), "mbp.local");
+    }
+
+    #[test]
+    fn uptime_counts_from_boot() {
+        assert_eq!(uptime_seconds(&mac(), 1760000000 + 90061).unwrap(), 90061);
+        // A clock set before the boot time doesn't wrap around.
+        assert_eq!(uptime_seconds(&mac(), 1700000000).unwrap(), 0);
+    }
+
+    #[test]
+    fn memory_is_converted_to_kb() {
+        // 16 GiB in total, and 131072 free pages of 16 KiB is 2 GiB.
+        let names = ("hw.memsize", "vm.page_free_count");
+        assert_eq!(memory_kb(&mac(), names).unwrap(), (16 * 1024 * 1024, 2 * 1024 * 1024));
+    }
+
+    #[test]
+    fn missing_sysctls_are_errors() {
+        let empty = MockSysctl(HashMap::new());
+        assert!(hostname(&empty).is_err());
+        assert!(uptime_seconds(&empty, 0).is_err());
+        assert!(memory_kb(&empty, ("hw.physmem", "vm.stats.vm.v_free_count")).is_err());
+    }
+}
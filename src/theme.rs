@@ -0,0 +1,207 @@
+use chrono::NaiveDate;
+use colored::{Color, ColoredString, Colorize};
+use std::env;
+
+/// Whether colored output is wanted at all.
+/// Following https://no-color.org, any non-empty NO_COLOR disables color.
+pub fn colors_enabled() -> bool {
+    !env::var("NO_COLOR").is_ok_and(|value| !value.is_empty())
+}
+
+/// Parses a color name such as "red", "purple" or "bright blue".
+/// Underscores and hyphens are accepted in place of spaces, e.g. "bright_blue".
+pub fn parse_color(name: &str) -> Option<Color> {
+    name.trim().replace(['_', '-'], " ").parse().ok()
+}
+
+/// Reads a color from the environment variable `var`, falling back to `default`
+/// when it's unset. An unknown color name is reported on stderr.
+pub fn env_color(var: &str, default: Option<Color>) -> Option<Color> {
+    let value = match env::var(var) {
+        Ok(value) => value,
+        Err(_) => return default,
+    };
+    match parse_color(&value) {
+        Some(color) => Some(color),
+        None => {
+            eprintln!("rwelcome: warning: {var}=\"{value}\" is not a known color, using the default");
+            default
+        },
+    }
+}
+
+/// The colors rwelcome draws with. None means the terminal's default color.
+#[derive(Clone, Copy)]
+pub struct Theme {
+    pub label: Option<Color>,
+    pub value: Option<Color>,
+    pub user: Option<Color>,
+    pub at: Option<Color>,
+    pub host: Option<Color>,
+    pub warning: Option<Color>,
+    pub error: Option<Color>,
+}
+
+impl Default for Theme {
+    fn default() -> Theme {
+        Theme {
+            label: Some(Color::BrightBlue),
+            value: None,
+            user: Some(Color::Magenta),
+            at: Some(Color::White),
+            host: None,
+            warning: Some(Color::Yellow),
+            error: Some(Color::Red),
+        }
+    }
+}
+
+/// Named palettes, chosen with RWELCOME_COLOR_SCHEME.
+#[derive(Clone, Copy)]
+pub enum ColorScheme {
+    Solarized,
+    Gruvbox,
+    Nord,
+    Dracula,
+}
+
+const COLOR_SCHEMES: &[(&str, ColorScheme)] = &[
+    ("solarized", ColorScheme::Solarized),
+    ("gruvbox", ColorScheme::Gruvbox),
+    ("nord", ColorScheme::Nord),
+    ("dracula", ColorScheme::Dracula),
+];
+
+/// The names RWELCOME_COLOR_SCHEME accepts.
+pub fn list_color_schemes() -> Vec<&'static str> {
+    COLOR_SCHEMES.iter().map(|(name, _)| *name).collect()
+}
+
+/// A 24-bit color from its hex code, e.g. 0x268bd2.
+const fn rgb(hex: u32) -> Option<Color> {
+    Some(Color::TrueColor { r: (hex >> 16) as u8, g: (hex >> 8) as u8, b: hex as u8 })
+}
+
+impl ColorScheme {
+    pub fn theme(self) -> Theme {
+        // label, value, user, at, host, warning, error
+        let [label, value, user, at, host, warning, error] = match self {
+            ColorScheme::Solarized => [0x268bd2, 0x839496, 0xd33682, 0x586e75, 0x2aa198, 0xb58900, 0xdc322f],
+            ColorScheme::Gruvbox   => [0x83a598, 0xebdbb2, 0xd3869b, 0xa89984, 0x8ec07c, 0xfabd2f, 0xfb4934],
+            ColorScheme::Nord      => [0x88c0d0, 0xd8dee9, 0xb48ead, 0x4c566a, 0x81a1c1, 0xebcb8b, 0xbf616a],
+            ColorScheme::Dracula   => [0x8be9fd, 0xf8f8f2, 0xff79c6, 0x6272a4, 0xbd93f9, 0xf1fa8c, 0xff5555],
+        };
+        Theme {
+            label: rgb(label),
+            value: rgb(value),
+            user: rgb(user),
+            at: rgb(at),
+            host: rgb(host),
+            warning: rgb(warning),
+            error: rgb(error),
+        }
+    }
+}
+
+/// The theme to draw with: the RWELCOME_COLOR_SCHEME preset if set, otherwise
+/// the default colors. RWELCOME_COLOR_USER, RWELCOME_COLOR_AT and
+/// RWELCOME_COLOR_HOST still override the header colors either way.
+pub fn load_theme() -> Theme {
+    let mut theme = Theme::default();
+    if let Ok(name) = env::var("RWELCOME_COLOR_SCHEME") {
+        match COLOR_SCHEMES.iter().find(|(scheme, _)| scheme.eq_ignore_ascii_case(name.trim())) {
+            Some((_, scheme)) => theme = scheme.theme(),
+            None => eprintln!(
+                "rwelcome: warning: RWELCOME_COLOR_SCHEME=\"{name}\" is not one of {}, using the default colors",
+                list_color_schemes().join("|"),
+            ),
+        }
+    }
+    theme.user = env_color("RWELCOME_COLOR_USER", theme.user);
+    theme.at = env_color("RWELCOME_COLOR_AT", theme.at);
+    theme.host = env_color("RWELCOME_COLOR_HOST", theme.host);
+    theme
+}
+
+/// Applies `color` to `text`, or leaves it uncolored when `color` is None.
+pub fn paint(text: &str, color: Option<Color>) -> ColoredString {
+    match color {
+        Some(color) => text.color(color),
+        None => text.normal(),
+    }
+}
+
+/// Picks the color for a todo item based on its due date:
+/// red when it's overdue, yellow when it's due today, and
+/// None (the default color) when it's due later or has no due date.
+pub fn due_date_color(due: Option<NaiveDate>, now: NaiveDate) -> Option<Color> {
+    let due = due?;
+    if due < now {
+        Some(Color::Red)
+    } else if due == now {
+        Some(Color::Yellow)
+    } else {
+        None
+    }
+}
+
+/// The plain-text counterpart of `due_date_color()`, for when colors are disabled.
+pub fn due_date_label(due: Option<NaiveDate>, now: NaiveDate) -> Option<&'static str> {
+    let due = due?;
+    if due < now {
+        Some("[OVERDUE]")
+    } else if due == now {
+        Some("[DUE TODAY]")
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn day(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2026, 10, day).unwrap()
+    }
+
+    #[test]
+    fn overdue_todos_are_red() {
+        assert_eq!(due_date_color(Some(day(14)), day(15)), Some(Color::Red));
+        assert_eq!(due_date_label(Some(day(1)), day(15)), Some("[OVERDUE]"));
+    }
+
+    #[test]
+    fn todos_due_today_are_yellow() {
+        assert_eq!(due_date_color(Some(day(15)), day(15)), Some(Color::Yellow));
+        assert_eq!(due_date_label(Some(day(15)), day(15)), Some("[DUE TODAY]"));
+    }
+
+    #[test]
+    fn later_todos_keep_the_default_color() {
+        for due in [day(16), day(31)] {
+            assert_eq!(due_date_color(Some(due), day(15)), None);
+            assert_eq!(due_date_label(Some(due), day(15)), None);
+        }
+    }
+
+    #[test]
+    fn todos_without_a_due_date_keep_the_default_color() {
+        assert_eq!(due_date_color(None, day(15)), None);
+        assert_eq!(due_date_label(None, day(15)), None);
+    }
+
+    #[test]
+    fn every_color_scheme_sets_every_color() {
+        for (name, scheme) in COLOR_SCHEMES {
+            let Theme { label, value, user, at, host, warning, error } = scheme.theme();
+            let colors = [
+                ("label", label), ("value", value), ("user", user), ("at", at),
+                ("host", host), ("warning", warning), ("error", error),
+            ];
+            for (field, color) in colors {
+                assert!(color.is_some(), "{name} leaves {field} unset");
+            }
+        }
+    }
+}
@@ -1,8 +1,12 @@
-use reqwest;
+use async_trait::async_trait;
 use serde::{Serialize, Deserialize};
-use std::env;
+use std::collections::HashMap;
+use std::io;
+use std::time::Duration;
+use crate::config::{self, Config, WeatherUnits};
+use crate::ext;
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Default)]
 pub struct LocationInfo {
     pub name: String,
     pub region: String,
@@ -14,14 +18,14 @@ pub struct LocationInfo {
     pub localtime: String,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Default)]
 pub struct ConditionInfo {
     pub text: String,
     pub icon: String,
     pub code: u16,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Default)]
 pub struct CurrentWeatherInfo {
     pub last_updated_epoch: i64,
     pub last_updated: String,
@@ -46,19 +50,832 @@ pub struct CurrentWeatherInfo {
     pub uv: f64,
     pub gust_mph: f64,
     pub gust_kph: f64,
+    /// Only filled in by weatherapi.com, when asked for with `aqi=yes`.
+    #[serde(default)]
+    pub air_quality: Option<AirQualityInfo>,
+}
+
+/// Pollutant concentrations (in μg/m³) and air quality indexes from weatherapi.com.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct AirQualityInfo {
+    #[serde(default)]
+    pub pm2_5: f64,
+    #[serde(default)]
+    pub pm10: f64,
+    /// The US EPA category, from 1 (Good) to 6 (Hazardous).
+    #[serde(rename = "us-epa-index", default)]
+    pub us_epa_index: u8,
+    /// The UK DEFRA band, from 1 (Low) to 10 (Very High).
+    #[serde(rename = "gb-defra-index", default)]
+    pub gb_defra_index: u8,
+}
+
+/// The US EPA's name for an index category.
+pub fn epa_category(us_epa_index: u8) -> &'static str {
+    match us_epa_index {
+        1 => "Good",
+        2 => "Moderate",
+        3 => "Unhealthy for sensitive groups",
+        4 => "Unhealthy",
+        5 => "Very unhealthy",
+        6 => "Hazardous",
+        _ => "Unknown",
+    }
 }
 
-#[derive(Deserialize, Debug)]
+/// The US EPA category (1 to 6, see `epa_category()`) of a US AQI value,
+/// so that a number and its category always agree.
+pub fn aqi_to_epa_index(aqi: u32) -> u8 {
+    match aqi {
+        0..=50 => 1,
+        51..=100 => 2,
+        101..=150 => 3,
+        151..=200 => 4,
+        201..=300 => 5,
+        _ => 6,
+    }
+}
+
+/// Converts a PM2.5 concentration in μg/m³ to the US AQI scale (0-500),
+/// interpolating between the EPA's breakpoints.
+pub fn pm2_5_to_aqi(pm2_5: f64) -> u32 {
+    // (concentration low, concentration high, AQI low, AQI high)
+    const BREAKPOINTS: [(f64, f64, f64, f64); 6] = [
+        (0.0, 9.0, 0.0, 50.0),
+        (9.1, 35.4, 51.0, 100.0),
+        (35.5, 55.4, 101.0, 150.0),
+        (55.5, 125.4, 151.0, 200.0),
+        (125.5, 225.4, 201.0, 300.0),
+        (225.5, 325.4, 301.0, 500.0),
+    ];
+    // The EPA truncates to one decimal place first.
+    let pm2_5 = (pm2_5.max(0.0) * 10.0).trunc() / 10.0;
+    let (c_low, c_high, i_low, i_high) = BREAKPOINTS.iter()
+        .find(|(_, c_high, _, _)| pm2_5 <= *c_high)
+        .copied()
+        .unwrap_or(BREAKPOINTS[BREAKPOINTS.len() - 1]);
+    let aqi = (i_high - i_low) / (c_high - c_low) * (pm2_5.min(c_high) - c_low) + i_low;
+    aqi.round() as u32
+}
+
+#[derive(Serialize, Deserialize, Debug)]
 pub struct WeatherResponse {
     pub location: LocationInfo,
     pub current: CurrentWeatherInfo,
 }
 
-pub async fn acquire(key: String) -> reqwest::Result<WeatherResponse> {
-    let location = env::var("RWELCOME_WEATHER_LOCATION")
-                          .unwrap_or_else(|_| "Brighton".to_string());
-    let url = format!("https://api.weatherapi.com/v1/current.json?key={key}&q={location}&aqi=no");
-    let res = reqwest::get(url).await?;
-    let weather_res: WeatherResponse = res.json().await?;
-    Ok(weather_res)
+/// Built-in icons, keyed by lowercase substrings of weatherapi.com condition texts.
+/// Ordered most specific first, so that e.g. "Patchy light rain with thunder"
+/// gets the thunder icon.
+const EMOJI_ICONS: &[(&str, &str)] = &[
+    ("thunder",  "⛈️"),
+    ("blizzard", "🌨️"),
+    ("snow",     "🌨️"),
+    ("sleet",    "🌨️"),
+    ("ice",      "🌨️"),
+    ("freezing", "🌨️"),
+    ("drizzle",  "🌦️"),
+    ("shower",   "🌦️"),
+    ("rain",     "🌧️"),
+    ("fog",      "☁️"),
+    ("mist",     "☁️"),
+    ("overcast", "☁️"),
+    ("partly",   "⛅"),
+    ("cloudy",   "☁️"),
+    ("sunny",    "🌤️"),
+    ("clear",    "🌙"),
+];
+
+/// ASCII counterparts of `EMOJI_ICONS`, for terminals that don't render emoji well.
+const ASCII_ICONS: &[(&str, &str)] = &[
+    ("thunder",  "[THN]"),
+    ("blizzard", "[SNW]"),
+    ("snow",     "[SNW]"),
+    ("sleet",    "[SNW]"),
+    ("ice",      "[SNW]"),
+    ("freezing", "[SNW]"),
+    ("drizzle",  "[DRZ]"),
+    ("shower",   "[DRZ]"),
+    ("rain",     "[RAIN]"),
+    ("fog",      "[FOG]"),
+    ("mist",     "[FOG]"),
+    ("overcast", "[CLD]"),
+    ("partly",   "[PCL]"),
+    ("cloudy",   "[CLD]"),
+    ("sunny",    "[SUN]"),
+    ("clear",    "[CLR]"),
+];
+
+fn lookup_icon(table: &'static [(&str, &str)], condition: &str) -> Option<&'static str> {
+    table.iter()
+        .find(|(needle, _)| condition.contains(needle))
+        .map(|(_, icon)| *icon)
+}
+
+/// Picks an emoji for a weatherapi.com condition text, e.g. "Patchy light rain".
+pub fn condition_to_emoji(condition: &str) -> &'static str {
+    lookup_icon(EMOJI_ICONS, &condition.to_lowercase()).unwrap_or("🌥️")
+}
+
+/// Picks an icon for a weatherapi.com condition text.
+/// `icons` (from the [weather.icons] config section) is checked first; when several
+/// of its keys appear in the condition, the longest one wins. Otherwise the built-in
+/// emoji are used, or the built-in ASCII icons when RWELCOME_WEATHER_ICONS=ascii.
+pub fn condition_to_icon<'a>(condition: &str, icons: &'a HashMap<String, String>) -> &'a str {
+    let condition = condition.to_lowercase();
+    let configured = icons.iter()
+        .filter(|(needle, _)| condition.contains(&needle.to_lowercase()))
+        .max_by_key(|(needle, _)| needle.len())
+        .map(|(_, icon)| icon.as_str());
+    if let Some(icon) = configured {
+        return icon;
+    }
+    if ext::env_one_of("RWELCOME_WEATHER_ICONS", &["emoji", "ascii"], "emoji") == "ascii" {
+        return lookup_icon(ASCII_ICONS, &condition).unwrap_or("[CLD]");
+    }
+    condition_to_emoji(&condition)
+}
+
+/// Formats a location as e.g. "Brighton, East Sussex", falling back to
+/// "Brighton, United Kingdom" when there's no region, and just "Brighton" with neither.
+pub fn format_location(loc: &LocationInfo) -> String {
+    if !loc.region.trim().is_empty() {
+        format!("{}, {}", loc.name, loc.region)
+    } else if !loc.country.trim().is_empty() {
+        format!("{}, {}", loc.name, loc.country)
+    } else {
+        loc.name.clone()
+    }
+}
+
+/// The location to display: just the name, or `format_location()` with RWELCOME_WEATHER_SHOW_REGION=1.
+pub fn display_location(loc: &LocationInfo) -> String {
+    if ext::env_flag("RWELCOME_WEATHER_SHOW_REGION") {
+        format_location(loc)
+    } else {
+        loc.name.clone()
+    }
+}
+
+/// Weather values formatted with their units, e.g. "18°C" and "12 km/h".
+pub struct FormattedWeather {
+    pub temperature: String,
+    pub wind: String,
+    pub pressure: String,
+    pub precipitation: String,
+    pub visibility: String,
+}
+
+impl CurrentWeatherInfo {
+    /// Fills in the imperial figures from the metric ones, for the providers
+    /// that only give metric figures.
+    fn with_imperial(self) -> CurrentWeatherInfo {
+        CurrentWeatherInfo {
+            temp_f: self.temp_c * 9.0 / 5.0 + 32.0,
+            feelslike_f: self.feelslike_c * 9.0 / 5.0 + 32.0,
+            wind_mph: self.wind_kph / 1.609,
+            gust_mph: self.gust_kph / 1.609,
+            pressure_in: self.pressure_mb * 0.02953,
+            precip_in: self.precip_mm / 25.4,
+            vis_miles: self.vis_km / 1.609,
+            ..self
+        }
+    }
+}
+
+/// Rounds to one decimal place, so conversions don't show float noise.
+fn round1(value: f64) -> f64 {
+    (value * 10.0).round() / 10.0
+}
+
+/// Picks the fields of `response` in `units` and formats them with their unit symbols.
+pub fn format_weather(response: &WeatherResponse, units: WeatherUnits) -> FormattedWeather {
+    let current = &response.current;
+    match units {
+        WeatherUnits::Metric => FormattedWeather {
+            temperature: format!("{}°C", round1(current.temp_c)),
+            wind: format!("{} km/h", round1(current.wind_kph)),
+            pressure: format!("{} mb", round1(current.pressure_mb)),
+            precipitation: format!("{} mm", round1(current.precip_mm)),
+            visibility: format!("{} km", round1(current.vis_km)),
+        },
+        WeatherUnits::Imperial => FormattedWeather {
+            temperature: format!("{}°F", round1(current.temp_f)),
+            wind: format!("{} mph", round1(current.wind_mph)),
+            pressure: format!("{} inHg", round1(current.pressure_in)),
+            precipitation: format!("{} in", round1(current.precip_in)),
+            visibility: format!("{} mi", round1(current.vis_miles)),
+        },
+        WeatherUnits::Si => FormattedWeather {
+            temperature: format!("{} K", round1(current.temp_c + 273.15)),
+            wind: format!("{} m/s", round1(current.wind_kph / 3.6)),
+            pressure: format!("{} hPa", round1(current.pressure_mb)),
+            precipitation: format!("{} mm", round1(current.precip_mm)),
+            visibility: format!("{} km", round1(current.vis_km)),
+        },
+    }
+}
+
+/// Where to get the weather for: a place name, or a latitude and longitude.
+pub enum WeatherLocation {
+    Name(String),
+    Coordinates { lat: f64, lon: f64 },
+}
+
+impl WeatherLocation {
+    /// Parses either a place name like "Brighton", or coordinates like "lat:50.8225,lon:-0.1372".
+    pub fn parse(location: &str) -> WeatherLocation {
+        match config::parse_coordinates(location) {
+            Some((lat, lon)) => WeatherLocation::Coordinates { lat, lon },
+            None => WeatherLocation::Name(location.to_string()),
+        }
+    }
+}
+
+/// A source of current weather conditions.
+#[async_trait]
+pub trait WeatherProvider: Send + Sync {
+    async fn fetch(&self, location: &WeatherLocation) -> io::Result<WeatherResponse>;
+}
+
+fn to_io_error(err: reqwest::Error) -> io::Error {
+    io::Error::other(err)
+}
+
+/// The weatherapi.com URL for the current weather at `location`,
+/// including the air quality if `aqi` is true.
+fn weatherapi_url(key: &str, location: &WeatherLocation, aqi: bool) -> String {
+    let query = match location {
+        WeatherLocation::Name(name) => name.clone(),
+        WeatherLocation::Coordinates { lat, lon } => format!("{lat},{lon}"),
+    };
+    let aqi = if aqi { "yes" } else { "no" };
+    format!("https://api.weatherapi.com/v1/current.json?key={key}&q={query}&aqi={aqi}")
+}
+
+/// The OpenWeatherMap URL for the current weather at `location`, in metric units.
+fn openweathermap_url(key: &str, location: &WeatherLocation) -> String {
+    let query = match location {
+        WeatherLocation::Name(name) => format!("q={name}"),
+        WeatherLocation::Coordinates { lat, lon } => format!("lat={lat}&lon={lon}"),
+    };
+    format!("https://api.openweathermap.org/data/2.5/weather?{query}&appid={key}&units=metric")
+}
+
+/// https://www.weatherapi.com, whose responses rwelcome's weather types mirror.
+pub struct WeatherApiProvider {
+    pub key: String,
+    /// Whether to ask for the air quality too.
+    pub aqi: bool,
+}
+
+#[async_trait]
+impl WeatherProvider for WeatherApiProvider {
+    async fn fetch(&self, location: &WeatherLocation) -> io::Result<WeatherResponse> {
+        let res = reqwest::get(weatherapi_url(&self.key, location, self.aqi)).await.map_err(to_io_error)?;
+        let weather_res: WeatherResponse = res.json().await.map_err(to_io_error)?;
+        Ok(weather_res)
+    }
+}
+
+/// https://openweathermap.org, whose current weather is converted to a `WeatherResponse`.
+pub struct OpenWeatherMapProvider {
+    pub key: String,
+}
+
+#[derive(Deserialize)]
+struct OwmResponse {
+    name: String,
+    coord: OwmCoord,
+    sys: OwmSys,
+    main: OwmMain,
+    weather: Vec<OwmCondition>,
+    wind: OwmWind,
+    /// In metres.
+    #[serde(default)]
+    visibility: f64,
+    /// Left out when it isn't raining or snowing.
+    #[serde(default)]
+    rain: OwmPrecipitation,
+    #[serde(default)]
+    snow: OwmPrecipitation,
+}
+
+/// How much rain or snow fell, in mm.
+#[derive(Deserialize, Default)]
+struct OwmPrecipitation {
+    #[serde(rename = "1h", default)]
+    last_hour: f64,
+}
+
+#[derive(Deserialize)]
+struct OwmCoord {
+    lat: f64,
+    lon: f64,
+}
+
+#[derive(Deserialize)]
+struct OwmSys {
+    #[serde(default)]
+    country: String,
+}
+
+#[derive(Deserialize)]
+struct OwmMain {
+    temp: f64,
+    feels_like: f64,
+    pressure: f64,
+    humidity: u8,
+}
+
+#[derive(Deserialize)]
+struct OwmCondition {
+    id: u16,
+    description: String,
+    icon: String,
+}
+
+#[derive(Deserialize)]
+struct OwmWind {
+    speed: f64,
+    deg: u16,
+}
+
+impl From<OwmResponse> for WeatherResponse {
+    fn from(owm: OwmResponse) -> WeatherResponse {
+        let condition = match owm.weather.into_iter().next() {
+            Some(condition) => ConditionInfo { text: condition.description, icon: condition.icon, code: condition.id },
+            None => ConditionInfo::default(),
+        };
+        WeatherResponse {
+            location: LocationInfo {
+                name: owm.name,
+                country: owm.sys.country,
+                lat: owm.coord.lat,
+                lon: owm.coord.lon,
+                ..LocationInfo::default()
+            },
+            current: CurrentWeatherInfo {
+                temp_c: owm.main.temp,
+                feelslike_c: owm.main.feels_like,
+                pressure_mb: owm.main.pressure,
+                precip_mm: owm.rain.last_hour + owm.snow.last_hour,
+                vis_km: owm.visibility / 1000.0,
+                humidity: owm.main.humidity,
+                wind_kph: owm.wind.speed * 3.6,
+                wind_degree: owm.wind.deg,
+                condition,
+                ..CurrentWeatherInfo::default()
+            }.with_imperial(),
+        }
+    }
+}
+
+#[async_trait]
+impl WeatherProvider for OpenWeatherMapProvider {
+    async fn fetch(&self, location: &WeatherLocation) -> io::Result<WeatherResponse> {
+        let res = reqwest::get(openweathermap_url(&self.key, location)).await
+            .and_then(|res| res.error_for_status())
+            .map_err(to_io_error)?;
+        let owm_res: OwmResponse = res.json().await.map_err(to_io_error)?;
+        Ok(owm_res.into())
+    }
+}
+
+/// https://open-meteo.com, which needs no API key. It only takes coordinates,
+/// so place names are looked up with its geocoding API first.
+pub struct OpenMeteoProvider;
+
+#[derive(Deserialize)]
+struct OpenMeteoGeocoding {
+    #[serde(default)]
+    results: Vec<OpenMeteoPlace>,
+}
+
+#[derive(Deserialize)]
+struct OpenMeteoPlace {
+    name: String,
+    latitude: f64,
+    longitude: f64,
+    #[serde(default)]
+    admin1: String,
+    #[serde(default)]
+    country: String,
+}
+
+#[derive(Deserialize)]
+struct OpenMeteoResponse {
+    latitude: f64,
+    longitude: f64,
+    #[serde(default)]
+    timezone: String,
+    current: OpenMeteoCurrent,
+}
+
+#[derive(Deserialize)]
+struct OpenMeteoCurrent {
+    time: String,
+    temperature_2m: f64,
+    relative_humidity_2m: u8,
+    apparent_temperature: f64,
+    is_day: u8,
+    weather_code: u16,
+    wind_speed_10m: f64,
+    wind_direction_10m: u16,
+    /// In hPa.
+    #[serde(default)]
+    pressure_msl: f64,
+    /// In mm, over the last hour.
+    #[serde(default)]
+    precipitation: f64,
+    /// In metres.
+    #[serde(default)]
+    visibility: f64,
+}
+
+impl OpenMeteoResponse {
+    /// Converts the response to a `WeatherResponse` for `location`, which
+    /// has the place's name when it was looked up by name.
+    fn into_weather(self, location: LocationInfo) -> WeatherResponse {
+        let current = self.current;
+        WeatherResponse {
+            location: LocationInfo { lat: self.latitude, lon: self.longitude, tz_id: self.timezone, ..location },
+            current: CurrentWeatherInfo {
+                last_updated: current.time,
+                temp_c: current.temperature_2m,
+                is_day: current.is_day,
+                condition: ConditionInfo {
+                    text: wmo_code_to_text(current.weather_code).to_string(),
+                    code: current.weather_code,
+                    ..ConditionInfo::default()
+                },
+                humidity: current.relative_humidity_2m,
+                feelslike_c: current.apparent_temperature,
+                wind_kph: current.wind_speed_10m,
+                wind_degree: current.wind_direction_10m,
+                pressure_mb: current.pressure_msl,
+                precip_mm: current.precipitation,
+                vis_km: current.visibility / 1000.0,
+                ..CurrentWeatherInfo::default()
+            }.with_imperial(),
+        }
+    }
+}
+
+/// Describes a WMO weather interpretation code, as returned by Open-Meteo,
+/// using the words of weatherapi.com's condition texts so the icons match.
+pub fn wmo_code_to_text(code: u16) -> &'static str {
+    match code {
+        0 => "Clear",
+        1 | 2 => "Partly cloudy",
+        3 => "Overcast",
+        45 | 48 => "Fog",
+        51..=57 => "Drizzle",
+        61..=67 => "Rain",
+        71..=77 => "Snow",
+        80..=82 => "Rain shower",
+        85 | 86 => "Snow",
+        95..=99 => "Thunder",
+        _ => "Cloudy",
+    }
+}
+
+impl OpenMeteoProvider {
+    /// Looks up a place name, returning the best match.
+    async fn geocode(name: &str) -> io::Result<OpenMeteoPlace> {
+        let url = format!("https://geocoding-api.open-meteo.com/v1/search?name={name}&count=1");
+        let res = reqwest::get(url).await
+            .and_then(|res| res.error_for_status())
+            .map_err(to_io_error)?;
+        let geocoding: OpenMeteoGeocoding = res.json().await.map_err(to_io_error)?;
+        geocoding.results.into_iter().next().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::NotFound, format!("couldn't find a place called {name}"))
+        })
+    }
+}
+
+#[async_trait]
+impl WeatherProvider for OpenMeteoProvider {
+    async fn fetch(&self, location: &WeatherLocation) -> io::Result<WeatherResponse> {
+        let (lat, lon, place) = match location {
+            WeatherLocation::Name(name) => {
+                let place = OpenMeteoProvider::geocode(name).await?;
+                (place.latitude, place.longitude, Some(place))
+            },
+            WeatherLocation::Coordinates { lat, lon } => (*lat, *lon, None),
+        };
+        let url = format!(
+            "https://api.open-meteo.com/v1/forecast?latitude={lat}&longitude={lon}&current=temperature_2m,\
+             relative_humidity_2m,apparent_temperature,is_day,weather_code,wind_speed_10m,wind_direction_10m,\
+             pressure_msl,precipitation,visibility&timezone=auto"
+        );
+        let res = reqwest::get(url).await
+            .and_then(|res| res.error_for_status())
+            .map_err(to_io_error)?;
+        let meteo: OpenMeteoResponse = res.json().await.map_err(to_io_error)?;
+        let location = match place {
+            Some(place) => LocationInfo { name: place.name, region: place.admin1, country: place.country, ..LocationInfo::default() },
+            None => LocationInfo { name: format!("{lat:.2}, {lon:.2}"), ..LocationInfo::default() },
+        };
+        Ok(meteo.into_weather(location))
+    }
+}
+
+/// The cache file for the weather at `location` from `provider`, e.g.
+/// "weather_weatherapi_brighton.json", so each location is cached separately.
+fn cache_name(provider: &str, location: &WeatherLocation) -> String {
+    let location = match location {
+        WeatherLocation::Name(name) => name.trim().to_lowercase(),
+        WeatherLocation::Coordinates { lat, lon } => format!("{lat},{lon}"),
+    };
+    let location: String = location.chars()
+        .map(|c| if c.is_alphanumeric() || c == '-' || c == '.' { c } else { '_' })
+        .collect();
+    format!("weather_{provider}_{location}.json")
+}
+
+/// Fetches the weather at `location` from `provider` (named `provider_name`),
+/// unless it was fetched less than RWELCOME_WEATHER_CACHE_TTL_SECS seconds ago
+/// (600 by default), in which case it's read from the cache. Every location
+/// shares the one TTL.
+pub async fn fetch_cached(
+    provider: &dyn WeatherProvider,
+    provider_name: &str,
+    location: &WeatherLocation,
+) -> io::Result<WeatherResponse> {
+    let cache_name = cache_name(provider_name, location);
+    let ttl = Duration::from_secs(ext::parse_env_number("RWELCOME_WEATHER_CACHE_TTL_SECS", 600));
+    if let Some(cached) = ext::read_cache(&cache_name, ttl) {
+        if let Ok(response) = serde_json::from_str(&cached) {
+            return Ok(response);
+        }
+    }
+    let response = provider.fetch(location).await?;
+    if let Ok(json) = serde_json::to_string(&response) {
+        ext::write_cache(&cache_name, &json);
+    }
+    Ok(response)
+}
+
+/// Builds the weather provider chosen by RWELCOME_WEATHER_PROVIDER, or None
+/// when there's no API key (RWELCOME_WEATHER_API_KEY) to use it with.
+/// Open-Meteo needs no key, so it's always available.
+pub fn build_provider(config: &Config) -> Option<Box<dyn WeatherProvider>> {
+    if config.weather_provider == "openmeteo" {
+        return Some(Box::new(OpenMeteoProvider));
+    }
+    let key = config.weather_api_key.clone()?;
+    match config.weather_provider.as_str() {
+        "openweathermap" => Some(Box::new(OpenWeatherMapProvider { key })),
+        _ => Some(Box::new(WeatherApiProvider { key, aqi: ext::env_flag("RWELCOME_SHOW_WEATHER_AQI") })),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    #[test]
+    fn epa_indexes_are_named() {
+        let names: Vec<&str> = (0..=7).map(epa_category).collect();
+        assert_eq!(names, [
+            "Unknown", "Good", "Moderate", "Unhealthy for sensitive groups",
+            "Unhealthy", "Very unhealthy", "Hazardous", "Unknown",
+        ]);
+    }
+
+    #[test]
+    fn aqi_number_and_category_agree() {
+        let cases = [(0.0, 0, 1), (9.0, 50, 1), (9.1, 51, 2), (35.4, 100, 2), (35.5, 101, 3), (55.5, 151, 4), (125.5, 201, 5), (300.0, 449, 6), (900.0, 500, 6)];
+        for (pm2_5, aqi, index) in cases {
+            assert_eq!(pm2_5_to_aqi(pm2_5), aqi, "PM2.5 {pm2_5}");
+            assert_eq!(aqi_to_epa_index(aqi), index, "AQI {aqi}");
+        }
+    }
+
+    #[test]
+    fn every_known_condition_has_its_emoji() {
+        let cases = [
+            ("Sunny", "🌤️"),
+            ("Clear", "🌙"),
+            ("Partly cloudy", "⛅"),
+            ("Cloudy", "☁️"),
+            ("Overcast", "☁️"),
+            ("Mist", "☁️"),
+            ("Freezing fog", "🌨️"),
+            ("Patchy light drizzle", "🌦️"),
+            ("Light rain shower", "🌦️"),
+            ("Moderate rain", "🌧️"),
+            ("Light sleet", "🌨️"),
+            ("Patchy snow possible", "🌨️"),
+            ("Blowing snow", "🌨️"),
+            ("Blizzard", "🌨️"),
+            ("Ice pellets", "🌨️"),
+            ("Thundery outbreaks possible", "⛈️"),
+            ("Moderate or heavy rain with thunder", "⛈️"),
+        ];
+        for (condition, emoji) in cases {
+            assert_eq!(condition_to_emoji(condition), emoji, "{condition}");
+            assert_eq!(condition_to_emoji(&condition.to_uppercase()), emoji, "{condition}");
+        }
+        assert_eq!(condition_to_emoji("Volcanic ash"), "🌥️");
+    }
+
+    fn location(name: &str, region: &str, country: &str) -> LocationInfo {
+        LocationInfo { name: name.to_string(), region: region.to_string(), country: country.to_string(), ..LocationInfo::default() }
+    }
+
+    #[test]
+    fn locations_name_their_region_or_else_their_country() {
+        assert_eq!(format_location(&location("Brighton", "", "")), "Brighton");
+        assert_eq!(format_location(&location("Brighton", "East Sussex", "")), "Brighton, East Sussex");
+        assert_eq!(format_location(&location("Brighton", " ", "United Kingdom")), "Brighton, United Kingdom");
+        assert_eq!(format_location(&location("Springfield", "Illinois", "USA")), "Springfield, Illinois");
+    }
+
+    #[test]
+    fn only_the_name_is_shown_by_default() {
+        let _lock = crate::ENV_LOCK.lock().unwrap_or_else(|err| err.into_inner());
+        let brighton = location("Brighton", "East Sussex", "United Kingdom");
+        env::remove_var("RWELCOME_WEATHER_SHOW_REGION");
+        let short = display_location(&brighton);
+        env::set_var("RWELCOME_WEATHER_SHOW_REGION", "1");
+        let long = display_location(&brighton);
+        env::remove_var("RWELCOME_WEATHER_SHOW_REGION");
+        assert_eq!((short.as_str(), long.as_str()), ("Brighton", "Brighton, East Sussex"));
+    }
+
+    /// Knows the weather in one place, and nowhere else.
+    struct MockWeatherProvider {
+        place: &'static str,
+        temp_c: f64,
+    }
+
+    #[async_trait]
+    impl WeatherProvider for MockWeatherProvider {
+        async fn fetch(&self, wanted: &WeatherLocation) -> io::Result<WeatherResponse> {
+            match wanted {
+                WeatherLocation::Name(name) if name == self.place => Ok(WeatherResponse {
+                    location: location(name, "", ""),
+                    current: CurrentWeatherInfo { temp_c: self.temp_c, ..CurrentWeatherInfo::default() },
+                }),
+                _ => Err(io::Error::new(io::ErrorKind::NotFound, "no matching location found")),
+            }
+        }
+    }
+
+    #[test]
+    fn providers_can_be_swapped_out() {
+        let provider: Box<dyn WeatherProvider> = Box::new(MockWeatherProvider { place: "Brighton", temp_c: 14.2 });
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let weather = runtime.block_on(provider.fetch(&WeatherLocation::parse("Brighton"))).unwrap();
+        assert_eq!(weather.location.name, "Brighton");
+        assert_eq!(format_weather(&weather, WeatherUnits::Metric).temperature, "14.2°C");
+        let elsewhere = runtime.block_on(provider.fetch(&WeatherLocation::parse("lat:50.8,lon:-0.1")));
+        assert_eq!(elsewhere.unwrap_err().kind(), io::ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn each_location_is_cached_separately() {
+        let _lock = crate::ENV_LOCK.lock().unwrap_or_else(|err| err.into_inner());
+        let cache = tempfile::tempdir().unwrap();
+        env::set_var("XDG_CACHE_HOME", cache.path());
+        env::remove_var("RWELCOME_WEATHER_CACHE_TTL_SECS");
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let fetch = |provider: MockWeatherProvider, place: &str| {
+            runtime.block_on(fetch_cached(&provider, "mock", &WeatherLocation::parse(place))).map(|weather| weather.current.temp_c)
+        };
+
+        let brighton = fetch(MockWeatherProvider { place: "Brighton", temp_c: 14.2 }, "Brighton");
+        // Within the TTL, the cached answer is used rather than the provider's.
+        let cached = fetch(MockWeatherProvider { place: "Brighton", temp_c: 30.0 }, "Brighton");
+        let berlin = fetch(MockWeatherProvider { place: "Berlin", temp_c: 9.5 }, "Berlin");
+        env::set_var("RWELCOME_WEATHER_CACHE_TTL_SECS", "0");
+        std::thread::sleep(Duration::from_millis(10));
+        let expired = fetch(MockWeatherProvider { place: "Brighton", temp_c: 30.0 }, "Brighton");
+        env::remove_var("RWELCOME_WEATHER_CACHE_TTL_SECS");
+        env::remove_var("XDG_CACHE_HOME");
+
+        assert_eq!(brighton.unwrap(), 14.2);
+        assert_eq!(cached.unwrap(), 14.2);
+        assert_eq!(berlin.unwrap(), 9.5);
+        assert_eq!(expired.unwrap(), 30.0);
+        let cache = cache.path().join("rwelcome");
+        assert!(cache.join("weather_mock_brighton.json").exists());
+        assert!(cache.join("weather_mock_berlin.json").exists());
+        assert_eq!(
+            cache_name("openmeteo", &WeatherLocation::parse("lat:50.8225,lon:-0.1372")),
+            "weather_openmeteo_50.8225_-0.1372.json",
+        );
+    }
+
+    #[test]
+    fn providers_need_a_key_except_open_meteo() {
+        let config = |provider: &str, key: Option<&str>| Config {
+            weather_provider: provider.to_string(),
+            weather_api_key: key.map(str::to_string),
+            ..Config::default()
+        };
+        assert!(build_provider(&config("weatherapi", None)).is_none());
+        assert!(build_provider(&config("openweathermap", None)).is_none());
+        assert!(build_provider(&config("openmeteo", None)).is_some());
+        assert!(build_provider(&config("weatherapi", Some("s3cret"))).is_some());
+    }
+
+    #[test]
+    fn locations_are_names_unless_they_are_coordinates() {
+        assert!(matches!(WeatherLocation::parse("Brighton"), WeatherLocation::Name(name) if name == "Brighton"));
+        assert!(matches!(
+            WeatherLocation::parse("lat:50.8225,lon:-0.1372"),
+            WeatherLocation::Coordinates { lat: 50.8225, lon: -0.1372 },
+        ));
+        let name = WeatherLocation::parse("Brighton");
+        assert_eq!(
+            openweathermap_url("k", &name),
+            "https://api.openweathermap.org/data/2.5/weather?q=Brighton&appid=k&units=metric",
+        );
+    }
+
+    #[test]
+    fn provider_urls_take_a_name_or_coordinates() {
+        let brighton = WeatherLocation::parse("Brighton");
+        let coordinates = WeatherLocation::parse("lat:50.8225,lon:-0.1372");
+        assert_eq!(
+            weatherapi_url("k", &brighton, false),
+            "https://api.weatherapi.com/v1/current.json?key=k&q=Brighton&aqi=no",
+        );
+        assert_eq!(
+            weatherapi_url("k", &coordinates, true),
+            "https://api.weatherapi.com/v1/current.json?key=k&q=50.8225,-0.1372&aqi=yes",
+        );
+        assert_eq!(
+            openweathermap_url("k", &coordinates),
+            "https://api.openweathermap.org/data/2.5/weather?lat=50.8225&lon=-0.1372&appid=k&units=metric",
+        );
+    }
+
+    const OWM_JSON: &str = r#"{
+        "coord": {"lon": -0.1372, "lat": 50.8225},
+        "weather": [{"id": 500, "main": "Rain", "description": "light rain", "icon": "10d"}],
+        "main": {"temp": 14.2, "feels_like": 13.5, "pressure": 1012, "humidity": 82},
+        "visibility": 8000,
+        "wind": {"speed": 5.1, "deg": 240},
+        "rain": {"1h": 0.6},
+        "sys": {"country": "GB"},
+        "name": "Brighton"
+    }"#;
+
+    const OPEN_METEO_JSON: &str = r#"{
+        "latitude": 50.82,
+        "longitude": -0.14,
+        "timezone": "Europe/London",
+        "current": {
+            "time": "2026-10-15T14:30",
+            "temperature_2m": 14.2,
+            "relative_humidity_2m": 82,
+            "apparent_temperature": 13.5,
+            "is_day": 1,
+            "weather_code": 61,
+            "wind_speed_10m": 18.4,
+            "wind_direction_10m": 240,
+            "pressure_msl": 1012.0,
+            "precipitation": 0.6,
+            "visibility": 8000.0
+        }
+    }"#;
+
+    #[test]
+    fn openweathermap_fills_in_every_unit() {
+        let owm: OwmResponse = serde_json::from_str(OWM_JSON).unwrap();
+        let weather: WeatherResponse = owm.into();
+        let imperial = format_weather(&weather, WeatherUnits::Imperial);
+        assert_eq!(imperial.pressure, "29.9 inHg");
+        assert_eq!(imperial.precipitation, "0 in");
+        assert_eq!(imperial.visibility, "5 mi");
+        let metric = format_weather(&weather, WeatherUnits::Metric);
+        assert_eq!(metric.precipitation, "0.6 mm");
+        assert_eq!(metric.visibility, "8 km");
+        assert_eq!(metric.wind, "18.4 km/h");
+    }
+
+    #[test]
+    fn openweathermap_without_rain_has_none() {
+        let json = OWM_JSON.replace(r#""rain": {"1h": 0.6},"#, "");
+        let weather: WeatherResponse = serde_json::from_str::<OwmResponse>(&json).unwrap().into();
+        assert_eq!(weather.current.precip_mm, 0.0);
+    }
+
+    #[test]
+    fn open_meteo_fills_in_every_unit() {
+        let meteo: OpenMeteoResponse = serde_json::from_str(OPEN_METEO_JSON).unwrap();
+        let weather = meteo.into_weather(LocationInfo { name: "Brighton".to_string(), ..LocationInfo::default() });
+        assert_eq!(weather.location.tz_id, "Europe/London");
+        let imperial = format_weather(&weather, WeatherUnits::Imperial);
+        assert_eq!(imperial.temperature, "57.6°F");
+        assert_eq!(imperial.wind, "11.4 mph");
+        assert_eq!(imperial.pressure, "29.9 inHg");
+        assert_eq!(imperial.visibility, "5 mi");
+        assert_eq!(format_weather(&weather, WeatherUnits::Si).pressure, "1012 hPa");
+    }
 }